@@ -8,6 +8,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         "proto/instrument.proto",
         "proto/resources.proto",
         "proto/async_ops.proto",
+        "proto/recording.proto",
     ];
     let dirs = &["proto"];
 
@@ -6,6 +6,8 @@ pub mod async_ops;
 mod common;
 /// Represents interactions between the console-subscriber and a console client observing it.
 pub mod instrument;
+/// Represents the events written to a binary recording of a process.
+pub mod recording;
 /// Represents updates to the resources in an async runtime.
 pub mod resources;
 /// Represents updates to the tasks in an async runtime.
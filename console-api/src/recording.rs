@@ -0,0 +1,40 @@
+tonic::include_proto!("rs.tokio.console.recording");
+
+#[cfg(feature = "reader")]
+mod reader;
+
+#[cfg(feature = "reader")]
+pub use self::reader::{Reader, V1_SPAWN_META_ID};
+
+/// The magic bytes at the start of every binary recording.
+///
+/// These are followed by a length-delimited [`Header`], and then by a sequence
+/// of length-delimited [`Event`]s.
+pub const MAGIC: &[u8; 8] = b"TOKIOREC";
+
+/// The current version of the recording format.
+///
+/// Recordings in both formats start with a header containing the version in
+/// which they were written. The version is increased whenever the set of
+/// recorded events changes in a way that readers must be aware of. The
+/// `Reader` enabled by the `reader` feature flag can read recordings in every
+/// version up to this one.
+///
+/// Version history:
+///
+/// - 1: task spawns, enters, exits, closes and waker operations.
+/// - 2: adds callsite metadata, resources, async ops, poll ops and attribute
+///   state updates, so that a recording contains everything the console
+///   displays for a live process. Each complete recording file ends with a
+///   trailer.
+pub const VERSION: u32 = 2;
+
+/// The format in which a recording is written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// Each event is a JSON object on its own line, following a JSON header.
+    Json,
+    /// Each event is a length-delimited [`Event`] message, following the
+    /// [`MAGIC`] bytes and a length-delimited [`Header`] message.
+    Binary,
+}
@@ -0,0 +1,834 @@
+use super::{event::Event as Kind, Event, Format, MAGIC, VERSION};
+use crate as proto;
+use serde::Deserialize;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    time::SystemTime,
+};
+
+/// The callsite ID given to tasks in recordings that predate recorded
+/// metadata.
+///
+/// Version 1 recordings only contain task spans, which were always created by
+/// `tokio`'s `runtime.spawn` callsite.
+pub const V1_SPAWN_META_ID: u64 = u64::MAX;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Reads the events in a recording written by `console-subscriber`.
+///
+/// Both the JSON and binary recording formats are supported, in every version
+/// up to [`VERSION`]. Recordings compressed with gzip or zstd are supported
+/// when the `gzip` or `zstd` feature flags are enabled, respectively.
+///
+/// A `Reader` is an iterator over the [`Event`]s in a recording, which are
+/// read from the underlying file as they are needed. Events are always
+/// returned as their protobuf representation, regardless of the format of the
+/// file. Events in recordings written by older versions of
+/// `console-subscriber` are converted to the current representation, so
+/// consumers don't need to handle each version separately. In particular,
+/// version 1 recordings did not record callsites, so a `Reader` returns a
+/// [`Metadata`] event for the callsite that spawned every task (with the ID
+/// [`V1_SPAWN_META_ID`]) before the first task is spawned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use console_api::recording::{event::Event, Reader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let reader = Reader::open("console.rec")?;
+/// println!("recording format version {}", reader.version());
+///
+/// for event in reader {
+///     if let Some(Event::Spawn(task)) = event?.event {
+///         println!("spawned task {:?}", task.id);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Metadata`]: super::event::Event::Metadata
+pub struct Reader {
+    version: u32,
+    events: Events,
+    /// Whether the synthetic metadata for version 1 task spans has been
+    /// returned yet.
+    v1_metadata_sent: bool,
+    /// An event that was read, but not yet returned.
+    pending: Option<Event>,
+}
+
+enum Events {
+    Json {
+        lines: io::Lines<BufReader<Box<dyn Read + Send>>>,
+        line: u64,
+    },
+    Binary(BufReader<Box<dyn Read + Send>>),
+}
+
+#[derive(Deserialize)]
+struct Header {
+    v: u32,
+}
+
+#[derive(Deserialize)]
+enum JsonEvent {
+    Metadata {
+        id: u64,
+        at: SystemTime,
+        metadata: JsonMetadata,
+    },
+    Spawn {
+        id: u64,
+        at: SystemTime,
+        // Not present in version 1 recordings.
+        #[serde(default)]
+        metadata_id: Option<u64>,
+        fields: Vec<JsonField>,
+        #[serde(default)]
+        location: Option<JsonLocation>,
+    },
+    Resource {
+        id: u64,
+        at: SystemTime,
+        parent_id: Option<u64>,
+        metadata_id: u64,
+        concrete_type: String,
+        kind: Option<String>,
+        location: Option<JsonLocation>,
+        is_internal: bool,
+        inherit_child_attrs: bool,
+    },
+    AsyncOp {
+        id: u64,
+        at: SystemTime,
+        parent_id: Option<u64>,
+        resource_id: u64,
+        metadata_id: u64,
+        source: String,
+        inherit_child_attrs: bool,
+    },
+    PollOp {
+        at: SystemTime,
+        metadata_id: u64,
+        resource_id: u64,
+        async_op_id: u64,
+        task_id: u64,
+        op_name: String,
+        is_ready: bool,
+    },
+    StateUpdate {
+        id: u64,
+        at: SystemTime,
+        field: JsonField,
+        op: Option<JsonUpdateOp>,
+        unit: Option<String>,
+    },
+    Enter {
+        id: u64,
+        at: SystemTime,
+    },
+    Exit {
+        id: u64,
+        at: SystemTime,
+    },
+    Close {
+        id: u64,
+        at: SystemTime,
+    },
+    Waker {
+        id: u64,
+        op: JsonWakeOp,
+        at: SystemTime,
+    },
+    Trailer {
+        at: SystemTime,
+        events: u64,
+    },
+}
+
+#[derive(Deserialize)]
+struct JsonMetadata {
+    name: String,
+    target: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    level: String,
+    kind: String,
+    field_names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonField {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    name_idx: Option<u64>,
+    // Not present in recordings written before value types were recorded.
+    #[serde(default, rename = "type")]
+    ty: Option<JsonValueType>,
+    value: JsonValue,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonValue {
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    Str(String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonValueType {
+    Debug,
+    Str,
+    U64,
+    I64,
+    Bool,
+}
+
+#[derive(Deserialize)]
+struct JsonLocation {
+    file: Option<String>,
+    module_path: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+#[derive(Deserialize)]
+enum JsonUpdateOp {
+    Add,
+    Override,
+    Sub,
+}
+
+#[derive(Deserialize)]
+enum JsonWakeOp {
+    Wake { self_wake: bool },
+    WakeByRef { self_wake: bool },
+    Clone,
+    Drop,
+}
+
+// === impl Reader ===
+
+impl Reader {
+    /// Opens the recording file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the file cannot be opened, or if it does not
+    /// start with a valid header for a supported version of either recording
+    /// format.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    /// Returns a reader for the recording read from `reader`, detecting its
+    /// compression, format and version from the start of the recording.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the recording does not start with a valid
+    /// header for a supported version of either recording format, or if it is
+    /// compressed with an algorithm whose feature flag is not enabled.
+    pub fn new(reader: impl Read + Send + 'static) -> io::Result<Self> {
+        let (prefix, reader) = peek(reader, ZSTD_MAGIC.len())?;
+        let reader = decompress(&prefix, reader)?;
+
+        let (prefix, reader) = peek(reader, MAGIC.len())?;
+        let mut reader = BufReader::new(Box::new(reader) as Box<dyn Read + Send>);
+        let (version, events) = if prefix == MAGIC {
+            io::copy(&mut (&mut reader).take(MAGIC.len() as u64), &mut io::sink())?;
+            let header = read_delimited::<super::Header>(&mut reader)?
+                .ok_or_else(|| invalid_data("recording is missing a header"))?;
+            (header.version, Events::Binary(reader))
+        } else {
+            let mut lines = reader.lines();
+            let header = lines
+                .next()
+                .ok_or_else(|| invalid_data("recording is missing a header"))??;
+            let header: Header = serde_json::from_str(&header)
+                .map_err(|error| invalid_data(format!("invalid recording header: {}", error)))?;
+            (header.v, Events::Json { lines, line: 1 })
+        };
+
+        if version == 0 || version > VERSION {
+            return Err(invalid_data(format!(
+                "unsupported recording format version {} (expected 1 to {})",
+                version, VERSION
+            )));
+        }
+
+        Ok(Self {
+            version,
+            events,
+            v1_metadata_sent: false,
+            pending: None,
+        })
+    }
+
+    /// Returns the version of the recording format in which the recording
+    /// was written.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the format in which the recording was written.
+    pub fn format(&self) -> Format {
+        match self.events {
+            Events::Json { .. } => Format::Json,
+            Events::Binary(_) => Format::Binary,
+        }
+    }
+
+    fn next_event(&mut self) -> io::Result<Option<Event>> {
+        match self.events {
+            Events::Binary(ref mut reader) => read_delimited(reader),
+            Events::Json {
+                ref mut lines,
+                ref mut line,
+            } => loop {
+                let text = match lines.next() {
+                    Some(text) => text?,
+                    None => return Ok(None),
+                };
+                *line += 1;
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let event = serde_json::from_str::<JsonEvent>(&text).map_err(|error| {
+                    invalid_data(format!("invalid event on line {}: {}", line, error))
+                })?;
+                return Ok(Some(event.into()));
+            },
+        }
+    }
+}
+
+impl Iterator for Reader {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.take() {
+            return Some(Ok(event));
+        }
+
+        let event = match self.next_event() {
+            Ok(event) => event?,
+            Err(error) => return Some(Err(error)),
+        };
+
+        // Version 1 recordings don't record any callsites, so make up the
+        // callsite that spawned every task before the first task is spawned.
+        if self.version < 2 && !self.v1_metadata_sent {
+            if let Some(Kind::Spawn(_)) = event.event {
+                self.v1_metadata_sent = true;
+                let metadata = v1_spawn_metadata(event.at.clone());
+                self.pending = Some(event);
+                return Some(Ok(metadata));
+            }
+        }
+
+        Some(Ok(event))
+    }
+}
+
+impl From<JsonEvent> for Event {
+    fn from(event: JsonEvent) -> Self {
+        fn id(id: u64) -> Option<proto::Id> {
+            Some(proto::Id { id })
+        }
+
+        fn meta_id(id: u64) -> Option<proto::MetaId> {
+            Some(proto::MetaId { id })
+        }
+
+        fn span_id(id: u64) -> Option<proto::SpanId> {
+            Some(proto::SpanId { id })
+        }
+
+        let (at, event) = match event {
+            JsonEvent::Metadata { id, at, metadata } => (
+                at,
+                Kind::Metadata(proto::register_metadata::NewMetadata {
+                    id: meta_id(id),
+                    metadata: Some(metadata.into()),
+                }),
+            ),
+            JsonEvent::Spawn {
+                id: task_id,
+                at,
+                metadata_id,
+                fields,
+                location,
+            } => (
+                at,
+                Kind::Spawn(proto::tasks::Task {
+                    id: id(task_id),
+                    metadata: meta_id(metadata_id.unwrap_or(V1_SPAWN_META_ID)),
+                    kind: proto::tasks::task::Kind::Spawn as i32,
+                    fields: fields.into_iter().map(Into::into).collect(),
+                    parents: Vec::new(),
+                    location: location.map(Into::into),
+                }),
+            ),
+            JsonEvent::Resource {
+                id: resource_id,
+                at,
+                parent_id,
+                metadata_id,
+                concrete_type,
+                kind,
+                location,
+                is_internal,
+                inherit_child_attrs,
+            } => (
+                at,
+                Kind::Resource(super::Resource {
+                    resource: Some(proto::resources::Resource {
+                        id: id(resource_id),
+                        metadata: meta_id(metadata_id),
+                        concrete_type,
+                        kind: Some(resource_kind(kind)),
+                        location: location.map(Into::into),
+                        parent_resource_id: parent_id.and_then(id),
+                        is_internal,
+                    }),
+                    inherit_child_attrs,
+                }),
+            ),
+            JsonEvent::AsyncOp {
+                id: async_op_id,
+                at,
+                parent_id,
+                resource_id,
+                metadata_id,
+                source,
+                inherit_child_attrs,
+            } => (
+                at,
+                Kind::AsyncOp(super::AsyncOp {
+                    async_op: Some(proto::async_ops::AsyncOp {
+                        id: id(async_op_id),
+                        metadata: meta_id(metadata_id),
+                        source,
+                        parent_async_op_id: parent_id.and_then(id),
+                        resource_id: id(resource_id),
+                    }),
+                    inherit_child_attrs,
+                }),
+            ),
+            JsonEvent::PollOp {
+                at,
+                metadata_id,
+                resource_id,
+                async_op_id,
+                task_id,
+                op_name,
+                is_ready,
+            } => (
+                at,
+                Kind::PollOp(proto::resources::PollOp {
+                    metadata: meta_id(metadata_id),
+                    resource_id: id(resource_id),
+                    name: op_name,
+                    task_id: id(task_id),
+                    async_op_id: id(async_op_id),
+                    is_ready,
+                }),
+            ),
+            JsonEvent::StateUpdate {
+                id,
+                at,
+                field,
+                op,
+                unit,
+            } => {
+                use super::state_update::Op;
+                let op = op.map(|op| match op {
+                    JsonUpdateOp::Add => Op::Add as i32,
+                    JsonUpdateOp::Override => Op::Override as i32,
+                    JsonUpdateOp::Sub => Op::Sub as i32,
+                });
+                (
+                    at,
+                    Kind::StateUpdate(super::StateUpdate {
+                        id: span_id(id),
+                        field: Some(field.into()),
+                        op,
+                        unit,
+                    }),
+                )
+            }
+            JsonEvent::Enter { id, at } => (at, Kind::Enter(proto::SpanId { id })),
+            JsonEvent::Exit { id, at } => (at, Kind::Exit(proto::SpanId { id })),
+            JsonEvent::Close { id, at } => (at, Kind::Close(proto::SpanId { id })),
+            JsonEvent::Waker { id, op, at } => {
+                use super::waker::Op;
+                let (op, self_wake) = match op {
+                    JsonWakeOp::Wake { self_wake } => (Op::Wake, self_wake),
+                    JsonWakeOp::WakeByRef { self_wake } => (Op::WakeByRef, self_wake),
+                    JsonWakeOp::Clone => (Op::Clone, false),
+                    JsonWakeOp::Drop => (Op::Drop, false),
+                };
+                (
+                    at,
+                    Kind::Waker(super::Waker {
+                        id: span_id(id),
+                        op: op as i32,
+                        self_wake,
+                    }),
+                )
+            }
+            JsonEvent::Trailer { at, events } => (at, Kind::Trailer(super::Trailer { events })),
+        };
+
+        Event {
+            at: Some(at.into()),
+            event: Some(event),
+        }
+    }
+}
+
+impl From<JsonMetadata> for proto::Metadata {
+    fn from(meta: JsonMetadata) -> Self {
+        use proto::metadata::{Kind, Level};
+        let level = match meta.level.as_str() {
+            "ERROR" => Level::Error,
+            "WARN" => Level::Warn,
+            "INFO" => Level::Info,
+            "DEBUG" => Level::Debug,
+            _ => Level::Trace,
+        };
+        let kind = match meta.kind.as_str() {
+            "event" => Kind::Event,
+            _ => Kind::Span,
+        };
+        proto::Metadata {
+            name: meta.name,
+            target: meta.target,
+            location: Some(proto::Location {
+                file: meta.file,
+                module_path: meta.module_path,
+                line: meta.line,
+                column: None,
+            }),
+            // Binary recordings only record the module path in the location,
+            // so JSON recordings are read the same way.
+            module_path: String::new(),
+            kind: kind as i32,
+            level: level as i32,
+            field_names: meta.field_names,
+        }
+    }
+}
+
+impl From<JsonField> for proto::Field {
+    fn from(field: JsonField) -> Self {
+        use proto::field::{Name, Value};
+        let name = match (field.name, field.name_idx) {
+            (Some(name), _) => Some(Name::StrName(name)),
+            (None, Some(idx)) => Some(Name::NameIdx(idx)),
+            (None, None) => None,
+        };
+        let value = match (field.ty, field.value) {
+            (Some(JsonValueType::Debug), JsonValue::Str(v)) => Value::DebugVal(v),
+            // A non-negative `i64` is deserialized as a `u64`.
+            (Some(JsonValueType::I64), JsonValue::U64(v)) if v <= i64::MAX as u64 => {
+                Value::I64Val(v as i64)
+            }
+            // Otherwise, the value's JSON type determines its type, as in
+            // recordings that don't record value types.
+            (_, JsonValue::Bool(v)) => Value::BoolVal(v),
+            (_, JsonValue::U64(v)) => Value::U64Val(v),
+            (_, JsonValue::I64(v)) => Value::I64Val(v),
+            (_, JsonValue::Str(v)) => Value::StrVal(v),
+        };
+        proto::Field {
+            name,
+            value: Some(value),
+            metadata_id: None,
+        }
+    }
+}
+
+impl From<JsonLocation> for proto::Location {
+    fn from(location: JsonLocation) -> Self {
+        proto::Location {
+            file: location.file,
+            module_path: location.module_path,
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+/// Parses a resource kind, as it was emitted by the runtime in the resource
+/// span's `kind` field.
+fn resource_kind(kind: Option<String>) -> proto::resources::resource::Kind {
+    use proto::resources::resource::kind::{Kind, Known};
+    let kind = kind.map(|kind| match kind.as_str() {
+        "timer" => Kind::Known(Known::Timer as i32),
+        _ => match kind
+            .strip_prefix("known(")
+            .and_then(|known| known.strip_suffix(')'))
+            .and_then(|known| known.parse().ok())
+        {
+            Some(known) => Kind::Known(known),
+            None => Kind::Other(kind),
+        },
+    });
+    proto::resources::resource::Kind { kind }
+}
+
+fn v1_spawn_metadata(at: Option<prost_types::Timestamp>) -> Event {
+    let metadata = proto::Metadata {
+        name: "runtime.spawn".to_string(),
+        target: "tokio::task".to_string(),
+        module_path: String::new(),
+        location: None,
+        kind: proto::metadata::Kind::Span as i32,
+        level: proto::metadata::Level::Trace as i32,
+        field_names: Vec::new(),
+    };
+    Event {
+        at,
+        event: Some(Kind::Metadata(proto::register_metadata::NewMetadata {
+            id: Some(proto::MetaId {
+                id: V1_SPAWN_META_ID,
+            }),
+            metadata: Some(metadata),
+        })),
+    }
+}
+
+/// Wraps `reader` in a decoder for the compression algorithm identified by
+/// `prefix`, the first bytes of the recording.
+fn decompress(
+    prefix: &[u8],
+    reader: impl Read + Send + 'static,
+) -> io::Result<Box<dyn Read + Send>> {
+    if prefix.starts_with(GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        return Ok(Box::new(flate2::read::MultiGzDecoder::new(reader)));
+        #[cfg(not(feature = "gzip"))]
+        return Err(invalid_data(
+            "recording is compressed with gzip, which requires the `gzip` feature flag",
+        ));
+    }
+
+    if prefix.starts_with(ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return Ok(Box::new(zstd_crate::Decoder::new(reader)?));
+        #[cfg(not(feature = "zstd"))]
+        return Err(invalid_data(
+            "recording is compressed with zstd, which requires the `zstd` feature flag",
+        ));
+    }
+
+    Ok(Box::new(reader))
+}
+
+/// Reads the first `len` bytes of `reader`, returning them along with a
+/// reader that still yields every byte.
+fn peek<R: Read>(mut reader: R, len: usize) -> io::Result<(Vec<u8>, impl Read)> {
+    let mut prefix = Vec::with_capacity(len);
+    (&mut reader).take(len as u64).read_to_end(&mut prefix)?;
+    Ok((prefix.clone(), io::Cursor::new(prefix).chain(reader)))
+}
+
+/// Reads a length-delimited protobuf message, returning `None` at the end of
+/// the recording.
+fn read_delimited<M: prost::Message + Default>(reader: &mut impl BufRead) -> io::Result<Option<M>> {
+    if reader.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+
+    let mut len = 0u64;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        len |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift >= 64 {
+            return Err(invalid_data("invalid length prefix"));
+        }
+    }
+
+    // The length comes from the recording, which may be truncated or
+    // corrupt, so don't allocate a buffer of that size up front.
+    let mut buf = Vec::new();
+    reader.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "recording ends in the middle of an event",
+        ));
+    }
+    M::decode(&buf[..]).map(Some).map_err(invalid_data)
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    fn binary(version: u32, events: &[u8]) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        super::super::Header { version }
+            .encode_length_delimited(&mut buf)
+            .unwrap();
+        buf.extend_from_slice(events);
+        buf
+    }
+
+    fn enter(id: u64) -> Event {
+        Event {
+            at: Some(SystemTime::UNIX_EPOCH.into()),
+            event: Some(Kind::Enter(proto::SpanId { id })),
+        }
+    }
+
+    fn read_all(recording: Vec<u8>) -> io::Result<Vec<Event>> {
+        Reader::new(io::Cursor::new(recording))?.collect()
+    }
+
+    #[test]
+    fn reads_v1_json() {
+        let recording = r#"{"v":1}
+{"Spawn":{"id":1,"at":{"secs_since_epoch":1,"nanos_since_epoch":0},"fields":[{"name":"kind","value":"task"}]}}
+{"Enter":{"id":1,"at":{"secs_since_epoch":2,"nanos_since_epoch":0}}}
+"#;
+        let reader = Reader::new(io::Cursor::new(recording)).unwrap();
+        assert_eq!(reader.version(), 1);
+        assert_eq!(reader.format(), Format::Json);
+        let events = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(events.len(), 3);
+
+        // The callsite that spawned every task is made up before the spawn.
+        let spawned_at = Some(prost_types::Timestamp {
+            seconds: 1,
+            nanos: 0,
+        });
+        assert_eq!(events[0], v1_spawn_metadata(spawned_at.clone()));
+        match events[1].event {
+            Some(Kind::Spawn(ref task)) => {
+                assert_eq!(task.id, Some(proto::Id { id: 1 }));
+                assert_eq!(
+                    task.metadata,
+                    Some(proto::MetaId {
+                        id: V1_SPAWN_META_ID
+                    })
+                );
+                assert_eq!(events[1].at, spawned_at);
+            }
+            ref event => panic!("expected a spawn, got {:?}", event),
+        }
+        assert_eq!(events[2].event, Some(Kind::Enter(proto::SpanId { id: 1 })));
+    }
+
+    #[test]
+    fn only_reads_v1_metadata_once() {
+        let recording = r#"{"v":1}
+{"Spawn":{"id":1,"at":{"secs_since_epoch":1,"nanos_since_epoch":0},"fields":[]}}
+{"Spawn":{"id":2,"at":{"secs_since_epoch":2,"nanos_since_epoch":0},"fields":[]}}
+"#;
+        let events = read_all(recording.into()).unwrap();
+        let metadata = events
+            .iter()
+            .filter(|event| matches!(event.event, Some(Kind::Metadata(_))))
+            .count();
+        assert_eq!(metadata, 1);
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in [0, VERSION + 1] {
+            let json = format!("{{\"v\":{}}}\n", version);
+            let error = read_all(json.into()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+            let error = read_all(binary(version, &[])).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_headers() {
+        let error = read_all(Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = read_all(MAGIC.to_vec()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reads_binary() {
+        let mut events = Vec::new();
+        enter(1).encode_length_delimited(&mut events).unwrap();
+        enter(2).encode_length_delimited(&mut events).unwrap();
+        let reader = Reader::new(io::Cursor::new(binary(VERSION, &events))).unwrap();
+        assert_eq!(reader.version(), VERSION);
+        assert_eq!(reader.format(), Format::Binary);
+        let events = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(events, vec![enter(1), enter(2)]);
+    }
+
+    #[test]
+    fn rejects_truncated_events() {
+        let mut events = Vec::new();
+        enter(1).encode_length_delimited(&mut events).unwrap();
+        events.pop();
+        let error = read_all(binary(VERSION, &events)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // A length prefix which is cut off.
+        let error = read_all(binary(VERSION, &[0x80])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn rejects_oversized_length_prefixes() {
+        // A length prefix of `u64::MAX`, which must not be allocated up front.
+        let mut events = vec![0xff; 9];
+        events.push(0x01);
+        events.extend_from_slice(&[0; 16]);
+        let error = read_all(binary(VERSION, &events)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn rejects_overlong_length_prefixes() {
+        let mut events = vec![0x80; 10];
+        events.push(0x00);
+        let error = read_all(binary(VERSION, &events)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_invalid_json_events() {
+        let recording = "{\"v\":2}\n{\"Enter\":{}}\n";
+        let mut reader = Reader::new(io::Cursor::new(recording)).unwrap();
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 2"), "{}", error);
+    }
+}
@@ -1,4 +1,5 @@
 use console_api as proto;
+use serde::Serialize;
 use std::collections::HashMap;
 use tracing::span::Id;
 
@@ -14,7 +15,7 @@ pub(crate) struct Update {
     pub(crate) unit: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub(crate) enum UpdateOp {
     Add,
     Override,
@@ -1,7 +1,12 @@
-use super::{ConsoleLayer, Server};
+use super::{
+    record::ErrorHandler, ConsoleLayer, RecordingCompression, RecordingFormat, RecordingGuard,
+    Server,
+};
 use std::{
+    fmt, io,
     net::{SocketAddr, ToSocketAddrs},
     path::PathBuf,
+    str::FromStr,
     thread,
     time::Duration,
 };
@@ -12,6 +17,7 @@ use tracing_subscriber::{
     layer::{Layer, SubscriberExt},
     prelude::*,
     registry::LookupSpan,
+    Registry,
 };
 
 /// Builder for configuring [`ConsoleLayer`]s.
@@ -36,6 +42,34 @@ pub struct Builder {
 
     /// If and where to save a recording of the events.
     pub(super) recording_path: Option<PathBuf>,
+
+    /// The format in which to save a recording of the events.
+    pub(super) recording_format: RecordingFormat,
+
+    /// The compression to apply to recordings.
+    pub(super) recording_compression: RecordingCompression,
+
+    /// The size in bytes at which to rotate to a new recording file.
+    pub(super) recording_max_file_size: Option<u64>,
+
+    /// How long to write a recording file before rotating to a new one.
+    pub(super) recording_rotation_interval: Option<Duration>,
+
+    /// The maximum number of recording files to retain.
+    pub(super) recording_max_files: Option<usize>,
+
+    /// Called with any I/O errors that occur while recording.
+    pub(super) recording_error_handler: ErrorHandler,
+
+    /// If set, how long events are held in memory by a flight recorder,
+    /// rather than being written to disk as they are recorded.
+    pub(super) flight_recorder: Option<Duration>,
+
+    /// The maximum number of events held in memory by the flight recorder.
+    pub(super) flight_recorder_max_events: usize,
+
+    /// Whether to dump the flight recorder when the process first panics.
+    pub(super) flight_recorder_dump_on_panic: bool,
 }
 
 impl Default for Builder {
@@ -47,6 +81,15 @@ impl Default for Builder {
             retention: ConsoleLayer::DEFAULT_RETENTION,
             server_addr: SocketAddr::new(Server::DEFAULT_IP, Server::DEFAULT_PORT),
             recording_path: None,
+            recording_format: RecordingFormat::default(),
+            recording_compression: RecordingCompression::default(),
+            recording_max_file_size: None,
+            recording_rotation_interval: None,
+            recording_max_files: None,
+            recording_error_handler: ErrorHandler::default(),
+            flight_recorder: None,
+            flight_recorder_max_events: ConsoleLayer::DEFAULT_FLIGHT_RECORDER_MAX_EVENTS,
+            flight_recorder_dump_on_panic: false,
         }
     }
 }
@@ -145,19 +188,241 @@ impl Builder {
         }
     }
 
+    /// Sets the format in which recordings are written.
+    ///
+    /// This has no effect unless a [recording path] is also set.
+    ///
+    /// By default, this is [`RecordingFormat::Json`]. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_RECORD_FORMAT` [environment variable]
+    /// before falling back on that default.
+    ///
+    /// [recording path]: `Builder::recording_path`
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn recording_format(self, recording_format: RecordingFormat) -> Self {
+        Self {
+            recording_format,
+            ..self
+        }
+    }
+
+    /// Sets the compression applied to recordings.
+    ///
+    /// Compressed recordings have the compression format's extension appended
+    /// to their file names. Compression formats other than
+    /// [`RecordingCompression::None`] require the feature flag of the same
+    /// name to be enabled; if it is not, building the [`ConsoleLayer`] will
+    /// panic.
+    ///
+    /// By default, this is [`RecordingCompression::None`]. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_RECORD_COMPRESSION` [environment
+    /// variable] before falling back on that default.
+    ///
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn recording_compression(self, recording_compression: RecordingCompression) -> Self {
+        Self {
+            recording_compression,
+            ..self
+        }
+    }
+
+    /// Sets the size, in bytes, at which the recording is rotated to a new
+    /// file.
+    ///
+    /// When the recording file currently being written reaches this size, it
+    /// is renamed to `<path>.1`, any previously rotated files are renamed to
+    /// `<path>.2`, `<path>.3`, and so on, and a new file is started at the
+    /// [recording path]. Each file can be read on its own. If compression is
+    /// enabled, this is the compressed size of the file.
+    ///
+    /// By default, recordings are not rotated by size. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_RECORD_MAX_FILE_SIZE` [environment
+    /// variable] before falling back on that default.
+    ///
+    /// [recording path]: `Builder::recording_path`
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn recording_max_file_size(self, bytes: u64) -> Self {
+        Self {
+            recording_max_file_size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Sets how long a recording file is written before the recording is
+    /// rotated to a new file.
+    ///
+    /// See [`Builder::recording_max_file_size`] for details on how files are
+    /// rotated. If both a size and an interval are set, the recording is
+    /// rotated when either limit is reached.
+    ///
+    /// By default, recordings are not rotated by time. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_RECORD_ROTATION_INTERVAL` [environment
+    /// variable] before falling back on that default.
+    ///
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn recording_rotation_interval(self, interval: Duration) -> Self {
+        Self {
+            recording_rotation_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of recording files to retain, including the
+    /// file currently being written.
+    ///
+    /// When the recording is rotated, the oldest files beyond this limit are
+    /// removed.
+    ///
+    /// By default, all rotated files are retained. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_RECORD_MAX_FILES` [environment variable]
+    /// before falling back on that default.
+    ///
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn recording_max_files(self, max_files: usize) -> Self {
+        Self {
+            recording_max_files: Some(max_files),
+            ..self
+        }
+    }
+
+    /// Sets a function to call with any I/O errors that occur while writing a
+    /// recording.
+    ///
+    /// When an error occurs, the recording is stopped, and any events recorded
+    /// afterwards are discarded.
+    ///
+    /// By default, errors are printed to stderr.
+    pub fn recording_error_handler(
+        self,
+        handler: impl Fn(io::Error) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            recording_error_handler: ErrorHandler::new(handler),
+            ..self
+        }
+    }
+
+    /// Enables the flight recorder, which holds the events recorded within the
+    /// last `window` in memory, rather than writing every event to disk.
+    ///
+    /// Continuously recording a busy process can be too expensive to leave
+    /// enabled in production. Instead, the flight recorder writes its events
+    /// to a new recording file when it is dumped: when a console client sends
+    /// a `DumpRecording` request, when [`FlightRecorder::dump`] is called on the
+    /// handle returned by [`ConsoleLayer::flight_recorder`], or, if
+    /// [enabled][dump on panic], when the process panics.
+    ///
+    /// Each dump is written to the [recording path], with the time of the dump
+    /// and a counter appended to its name, in the configured [format] and [compression]. If
+    /// no recording path is set, dumps are written to
+    /// [`ConsoleLayer::DEFAULT_FLIGHT_RECORDING_PATH`] in the current
+    /// directory. A dump starts with every registered callsite and the
+    /// creation of every live task, resource, and async op, so it can be
+    /// replayed on its own, but other events older than `window`, or beyond
+    /// the [maximum number of events], are discarded.
+    ///
+    /// By default, the flight recorder is disabled. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_FLIGHT_RECORDER` [environment variable]
+    /// before falling back on that default.
+    ///
+    /// [`FlightRecorder::dump`]: crate::FlightRecorder::dump
+    /// [dump on panic]: Builder::flight_recorder_dump_on_panic
+    /// [recording path]: Builder::recording_path
+    /// [format]: Builder::recording_format
+    /// [compression]: Builder::recording_compression
+    /// [maximum number of events]: Builder::flight_recorder_max_events
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn flight_recorder(self, window: Duration) -> Self {
+        Self {
+            flight_recorder: Some(window),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of events the [flight recorder] holds in
+    /// memory.
+    ///
+    /// A busy process can record a great many events within the flight
+    /// recorder's window. Once this many events are held, the oldest are
+    /// discarded as new events are recorded, even if they are within the
+    /// window.
+    ///
+    /// By default, this is [`ConsoleLayer::DEFAULT_FLIGHT_RECORDER_MAX_EVENTS`].
+    /// Methods like [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will
+    /// take the value from the `TOKIO_CONSOLE_FLIGHT_RECORDER_MAX_EVENTS`
+    /// [environment variable] before falling back on that default.
+    ///
+    /// [flight recorder]: Builder::flight_recorder
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn flight_recorder_max_events(self, max_events: usize) -> Self {
+        Self {
+            flight_recorder_max_events: max_events,
+            ..self
+        }
+    }
+
+    /// Sets whether the [flight recorder] is dumped when the process panics.
+    ///
+    /// When enabled, a panic hook is installed which dumps the flight recorder
+    /// after running the previously installed hook. Only the first panic in
+    /// the process is dumped, as panics are often caught and repeated, such as
+    /// in tasks spawned on a Tokio runtime.
+    ///
+    /// By default, the flight recorder is not dumped on panic. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_FLIGHT_RECORDER_DUMP_ON_PANIC`
+    /// [environment variable] before falling back on that default.
+    ///
+    /// [flight recorder]: Builder::flight_recorder
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn flight_recorder_dump_on_panic(self, dump_on_panic: bool) -> Self {
+        Self {
+            flight_recorder_dump_on_panic: dump_on_panic,
+            ..self
+        }
+    }
+
     /// Completes the builder, returning a [`ConsoleLayer`] and [`Server`] task.
     pub fn build(self) -> (ConsoleLayer, Server) {
         ConsoleLayer::build(self)
     }
 
+    /// Completes the builder, returning a [`ConsoleLayer`], a [`Server`] task,
+    /// and a [`RecordingGuard`] which finishes the recording when it is
+    /// dropped.
+    ///
+    /// The recording is also finished when the `ConsoleLayer` or the `Server`
+    /// is dropped, whichever happens first. If no [recording path] is
+    /// configured, the guard does nothing.
+    ///
+    /// [recording path]: Builder::recording_path
+    pub fn build_with_guard(self) -> (ConsoleLayer, Server, RecordingGuard) {
+        let (layer, server) = self.build();
+        let guard = layer.recording_guard();
+        (layer, server, guard)
+    }
+
     /// Configures this builder from a standard set of environment variables:
     ///
-    /// | **Environment Variable**         | **Purpose**                                                  | **Default Value** |
-    /// |----------------------------------|--------------------------------------------------------------|-------------------|
-    /// | `TOKIO_CONSOLE_RETENTION`        | The duration of seconds to accumulate completed tracing data | 3600s (1h)        |
-    /// | `TOKIO_CONSOLE_BIND`             | a HOST:PORT description, such as `localhost:1234`            | `127.0.0.1:6669`  |
-    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL` | The duration to wait between sending updates to the console  | 1000ms (1s)       |
-    /// | `TOKIO_CONSOLE_RECORD_PATH`      | The file path to save a recording                            | None              |
+    /// | **Environment Variable**                      | **Purpose**                                                  | **Default Value** |
+    /// |-----------------------------------------------|--------------------------------------------------------------|-------------------|
+    /// | `TOKIO_CONSOLE_RETENTION`                     | The duration of seconds to accumulate completed tracing data | 3600s (1h)        |
+    /// | `TOKIO_CONSOLE_BIND`                          | a HOST:PORT description, such as `localhost:1234`            | `127.0.0.1:6669`  |
+    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`              | The duration to wait between sending updates to the console  | 1000ms (1s)       |
+    /// | `TOKIO_CONSOLE_RECORD_PATH`                   | The file path to save a recording                            | None              |
+    /// | `TOKIO_CONSOLE_RECORD_FORMAT`                 | The format of the recording, either `json` or `binary`       | `json`            |
+    /// | `TOKIO_CONSOLE_RECORD_COMPRESSION`            | The compression of the recording: `none`, `gzip` or `zstd`   | `none`            |
+    /// | `TOKIO_CONSOLE_RECORD_MAX_FILE_SIZE`          | The size at which to rotate the recording, such as `100MB`   | None              |
+    /// | `TOKIO_CONSOLE_RECORD_ROTATION_INTERVAL`      | The duration after which to rotate the recording             | None              |
+    /// | `TOKIO_CONSOLE_RECORD_MAX_FILES`              | The maximum number of recording files to retain              | None              |
+    /// | `TOKIO_CONSOLE_FLIGHT_RECORDER`               | How long the flight recorder holds events, such as `1m`      | None              |
+    /// | `TOKIO_CONSOLE_FLIGHT_RECORDER_MAX_EVENTS`    | The maximum number of events the flight recorder holds       | 100000            |
+    /// | `TOKIO_CONSOLE_FLIGHT_RECORDER_DUMP_ON_PANIC` | Whether to dump the flight recorder on the first panic       | `false`           |
     pub fn with_default_env(mut self) -> Self {
         if let Some(retention) = duration_from_env("TOKIO_CONSOLE_RETENTION") {
             self.retention = retention;
@@ -179,6 +444,38 @@ impl Builder {
             self.recording_path = Some(path.into());
         }
 
+        if let Some(format) = parse_from_env("TOKIO_CONSOLE_RECORD_FORMAT") {
+            self.recording_format = format;
+        }
+
+        if let Some(compression) = parse_from_env("TOKIO_CONSOLE_RECORD_COMPRESSION") {
+            self.recording_compression = compression;
+        }
+
+        if let Some(size) = bytes_from_env("TOKIO_CONSOLE_RECORD_MAX_FILE_SIZE") {
+            self.recording_max_file_size = Some(size);
+        }
+
+        if let Some(interval) = duration_from_env("TOKIO_CONSOLE_RECORD_ROTATION_INTERVAL") {
+            self.recording_rotation_interval = Some(interval);
+        }
+
+        if let Some(max_files) = parse_from_env("TOKIO_CONSOLE_RECORD_MAX_FILES") {
+            self.recording_max_files = Some(max_files);
+        }
+
+        if let Some(window) = duration_from_env("TOKIO_CONSOLE_FLIGHT_RECORDER") {
+            self.flight_recorder = Some(window);
+        }
+
+        if let Some(max_events) = parse_from_env("TOKIO_CONSOLE_FLIGHT_RECORDER_MAX_EVENTS") {
+            self.flight_recorder_max_events = max_events;
+        }
+
+        if let Some(dump_on_panic) = parse_from_env("TOKIO_CONSOLE_FLIGHT_RECORDER_DUMP_ON_PANIC") {
+            self.flight_recorder_dump_on_panic = dump_on_panic;
+        }
+
         self
     }
 
@@ -214,13 +511,21 @@ impl Builder {
     /// use cases. If you need to tune these parameters, several environmental
     /// configuration variables are available:
     ///
-    /// | **Environment Variable**            | **Purpose**                                                               | **Default Value** |
-    /// |-------------------------------------|---------------------------------------------------------------------------|-------------------|
-    /// | `TOKIO_CONSOLE_RETENTION`           | The number of seconds to accumulate completed tracing data                | 3600s (1h)        |
-    /// | `TOKIO_CONSOLE_BIND`                | A HOST:PORT description, such as `localhost:1234`                         | `127.0.0.1:6669`  |
-    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`    | The number of milliseconds to wait between sending updates to the console | 1000ms (1s)       |
-    /// | `TOKIO_CONSOLE_RECORD_PATH`         | The file path to save a recording                                         | None              |
-    /// | `RUST_LOG`                          | Configures what events are logged events. See [`Targets`] for details.    | "error"           |
+    /// | **Environment Variable**                      | **Purpose**                                                               | **Default Value** |
+    /// |-----------------------------------------------|---------------------------------------------------------------------------|-------------------|
+    /// | `TOKIO_CONSOLE_RETENTION`                     | The number of seconds to accumulate completed tracing data                | 3600s (1h)        |
+    /// | `TOKIO_CONSOLE_BIND`                          | A HOST:PORT description, such as `localhost:1234`                         | `127.0.0.1:6669`  |
+    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`              | The number of milliseconds to wait between sending updates to the console | 1000ms (1s)       |
+    /// | `TOKIO_CONSOLE_RECORD_PATH`                   | The file path to save a recording                                         | None              |
+    /// | `TOKIO_CONSOLE_RECORD_FORMAT`                 | The format of the recording, either `json` or `binary`                    | `json`            |
+    /// | `TOKIO_CONSOLE_RECORD_COMPRESSION`            | The compression of the recording: `none`, `gzip` or `zstd`                | `none`            |
+    /// | `TOKIO_CONSOLE_RECORD_MAX_FILE_SIZE`          | The size at which to rotate the recording, such as `100MB`                | None              |
+    /// | `TOKIO_CONSOLE_RECORD_ROTATION_INTERVAL`      | The duration after which to rotate the recording, such as `1h`            | None              |
+    /// | `TOKIO_CONSOLE_RECORD_MAX_FILES`              | The maximum number of recording files to retain                           | None              |
+    /// | `TOKIO_CONSOLE_FLIGHT_RECORDER`               | How long the flight recorder holds events in memory, such as `1m`         | None              |
+    /// | `TOKIO_CONSOLE_FLIGHT_RECORDER_MAX_EVENTS`    | The maximum number of events the flight recorder holds                    | 100000            |
+    /// | `TOKIO_CONSOLE_FLIGHT_RECORDER_DUMP_ON_PANIC` | Whether to dump the flight recorder on the first panic                    | `false`           |
+    /// | `RUST_LOG`                                    | Configures what events are logged events. See [`Targets`] for details.    | "error"           |
     ///
     /// # Further customization
     ///
@@ -241,23 +546,30 @@ impl Builder {
     ///
     /// [`Targets`]: https://docs.rs/tracing-subscriber/latest/tracing-subscriber/filter/struct.Targets.html
     pub fn init(self) {
-        let fmt_filter = std::env::var("RUST_LOG")
-            .ok()
-            .and_then(|rust_log| match rust_log.parse::<Targets>() {
-                Ok(targets) => Some(targets),
-                Err(e) => {
-                    eprintln!("failed to parse `RUST_LOG={:?}`: {}", rust_log, e);
-                    None
-                }
-            })
-            .unwrap_or_else(|| Targets::default().with_default(LevelFilter::ERROR));
-
-        let console_layer = self.spawn();
-
-        tracing_subscriber::registry()
-            .with(console_layer)
-            .with(tracing_subscriber::fmt::layer().with_filter(fmt_filter))
-            .init();
+        init_with_layer(self.spawn());
+    }
+
+    /// Initializes the console [tracing `Subscriber`][sub] like [`init`],
+    /// returning a [`RecordingGuard`] which finishes the recording when it is
+    /// dropped.
+    ///
+    /// Because [`init`] sets the default subscriber, the recording would
+    /// otherwise not be finished before the process exits, and its last events
+    /// may be lost. Hold on to the guard for as long as the process should be
+    /// recorded, such as until the end of `main`.
+    ///
+    /// # Panics
+    ///
+    /// * If the subscriber's background thread could not be spawned.
+    /// * If the [default `tracing` subscriber][default] has already been set.
+    ///
+    /// [`init`]: Builder::init
+    /// [default]: https://docs.rs/tracing/latest/tracing/dispatcher/index.html#setting-the-default-subscriber
+    /// [sub]: https://docs.rs/tracing/latest/tracing/trait.Subscriber.html
+    pub fn init_with_guard(self) -> RecordingGuard {
+        let (layer, guard) = self.spawn_with_guard();
+        init_with_layer(layer);
+        guard
     }
 
     /// Returns a new `tracing` [`Layer`] consisting of a [`ConsoleLayer`]
@@ -320,42 +632,42 @@ impl Builder {
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
-        fn console_filter(meta: &tracing::Metadata<'_>) -> bool {
-            // events will have *targets* beginning with "runtime"
-            if meta.is_event() {
-                return meta.target().starts_with("runtime") || meta.target().starts_with("tokio");
-            }
-
-            // spans will have *names* beginning with "runtime". for backwards
-            // compatibility with older Tokio versions, enable anything with the `tokio`
-            // target as well.
-            meta.name().starts_with("runtime.") || meta.target().starts_with("tokio")
-        }
+        spawn_layer(self.build())
+    }
 
-        let (layer, server) = self.build();
-        let filter =
-            FilterFn::new(console_filter as for<'r, 's> fn(&'r tracing::Metadata<'s>) -> bool);
-        let layer = layer.with_filter(filter);
-
-        thread::Builder::new()
-            .name("console_subscriber".into())
-            .spawn(move || {
-                let runtime = runtime::Builder::new_current_thread()
-                    .enable_io()
-                    .enable_time()
-                    .build()
-                    .expect("console subscriber runtime initialization failed");
-
-                runtime.block_on(async move {
-                    server
-                        .serve()
-                        .await
-                        .expect("console subscriber server failed")
-                });
-            })
-            .expect("console subscriber could not spawn thread");
-
-        layer
+    /// Returns a new `tracing` [`Layer`] like [`Builder::spawn`], along with a
+    /// [`RecordingGuard`] which finishes the recording when it is dropped.
+    ///
+    /// [`Layer`]: https://docs.rs/tracing-subscriber/latest/tracing_subscriber/layer/trait.Layer.html
+    ///
+    /// # Panics
+    ///
+    /// * If the subscriber's background thread could not be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tracing_subscriber::prelude::*;
+    ///
+    /// let (console_layer, _guard) = console_subscriber::ConsoleLayer::builder()
+    ///     .with_default_env()
+    ///     .spawn_with_guard();
+    ///
+    /// tracing_subscriber::registry()
+    ///     .with(console_layer)
+    ///     .with(tracing_subscriber::fmt::layer())
+    /// //  .with(...)
+    ///     .init();
+    ///
+    /// // ... run the application, holding on to `_guard` until it exits ...
+    /// ```
+    #[must_use = "a `Layer` must be added to a `tracing::Subscriber` in order to be used"]
+    pub fn spawn_with_guard<S>(self) -> (impl Layer<S>, RecordingGuard)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let (layer, server, guard) = self.build_with_guard();
+        (spawn_layer((layer, server)), guard)
     }
 }
 
@@ -391,13 +703,21 @@ impl Builder {
 /// use cases. If you need to tune these parameters, several environmental
 /// configuration variables are available:
 ///
-/// | **Environment Variable**            | **Purpose**                                                               | **Default Value** |
-/// |-------------------------------------|---------------------------------------------------------------------------|-------------------|
-/// | `TOKIO_CONSOLE_RETENTION`           | The number of seconds to accumulate completed tracing data                | 3600s (1h)        |
-/// | `TOKIO_CONSOLE_BIND`                | A HOST:PORT description, such as `localhost:1234`                         | `127.0.0.1:6669`  |
-/// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`    | The number of milliseconds to wait between sending updates to the console | 1000ms (1s)       |
-/// | `TOKIO_CONSOLE_RECORD_PATH`         | The file path to save a recording                                         | None              |
-/// | `RUST_LOG`                          | Configures what events are logged events. See [`Targets`] for details.    | "error"           |
+/// | **Environment Variable**                      | **Purpose**                                                               | **Default Value** |
+/// |-----------------------------------------------|---------------------------------------------------------------------------|-------------------|
+/// | `TOKIO_CONSOLE_RETENTION`                     | The number of seconds to accumulate completed tracing data                | 3600s (1h)        |
+/// | `TOKIO_CONSOLE_BIND`                          | A HOST:PORT description, such as `localhost:1234`                         | `127.0.0.1:6669`  |
+/// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`              | The number of milliseconds to wait between sending updates to the console | 1000ms (1s)       |
+/// | `TOKIO_CONSOLE_RECORD_PATH`                   | The file path to save a recording                                         | None              |
+/// | `TOKIO_CONSOLE_RECORD_FORMAT`                 | The format of the recording, either `json` or `binary`                    | `json`            |
+/// | `TOKIO_CONSOLE_RECORD_COMPRESSION`            | The compression of the recording: `none`, `gzip` or `zstd`                | `none`            |
+/// | `TOKIO_CONSOLE_RECORD_MAX_FILE_SIZE`          | The size at which to rotate the recording, such as `100MB`                | None              |
+/// | `TOKIO_CONSOLE_RECORD_ROTATION_INTERVAL`      | The duration after which to rotate the recording, such as `1h`            | None              |
+/// | `TOKIO_CONSOLE_RECORD_MAX_FILES`              | The maximum number of recording files to retain                           | None              |
+/// | `TOKIO_CONSOLE_FLIGHT_RECORDER`               | How long the flight recorder holds events in memory, such as `1m`         | None              |
+/// | `TOKIO_CONSOLE_FLIGHT_RECORDER_MAX_EVENTS`    | The maximum number of events the flight recorder holds                    | 100000            |
+/// | `TOKIO_CONSOLE_FLIGHT_RECORDER_DUMP_ON_PANIC` | Whether to dump the flight recorder on the first panic                    | `false`           |
+/// | `RUST_LOG`                                    | Configures what events are logged events. See [`Targets`] for details.    | "error"           |
 ///
 /// # Further customization
 ///
@@ -492,6 +812,64 @@ where
     ConsoleLayer::builder().with_default_env().spawn::<S>()
 }
 
+fn init_with_layer(console_layer: impl Layer<Registry> + Send + Sync + 'static) {
+    let fmt_filter = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|rust_log| match rust_log.parse::<Targets>() {
+            Ok(targets) => Some(targets),
+            Err(e) => {
+                eprintln!("failed to parse `RUST_LOG={:?}`: {}", rust_log, e);
+                None
+            }
+        })
+        .unwrap_or_else(|| Targets::default().with_default(LevelFilter::ERROR));
+
+    tracing_subscriber::registry()
+        .with(console_layer)
+        .with(tracing_subscriber::fmt::layer().with_filter(fmt_filter))
+        .init();
+}
+
+fn spawn_layer<S>((layer, server): (ConsoleLayer, Server)) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn console_filter(meta: &tracing::Metadata<'_>) -> bool {
+        // events will have *targets* beginning with "runtime"
+        if meta.is_event() {
+            return meta.target().starts_with("runtime") || meta.target().starts_with("tokio");
+        }
+
+        // spans will have *names* beginning with "runtime". for backwards
+        // compatibility with older Tokio versions, enable anything with the `tokio`
+        // target as well.
+        meta.name().starts_with("runtime.") || meta.target().starts_with("tokio")
+    }
+
+    let filter = FilterFn::new(console_filter as for<'r, 's> fn(&'r tracing::Metadata<'s>) -> bool);
+    let layer = layer.with_filter(filter);
+
+    thread::Builder::new()
+        .name("console_subscriber".into())
+        .spawn(move || {
+            let runtime = runtime::Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .build()
+                .expect("console subscriber runtime initialization failed");
+
+            runtime.block_on(async move {
+                server
+                    .serve()
+                    .await
+                    .expect("console subscriber server failed")
+            });
+        })
+        .expect("console subscriber could not spawn thread");
+
+    layer
+}
+
 fn duration_from_env(var_name: &str) -> Option<Duration> {
     let var = std::env::var(var_name).ok()?;
     match var.parse::<humantime::Duration>() {
@@ -502,3 +880,41 @@ fn duration_from_env(var_name: &str) -> Option<Duration> {
         ),
     }
 }
+
+fn parse_from_env<T>(var_name: &str) -> Option<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let var = std::env::var(var_name).ok()?;
+    match var.parse::<T>() {
+        Ok(val) => Some(val),
+        Err(e) => panic!("failed to parse `{}={:?}`: {}", var_name, var, e),
+    }
+}
+
+/// Parses a size in bytes, such as `4096`, `512KB` or `100MB`. Units are
+/// powers of 1024.
+fn bytes_from_env(var_name: &str) -> Option<u64> {
+    let var = std::env::var(var_name).ok()?;
+    let trimmed = var.trim();
+    let (digits, unit) = trimmed.split_at(
+        trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len()),
+    );
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => Some(1),
+        "K" | "KB" | "KIB" => Some(1 << 10),
+        "M" | "MB" | "MIB" => Some(1 << 20),
+        "G" | "GB" | "GIB" => Some(1 << 30),
+        _ => None,
+    };
+    match (digits.parse::<u64>(), multiplier) {
+        (Ok(n), Some(multiplier)) => Some(n.saturating_mul(multiplier)),
+        _ => panic!(
+            "failed to parse a size in bytes from `{}={:?}`: expected a number with an optional unit (B, KB, MB or GB)",
+            var_name, var
+        ),
+    }
+}
@@ -6,6 +6,7 @@ use std::{
     cell::RefCell,
     fmt,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
@@ -43,6 +44,7 @@ use stack::SpanStack;
 use visitors::{AsyncOpVisitor, ResourceVisitor, ResourceVisitorResult, TaskVisitor, WakerVisitor};
 
 pub use builder::{init, spawn};
+pub use record::{FlightRecorder, RecordingCompression, RecordingFormat, RecordingGuard};
 
 use crate::visitors::{PollOpVisitor, StateUpdateVisitor};
 
@@ -128,6 +130,10 @@ pub struct Server {
     addr: SocketAddr,
     aggregator: Option<Aggregator>,
     client_buffer: usize,
+    /// Finishes the recording, if there is one, when the server is dropped.
+    _recording: RecordingGuard,
+    /// Dumped when a client sends a `DumpRecording` request.
+    flight_recorder: FlightRecorder,
 }
 
 pub(crate) trait ToProto {
@@ -241,6 +247,31 @@ impl ConsoleLayer {
         Builder::default()
     }
 
+    /// Returns a [`RecordingGuard`] which finishes this layer's recording when
+    /// it is dropped.
+    ///
+    /// If this layer is not recording, the returned guard does nothing.
+    pub fn recording_guard(&self) -> RecordingGuard {
+        self.recorder
+            .as_ref()
+            .map(Recorder::guard)
+            .unwrap_or_default()
+    }
+
+    /// Returns a handle to this layer's [flight recorder], which can be used
+    /// to write its recent events to a file.
+    ///
+    /// If this layer has no flight recorder, the returned handle cannot be
+    /// dumped.
+    ///
+    /// [flight recorder]: Builder::flight_recorder
+    pub fn flight_recorder(&self) -> FlightRecorder {
+        self.recorder
+            .as_ref()
+            .map(Recorder::flight_recorder)
+            .unwrap_or_default()
+    }
+
     fn build(config: Builder) -> (Self, Server) {
         // The `cfg` value *appears* to be a constant to clippy, but it changes
         // depending on the build-time configuration...
@@ -256,6 +287,14 @@ impl ConsoleLayer {
             ?config.retention,
             ?config.server_addr,
             ?config.recording_path,
+            ?config.recording_format,
+            ?config.recording_compression,
+            ?config.recording_max_file_size,
+            ?config.recording_rotation_interval,
+            ?config.recording_max_files,
+            ?config.flight_recorder,
+            config.flight_recorder_max_events,
+            config.flight_recorder_dump_on_panic,
             "configured console subscriber"
         );
 
@@ -266,15 +305,36 @@ impl ConsoleLayer {
         // Conservatively, start to trigger a flush when half the channel is full.
         // This tries to reduce the chance of losing events to a full channel.
         let flush_under_capacity = config.event_buffer_capacity / 2;
-        let recorder = config
-            .recording_path
-            .as_ref()
-            .map(|path| Recorder::new(path).expect("creating recorder"));
+        let recording_path = match (&config.recording_path, config.flight_recorder) {
+            (Some(path), _) => Some(path.clone()),
+            (None, Some(_)) => Some(PathBuf::from(Self::DEFAULT_FLIGHT_RECORDING_PATH)),
+            (None, None) => None,
+        };
+        let recorder = recording_path.map(|path| {
+            Recorder::new(record::Config {
+                path,
+                format: config.recording_format,
+                compression: config.recording_compression,
+                max_file_size: config.recording_max_file_size,
+                rotation_interval: config.recording_rotation_interval,
+                max_files: config.recording_max_files,
+                flight_recorder: config.flight_recorder,
+                flight_recorder_max_events: config.flight_recorder_max_events,
+                dump_on_panic: config.flight_recorder_dump_on_panic,
+                on_error: config.recording_error_handler.clone(),
+            })
+            .expect("creating recorder")
+        });
         let server = Server {
             aggregator: Some(aggregator),
             addr: config.server_addr,
             subscribe,
             client_buffer: config.client_buffer_capacity,
+            _recording: recorder.as_ref().map(Recorder::guard).unwrap_or_default(),
+            flight_recorder: recorder
+                .as_ref()
+                .map(Recorder::flight_recorder)
+                .unwrap_or_default(),
         };
         let layer = Self {
             current_spans: ThreadLocal::new(),
@@ -337,6 +397,21 @@ impl ConsoleLayer {
     /// [environment variable]: `Builder::with_default_env`
     pub const DEFAULT_RETENTION: Duration = Duration::from_secs(60 * 60);
 
+    /// The path to which a [flight recorder] is dumped if no [recording path]
+    /// is set.
+    ///
+    /// [flight recorder]: Builder::flight_recorder
+    /// [recording path]: Builder::recording_path
+    pub const DEFAULT_FLIGHT_RECORDING_PATH: &'static str = "console-flight-recording";
+
+    /// By default, a [flight recorder] holds at most this many events in
+    /// memory.
+    ///
+    /// See also [`Builder::flight_recorder_max_events`].
+    ///
+    /// [flight recorder]: Builder::flight_recorder
+    pub const DEFAULT_FLIGHT_RECORDER_MAX_EVENTS: usize = 100_000;
+
     fn is_spawn(&self, meta: &'static Metadata<'static>) -> bool {
         self.spawn_callsites.contains(meta)
     }
@@ -469,6 +544,13 @@ impl ConsoleLayer {
         };
 
         stats.update_attribute(id, &update);
+        self.record(|| record::Event::StateUpdate {
+            id: id.into_u64(),
+            at: SystemTime::now(),
+            field: record::SerializeField(update.field.clone()),
+            op: update.op.clone(),
+            unit: update.unit.clone(),
+        });
 
         if let Some(parent) = stats.parent_id.as_ref().and_then(|parent| ctx.span(parent)) {
             let exts = parent.extensions();
@@ -522,6 +604,11 @@ where
             (_, _) => &self.shared.dropped_tasks,
         };
 
+        self.record(|| record::Event::Metadata {
+            id: proto::MetaId::from(meta).id,
+            at: SystemTime::now(),
+            metadata: record::SerializeMetadata(meta),
+        });
         self.send_metadata(dropped, Event::Metadata(meta));
         subscriber::Interest::always()
     }
@@ -536,7 +623,9 @@ where
             self.record(|| record::Event::Spawn {
                 id: id.into_u64(),
                 at,
+                metadata_id: proto::MetaId::from(metadata).id,
                 fields: record::SerializeFields(fields.clone()),
+                location: location.clone().map(record::SerializeLocation),
             });
             if let Some(stats) = self.send_stats(&self.shared.dropped_tasks, move || {
                 let stats = Arc::new(stats::TaskStats::new(at));
@@ -569,6 +658,17 @@ where
                 let parent_id = self.current_spans.get().and_then(|stack| {
                     self.first_entered(&stack.borrow(), |id| self.is_id_resource(id, &ctx))
                 });
+                self.record(|| record::Event::Resource {
+                    id: id.into_u64(),
+                    at,
+                    parent_id: parent_id.as_ref().map(Id::into_u64),
+                    metadata_id: proto::MetaId::from(metadata).id,
+                    concrete_type: concrete_type.clone(),
+                    kind: record::SerializeKind(kind.clone()),
+                    location: location.clone().map(record::SerializeLocation),
+                    is_internal,
+                    inherit_child_attrs,
+                });
                 if let Some(stats) = self.send_stats(&self.shared.dropped_resources, move || {
                     let stats = Arc::new(stats::ResourceStats::new(
                         at,
@@ -607,6 +707,15 @@ where
                 });
 
                 if let Some(resource_id) = resource_id {
+                    self.record(|| record::Event::AsyncOp {
+                        id: id.into_u64(),
+                        at,
+                        parent_id: parent_id.as_ref().map(Id::into_u64),
+                        resource_id: resource_id.into_u64(),
+                        metadata_id: proto::MetaId::from(metadata).id,
+                        source: source.clone(),
+                        inherit_child_attrs,
+                    });
                     if let Some(stats) =
                         self.send_stats(&self.shared.dropped_async_ops, move || {
                             let stats = Arc::new(stats::AsyncOpStats::new(
@@ -693,6 +802,16 @@ where
                             }
                         }
 
+                        self.record(|| record::Event::PollOp {
+                            at: SystemTime::now(),
+                            metadata_id: proto::MetaId::from(metadata).id,
+                            resource_id: resource_id.into_u64(),
+                            async_op_id: async_op_id.into_u64(),
+                            task_id: task_id.into_u64(),
+                            op_name: op_name.clone(),
+                            is_ready,
+                        });
+
                         self.send_stats(&self.shared.dropped_async_ops, || {
                             let event = Event::PollOp {
                                 metadata,
@@ -704,8 +823,6 @@ where
                             };
                             (event, ())
                         });
-
-                        // TODO: JSON recorder doesn't care about poll ops.
                     }
                 }
             }
@@ -769,7 +886,12 @@ where
         if let Some(span) = cx.span(id) {
             if let Some(now) = update(&span, None) {
                 if let Some(parent) = span.parent() {
-                    update(&parent, Some(now));
+                    if update(&parent, Some(now)).is_some() {
+                        self.record(|| record::Event::Enter {
+                            id: parent.id().into_u64(),
+                            at: now,
+                        });
+                    }
                 }
                 self.current_spans
                     .get_or_default()
@@ -813,7 +935,12 @@ where
         if let Some(span) = cx.span(id) {
             if let Some(now) = update(&span, None) {
                 if let Some(parent) = span.parent() {
-                    update(&parent, Some(now));
+                    if update(&parent, Some(now)).is_some() {
+                        self.record(|| record::Event::Exit {
+                            id: parent.id().into_u64(),
+                            at: now,
+                        });
+                    }
                 }
                 self.current_spans.get_or_default().borrow_mut().pop(id);
 
@@ -1013,6 +1140,34 @@ impl proto::instrument::instrument_server::Instrument for Server {
         })?;
         Ok(tonic::Response::new(proto::instrument::ResumeResponse {}))
     }
+
+    async fn dump_recording(
+        &self,
+        _req: tonic::Request<proto::instrument::DumpRecordingRequest>,
+    ) -> Result<tonic::Response<proto::instrument::DumpRecordingResponse>, tonic::Status> {
+        if !self.flight_recorder.is_enabled() {
+            return Err(tonic::Status::failed_precondition(
+                "no flight recorder is configured",
+            ));
+        }
+
+        // Dumping writes a file, and waits for the recorder thread to do so.
+        let flight_recorder = self.flight_recorder.clone();
+        let path = tokio::task::spawn_blocking(move || flight_recorder.dump())
+            .await
+            .map_err(|_| tonic::Status::internal("flight recorder dump panicked"))?
+            .map_err(|e| {
+                tracing::warn!(error = %e, "failed to dump flight recorder");
+                tonic::Status::internal(format!("failed to dump flight recorder: {}", e))
+            })?;
+
+        tracing::debug!(?path, "dumped flight recorder");
+        Ok(tonic::Response::new(
+            proto::instrument::DumpRecordingResponse {
+                path: path.display().to_string(),
+            },
+        ))
+    }
 }
 
 impl WakeOp {
@@ -0,0 +1,370 @@
+use super::{Config, Event, RecordingCompression};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::{Instant, SystemTime},
+};
+
+/// Writes recorded events to a sequence of files, rotating to a new file when
+/// the current one grows too large or too old.
+///
+/// The file currently being written is always at the configured path. When it
+/// is rotated, it is renamed to `<path>.1`, the previous `<path>.1` is renamed
+/// to `<path>.2`, and so on. If compression is enabled, the compression
+/// format's extension is appended to each file name.
+///
+/// Every file starts with a header, followed by the events needed to make
+/// sense of the rest of the file on its own: all registered callsites, and the
+/// creation of every task, resource, and async op that is still live. Once a
+/// file is complete, it ends with a trailer.
+pub(super) struct RecordingFile {
+    config: Config,
+    sink: Sink,
+    opened_at: Instant,
+    /// The number of events written to the current file.
+    events: u64,
+    /// The encoded events which are written to the start of each new file.
+    preamble: Preamble<Vec<u8>>,
+}
+
+/// The events needed to make sense of the events recorded after them: all
+/// registered callsites, and the creation of every span that is still live.
+pub(super) struct Preamble<T> {
+    metadata: Vec<T>,
+    /// The creation events of live spans, in the order in which they were
+    /// recorded, so that parents are always written before their children.
+    live: BTreeMap<u64, T>,
+    /// Maps span IDs to their index in `live`.
+    live_ids: HashMap<u64, u64>,
+    next_idx: u64,
+}
+
+/// How an event changes the [`Preamble`] for the events recorded after it.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Lifecycle {
+    /// A callsite was registered.
+    Metadata,
+    /// The span with this ID was created.
+    Create(u64),
+    /// The span with this ID was closed.
+    Close(u64),
+    /// The event does not change the preamble.
+    None,
+}
+
+struct Sink {
+    writer: BufWriter<Encoder>,
+}
+
+enum Encoder {
+    Plain(Counting),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<Counting>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd_crate::Encoder<'static, Counting>),
+}
+
+/// Counts the number of bytes written to the underlying file, after any
+/// compression.
+struct Counting {
+    file: File,
+    bytes: u64,
+}
+
+// === impl RecordingFile ===
+
+impl RecordingFile {
+    pub(super) fn create(config: Config) -> io::Result<Self> {
+        let sink = Sink::create(&config, &file_name(&config, 0))?;
+        let mut file = Self {
+            config,
+            sink,
+            opened_at: Instant::now(),
+            events: 0,
+            preamble: Preamble::default(),
+        };
+        file.write_header()?;
+        Ok(file)
+    }
+
+    pub(super) fn write_event(&mut self, event: &Event) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.config.format.write_event(&mut buf, event)?;
+        self.sink.writer.write_all(&buf)?;
+        self.events += 1;
+        self.preamble.update(Lifecycle::of(event), buf);
+        Ok(())
+    }
+
+    /// Returns the path of the file currently being written.
+    pub(super) fn path(&self) -> PathBuf {
+        self.file_name(0)
+    }
+
+    /// Completes the current file with a trailer and syncs it to disk.
+    pub(super) fn finish(mut self) -> io::Result<()> {
+        self.write_trailer()?;
+        self.sink.finish()?.sync_all()
+    }
+
+    /// Flushes any buffered events, rotating to a new file if the current
+    /// file has exceeded its size or age limits.
+    pub(super) fn flush(&mut self) -> io::Result<()> {
+        self.sink.writer.flush()?;
+        if self.should_rotate() {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn should_rotate(&self) -> bool {
+        let too_large = self
+            .config
+            .max_file_size
+            .map(|max| self.sink.bytes_written() >= max)
+            .unwrap_or(false);
+        let too_old = self
+            .config
+            .rotation_interval
+            .map(|interval| self.opened_at.elapsed() >= interval)
+            .unwrap_or(false);
+        too_large || too_old
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        tracing::debug!(path = ?self.config.path, "rotating recording file");
+        // Finish the current file before renaming it, so that it is complete
+        // when it is read or removed.
+        let next = self.tmp_file_name();
+        self.write_trailer()?;
+        let sink = Sink::create(&self.config, &next)?;
+        std::mem::replace(&mut self.sink, sink)
+            .finish()?
+            .sync_all()?;
+
+        // Remove any files that would exceed the retention limit after
+        // rotating...
+        if let Some(max) = self.config.max_files {
+            let mut idx = max.max(1) - 1;
+            while self.file_name(idx).exists() {
+                fs::remove_file(self.file_name(idx))?;
+                idx += 1;
+            }
+        }
+
+        // ...and shift the rest along by one.
+        let mut free = 0;
+        while self.file_name(free).exists() {
+            free += 1;
+        }
+        for idx in (0..free).rev() {
+            fs::rename(self.file_name(idx), self.file_name(idx + 1))?;
+        }
+        fs::rename(next, self.file_name(0))?;
+
+        self.opened_at = Instant::now();
+        self.write_header()?;
+        self.events = 0;
+        for encoded in self.preamble.iter() {
+            self.sink.writer.write_all(encoded)?;
+            self.events += 1;
+        }
+        self.sink.writer.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.config.format.write_header(&mut self.sink.writer)
+    }
+
+    fn write_trailer(&mut self) -> io::Result<()> {
+        let trailer = Event::Trailer {
+            at: SystemTime::now(),
+            events: self.events,
+        };
+        self.config
+            .format
+            .write_event(&mut self.sink.writer, &trailer)
+    }
+
+    /// Returns the name of the file at `idx` in the rotation, where the file
+    /// currently being written is at index 0.
+    fn file_name(&self, idx: usize) -> PathBuf {
+        file_name(&self.config, idx)
+    }
+
+    /// Returns the name of the file that is written while rotating, before it
+    /// is moved to index 0.
+    fn tmp_file_name(&self) -> PathBuf {
+        let mut name = self.file_name(0).into_os_string();
+        name.push(".tmp");
+        name.into()
+    }
+}
+
+fn file_name(config: &Config, idx: usize) -> PathBuf {
+    let mut name = config.path.clone().into_os_string();
+    if idx > 0 {
+        name.push(format!(".{}", idx));
+    }
+    if let Some(extension) = config.compression.extension() {
+        name.push(extension);
+    }
+    name.into()
+}
+
+// === impl Preamble ===
+
+impl<T> Preamble<T> {
+    pub(super) fn update(&mut self, lifecycle: Lifecycle, event: T) {
+        match lifecycle {
+            Lifecycle::Metadata => self.metadata.push(event),
+            Lifecycle::Create(id) => {
+                let idx = self.next_idx;
+                self.next_idx += 1;
+                self.live.insert(idx, event);
+                if let Some(prev) = self.live_ids.insert(id, idx) {
+                    // span IDs may be reused once the span has closed.
+                    self.live.remove(&prev);
+                }
+            }
+            Lifecycle::Close(id) => {
+                if let Some(idx) = self.live_ids.remove(&id) {
+                    self.live.remove(&idx);
+                }
+            }
+            Lifecycle::None => {}
+        }
+    }
+
+    /// Returns the events in the preamble, in the order in which they must be
+    /// written.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.metadata.iter().chain(self.live.values())
+    }
+}
+
+impl<T> Default for Preamble<T> {
+    fn default() -> Self {
+        Self {
+            metadata: Vec::new(),
+            live: BTreeMap::new(),
+            live_ids: HashMap::new(),
+            next_idx: 0,
+        }
+    }
+}
+
+// === impl Lifecycle ===
+
+impl Lifecycle {
+    pub(super) fn of(event: &Event) -> Self {
+        match *event {
+            Event::Metadata { .. } => Lifecycle::Metadata,
+            Event::Spawn { id, .. } | Event::Resource { id, .. } | Event::AsyncOp { id, .. } => {
+                Lifecycle::Create(id)
+            }
+            Event::Close { id, .. } => Lifecycle::Close(id),
+            _ => Lifecycle::None,
+        }
+    }
+}
+
+// === impl Sink ===
+
+impl Sink {
+    fn create(config: &Config, path: &std::path::Path) -> io::Result<Self> {
+        let file = Counting {
+            file: File::create(path)?,
+            bytes: 0,
+        };
+        let encoder = match config.compression {
+            RecordingCompression::None => Encoder::Plain(file),
+            #[cfg(feature = "gzip")]
+            RecordingCompression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "zstd")]
+            RecordingCompression::Zstd => Encoder::Zstd(zstd_crate::Encoder::new(file, 0)?),
+            #[allow(unreachable_patterns)]
+            compression => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "{:?} recording compression requires the `{}` feature flag",
+                        compression,
+                        compression.feature(),
+                    ),
+                ))
+            }
+        };
+        Ok(Self {
+            writer: BufWriter::new(encoder),
+        })
+    }
+
+    fn bytes_written(&self) -> u64 {
+        match self.writer.get_ref() {
+            Encoder::Plain(file) => file.bytes,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.get_ref().bytes,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.get_ref().bytes,
+        }
+    }
+
+    /// Flushes all buffered data and finishes the compressed stream, returning
+    /// the underlying file.
+    #[allow(clippy::infallible_destructuring_match)] // when no compression features are enabled
+    fn finish(self) -> io::Result<File> {
+        let encoder = self.writer.into_inner().map_err(|e| e.into_error())?;
+        let counting = match encoder {
+            Encoder::Plain(file) => file,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        Ok(counting.file)
+    }
+}
+
+// === impl Encoder ===
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(file) => file.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(file) => file.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// === impl Counting ===
+
+impl Write for Counting {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,105 @@
+use super::{
+    file::{Lifecycle, Preamble, RecordingFile},
+    Config, Event, Message,
+};
+use crossbeam_channel::Receiver;
+use std::{
+    collections::VecDeque,
+    io,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Holds the events recorded within a window of time in memory, so that they
+/// can be written to a file when something goes wrong.
+///
+/// Events older than the window, or beyond the maximum number of events, are
+/// discarded oldest first, except for those that are
+/// needed to make sense of the events that remain: all registered callsites,
+/// and the creation of every task, resource, and async op that is still
+/// live. Other updates to live spans that are older than the window, such as
+/// attribute updates, are lost.
+struct Ring {
+    window: Duration,
+    max_events: usize,
+    events: VecDeque<Event>,
+    preamble: Preamble<Event>,
+}
+
+/// Receives recorded events, and writes the events in the flight recorder's
+/// window to a new file each time a dump is requested.
+pub(super) fn record_ring(config: Config, window: Duration, rx: Receiver<Message>) {
+    let mut ring = Ring {
+        window,
+        max_events: config.flight_recorder_max_events,
+        events: VecDeque::new(),
+        preamble: Preamble::default(),
+    };
+    while let Ok(msg) = rx.recv() {
+        match msg {
+            Message::Event(event) => ring.push(event),
+            Message::Dump(reply) => {
+                // If the requester has gone away, nobody is interested in the
+                // result of the dump.
+                let _ = reply.send(ring.dump(&config));
+            }
+            Message::Shutdown => break,
+        }
+    }
+    tracing::debug!("event stream ended; discarding flight recorder");
+}
+
+// === impl Ring ===
+
+impl Ring {
+    fn push(&mut self, event: Event) {
+        let cutoff = event.at().checked_sub(self.window);
+        self.events.push_back(event);
+
+        while let Some(oldest) = self.events.front() {
+            let expired = cutoff.map_or(false, |cutoff| oldest.at() < cutoff);
+            if !expired && self.events.len() <= self.max_events {
+                break;
+            }
+            let oldest = self.events.pop_front().expect("front exists");
+            self.preamble.update(Lifecycle::of(&oldest), oldest);
+        }
+    }
+
+    /// Writes every event in the window to a new recording file, returning
+    /// the path of the file.
+    fn dump(&self, config: &Config) -> io::Result<PathBuf> {
+        // Dumps requested within the same millisecond would otherwise be
+        // written to the same file.
+        static DUMPS: AtomicUsize = AtomicUsize::new(0);
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut path = config.path.clone().into_os_string();
+        path.push(format!(
+            ".{}-{}",
+            millis,
+            DUMPS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let config = Config {
+            path: path.into(),
+            // A dump is always written to a single file.
+            max_file_size: None,
+            rotation_interval: None,
+            max_files: None,
+            ..config.clone()
+        };
+
+        let mut file = RecordingFile::create(config)?;
+        let path = file.path();
+        tracing::debug!(?path, events = self.events.len(), "dumping flight recorder");
+        for event in self.preamble.iter().chain(self.events.iter()) {
+            file.write_event(event)?;
+        }
+        file.finish()?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,1121 @@
+use crate::{attribute, sync::Mutex, ToProto, WakeOp};
+use console_api as proto;
+use crossbeam_channel::{Receiver, Sender};
+
+use serde::{
+    ser::{SerializeSeq, SerializeStruct},
+    Serialize,
+};
+use std::{
+    fmt,
+    io::{self, Write},
+    panic,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+use tracing_core::Metadata;
+
+mod file;
+mod flight;
+
+use self::file::RecordingFile;
+
+/// The version of the recording format that is written.
+///
+/// See [`console_api::recording::VERSION`] for the version history. Readers in
+/// `console-api` support every version up to this one, so changes to the
+/// recorded events must be made there as well.
+const DATA_FORMAT_VERSION: u32 = proto::recording::VERSION;
+
+/// The format in which a recording is written.
+///
+/// Both formats record the same events, and share the same format version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecordingFormat {
+    /// Write each event as a JSON object on its own line.
+    ///
+    /// This is the default format. It is easy to inspect with standard tools,
+    /// but results in large recordings for busy processes.
+    Json,
+    /// Write each event as a length-delimited protobuf message.
+    ///
+    /// A binary recording starts with the magic bytes in
+    /// [`console_api::recording::MAGIC`], followed by a length-delimited
+    /// [`console_api::recording::Header`] and a sequence of length-delimited
+    /// [`console_api::recording::Event`]s, so it can be decoded using the
+    /// types generated in [`console_api`].
+    Binary,
+}
+
+/// The compression applied to recording files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecordingCompression {
+    /// Recordings are not compressed.
+    ///
+    /// This is the default.
+    None,
+    /// Recordings are compressed with gzip, and the `.gz` extension is
+    /// appended to their file names.
+    ///
+    /// This requires the `gzip` feature flag.
+    Gzip,
+    /// Recordings are compressed with zstd, and the `.zst` extension is
+    /// appended to their file names.
+    ///
+    /// This requires the `zstd` feature flag.
+    Zstd,
+}
+
+/// Configures how a recording is written to disk.
+#[derive(Clone, Debug)]
+pub(crate) struct Config {
+    pub(crate) path: PathBuf,
+    pub(crate) format: RecordingFormat,
+    pub(crate) compression: RecordingCompression,
+    /// Rotate to a new file once the current file is at least this many bytes.
+    pub(crate) max_file_size: Option<u64>,
+    /// Rotate to a new file once the current file has been written for this
+    /// long.
+    pub(crate) rotation_interval: Option<Duration>,
+    /// The maximum number of files to retain, including the file currently
+    /// being written.
+    pub(crate) max_files: Option<usize>,
+    /// Rather than writing every event to `path`, hold the events recorded
+    /// within this window in memory, and only write them when dumped.
+    pub(crate) flight_recorder: Option<Duration>,
+    /// The maximum number of events the flight recorder holds in memory.
+    pub(crate) flight_recorder_max_events: usize,
+    /// Whether to dump the flight recorder when the process first panics.
+    pub(crate) dump_on_panic: bool,
+    pub(crate) on_error: ErrorHandler,
+}
+
+/// Called with any I/O errors that occur while writing a recording.
+#[derive(Clone)]
+pub(crate) struct ErrorHandler(Arc<dyn Fn(io::Error) + Send + Sync>);
+
+/// Finishes writing a recording when dropped.
+///
+/// When a `RecordingGuard` is dropped, every event recorded so far is written
+/// to disk, the recording file is completed with a trailer, and the file is
+/// synced to the filesystem. Any events recorded after the guard is dropped
+/// are discarded.
+///
+/// The [`ConsoleLayer`] is often installed as part of the global default
+/// subscriber, which is never dropped, so a recording is not otherwise
+/// guaranteed to be complete when the process exits. Hold on to this guard
+/// for as long as the recording should continue, such as until the end of
+/// `main`.
+///
+/// A `RecordingGuard` returned by a builder that was not configured with a
+/// [recording path] does nothing.
+///
+/// [`ConsoleLayer`]: crate::ConsoleLayer
+/// [recording path]: crate::Builder::recording_path
+#[must_use = "the recording is finished when the guard is dropped"]
+#[derive(Debug, Default)]
+pub struct RecordingGuard {
+    handle: Option<Arc<Handle>>,
+}
+
+/// A handle to a [`ConsoleLayer`]'s flight recorder.
+///
+/// When a [flight recorder] is configured, the events recorded within its
+/// window are held in memory rather than written to disk. Calling
+/// [`FlightRecorder::dump`] writes those events to a new recording file, which
+/// can then be replayed in the console. The flight recorder is also dumped
+/// when a console client sends a `DumpRecording` request, and, if
+/// [enabled][dump on panic], when the process first panics.
+///
+/// A `FlightRecorder` returned by a [`ConsoleLayer`] which was not configured
+/// with a flight recorder cannot be dumped.
+///
+/// [`ConsoleLayer`]: crate::ConsoleLayer
+/// [flight recorder]: crate::Builder::flight_recorder
+/// [dump on panic]: crate::Builder::flight_recorder_dump_on_panic
+#[derive(Clone, Debug, Default)]
+pub struct FlightRecorder {
+    handle: Option<Arc<Handle>>,
+}
+
+pub(crate) struct Recorder {
+    handle: Arc<Handle>,
+}
+
+/// Shared between the [`Recorder`] and any [`RecordingGuard`]s, so that
+/// whichever is dropped first finishes the recording.
+struct Handle {
+    tx: Sender<Message>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    /// Whether events are held in memory by a flight recorder, rather than
+    /// written to disk as they are recorded.
+    is_flight_recorder: bool,
+}
+
+enum Message {
+    Event(Event),
+    /// Write the flight recorder's events to a file, replying with the path
+    /// of the file.
+    Dump(Sender<io::Result<PathBuf>>),
+    Shutdown,
+}
+
+/// The name of the thread which holds a flight recorder's events.
+const FLIGHT_RECORDER_THREAD: &str = "console/subscriber/recorder/flight";
+
+#[derive(Serialize)]
+struct Header {
+    v: u32,
+}
+
+#[derive(Serialize)]
+pub(crate) enum Event {
+    /// A new callsite was registered.
+    ///
+    /// Every other event refers to callsites by the `id` recorded here.
+    Metadata {
+        id: u64,
+        at: SystemTime,
+        metadata: SerializeMetadata,
+    },
+    Spawn {
+        id: u64,
+        at: SystemTime,
+        metadata_id: u64,
+        fields: SerializeFields,
+        location: Option<SerializeLocation>,
+    },
+    Resource {
+        id: u64,
+        at: SystemTime,
+        parent_id: Option<u64>,
+        metadata_id: u64,
+        concrete_type: String,
+        kind: SerializeKind,
+        location: Option<SerializeLocation>,
+        is_internal: bool,
+        inherit_child_attrs: bool,
+    },
+    AsyncOp {
+        id: u64,
+        at: SystemTime,
+        parent_id: Option<u64>,
+        resource_id: u64,
+        metadata_id: u64,
+        source: String,
+        inherit_child_attrs: bool,
+    },
+    PollOp {
+        at: SystemTime,
+        metadata_id: u64,
+        resource_id: u64,
+        async_op_id: u64,
+        task_id: u64,
+        op_name: String,
+        is_ready: bool,
+    },
+    /// An attribute of a resource or async op was updated.
+    ///
+    /// If the entity's parent inherits its children's attributes, the same
+    /// update also applies to the parent.
+    StateUpdate {
+        id: u64,
+        at: SystemTime,
+        field: SerializeField,
+        op: Option<attribute::UpdateOp>,
+        unit: Option<String>,
+    },
+    Enter {
+        id: u64,
+        at: SystemTime,
+    },
+    Exit {
+        id: u64,
+        at: SystemTime,
+    },
+    Close {
+        id: u64,
+        at: SystemTime,
+    },
+    Waker {
+        id: u64,
+        op: WakeOp,
+        at: SystemTime,
+    },
+    /// The last event in a complete recording file.
+    ///
+    /// This is written by the recorder itself, rather than recorded from a
+    /// span or event, and counts the events written to the file before it, not
+    /// including the header or the trailer.
+    Trailer {
+        at: SystemTime,
+        events: u64,
+    },
+}
+
+pub(crate) struct SerializeFields(pub(crate) Vec<proto::Field>);
+
+pub(crate) struct SerializeField(pub(crate) proto::Field);
+
+struct SerializeFieldRef<'a>(&'a proto::Field);
+
+pub(crate) struct SerializeMetadata(pub(crate) &'static Metadata<'static>);
+
+pub(crate) struct SerializeLocation(pub(crate) proto::Location);
+
+pub(crate) struct SerializeKind(pub(crate) proto::resources::resource::Kind);
+
+impl Recorder {
+    pub(crate) fn new(config: Config) -> io::Result<Self> {
+        let (tx, rx) = crossbeam_channel::bounded(4096);
+        let is_flight_recorder = config.flight_recorder.is_some();
+        let dumps_on_panic = is_flight_recorder && config.dump_on_panic;
+        let worker = match config.flight_recorder {
+            Some(window) => std::thread::Builder::new()
+                .name(FLIGHT_RECORDER_THREAD.into())
+                .spawn(move || flight::record_ring(config, window, rx))?,
+            None => {
+                let on_error = config.on_error.clone();
+                let file = RecordingFile::create(config)?;
+                std::thread::Builder::new()
+                    .name("console/subscriber/recorder/io".into())
+                    .spawn(move || {
+                        if let Err(e) = record_io(file, rx) {
+                            (on_error.0)(e);
+                        }
+                    })?
+            }
+        };
+
+        let handle = Arc::new(Handle {
+            tx,
+            worker: Mutex::new(Some(worker)),
+            is_flight_recorder,
+        });
+        if dumps_on_panic {
+            dump_on_panic(Arc::downgrade(&handle));
+        }
+        Ok(Recorder { handle })
+    }
+
+    pub(crate) fn record(&self, event: Event) {
+        // If sending fails, the recording has either been finished, or the I/O
+        // thread has already reported the error that ended it, so the event is
+        // discarded.
+        let _ = self.handle.tx.send(Message::Event(event));
+    }
+
+    pub(crate) fn guard(&self) -> RecordingGuard {
+        RecordingGuard {
+            handle: Some(self.handle.clone()),
+        }
+    }
+
+    pub(crate) fn flight_recorder(&self) -> FlightRecorder {
+        FlightRecorder {
+            handle: Some(self.handle.clone()).filter(|handle| handle.is_flight_recorder),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.handle.finish();
+    }
+}
+
+// === impl Handle ===
+
+impl Handle {
+    /// Tells the I/O thread to finish the recording once it has written all
+    /// previously recorded events, and waits for it to do so.
+    fn finish(&self) {
+        let worker = match self.worker.lock().take() {
+            Some(worker) => worker,
+            // the recording has already been finished.
+            None => return,
+        };
+        let _ = self.tx.send(Message::Shutdown);
+        if worker.join().is_err() {
+            eprintln!("event recorder thread panicked!");
+        }
+    }
+
+    /// Asks the flight recorder thread to write its events to a file, and
+    /// waits for it to do so.
+    fn dump(&self) -> io::Result<PathBuf> {
+        let finished = || io::Error::new(io::ErrorKind::Other, "the recording has finished");
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        self.tx.send(Message::Dump(tx)).map_err(|_| finished())?;
+        rx.recv().map_err(|_| finished())?
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("finished", &self.worker.lock().is_none())
+            .field("is_flight_recorder", &self.is_flight_recorder)
+            .finish()
+    }
+}
+
+// === impl FlightRecorder ===
+
+impl FlightRecorder {
+    /// Writes the events currently held by the flight recorder to a new
+    /// recording file, returning the path of the file.
+    ///
+    /// The file is written to the [recording path], with the time of the dump
+    /// (in milliseconds since the Unix epoch) and a counter appended to its
+    /// name, so each dump creates a new file. This blocks until the file has been written.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if no flight recorder is configured, if the
+    /// recording has been finished, or if the file could not be written.
+    ///
+    /// [recording path]: crate::Builder::recording_path
+    pub fn dump(&self) -> io::Result<PathBuf> {
+        match self.handle {
+            Some(ref handle) => handle.dump(),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "no flight recorder is configured",
+            )),
+        }
+    }
+
+    /// Returns `true` if this handle refers to a flight recorder which can be
+    /// dumped.
+    pub fn is_enabled(&self) -> bool {
+        self.handle.is_some()
+    }
+}
+
+// === impl RecordingGuard ===
+
+impl Drop for RecordingGuard {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.finish();
+        }
+    }
+}
+
+// === impl ErrorHandler ===
+
+impl ErrorHandler {
+    pub(crate) fn new(f: impl Fn(io::Error) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl Default for ErrorHandler {
+    fn default() -> Self {
+        Self::new(|e| eprintln!("event recorder failed: {}", e))
+    }
+}
+
+impl fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ErrorHandler")
+            .field(&format_args!(".."))
+            .finish()
+    }
+}
+
+/// Installs a panic hook which dumps the flight recorder before the process
+/// unwinds or aborts, after running the previously installed hook.
+///
+/// Only the first panic is dumped: panics that are caught, such as those in
+/// tasks spawned on a Tokio runtime, would otherwise write a new file each.
+fn dump_on_panic(handle: Weak<Handle>) {
+    static DUMPED: AtomicBool = AtomicBool::new(false);
+
+    let prev = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        prev(info);
+
+        // If the flight recorder's own thread panicked, it can't dump itself.
+        if std::thread::current().name() == Some(FLIGHT_RECORDER_THREAD) {
+            return;
+        }
+        if DUMPED.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        if let Some(handle) = handle.upgrade() {
+            match handle.dump() {
+                Ok(path) => eprintln!("flight recording written to {}", path.display()),
+                Err(e) => eprintln!("failed to write flight recording: {}", e),
+            }
+        }
+    }));
+}
+
+fn record_io(mut file: RecordingFile, rx: Receiver<Message>) -> io::Result<()> {
+    // wait to recieve an event...
+    while let Ok(msg) = rx.recv() {
+        let mut shutdown = false;
+        let mut msg = Some(msg);
+        while let Some(next) = msg.take() {
+            match next {
+                Message::Event(event) => file.write_event(&event)?,
+                // Only flight recorders can be dumped, and `Handle::dump` is
+                // never called for other recorders.
+                Message::Dump(_) => {}
+                Message::Shutdown => {
+                    shutdown = true;
+                    break;
+                }
+            }
+            // drain any additional events that are ready now
+            msg = rx.try_recv().ok();
+        }
+
+        if shutdown {
+            break;
+        }
+
+        file.flush()?;
+    }
+
+    tracing::debug!("event stream ended; finishing file");
+    file.finish()
+}
+
+// === impl RecordingFormat ===
+
+impl RecordingFormat {
+    fn write_header(self, file: &mut impl Write) -> io::Result<()> {
+        match self {
+            RecordingFormat::Json => write_json(
+                file,
+                &Header {
+                    v: DATA_FORMAT_VERSION,
+                },
+            ),
+            RecordingFormat::Binary => {
+                file.write_all(proto::recording::MAGIC)?;
+                write_proto(
+                    file,
+                    &proto::recording::Header {
+                        version: DATA_FORMAT_VERSION,
+                    },
+                )
+            }
+        }
+    }
+
+    fn write_event(self, file: &mut impl Write, event: &Event) -> io::Result<()> {
+        match self {
+            RecordingFormat::Json => write_json(file, event),
+            RecordingFormat::Binary => write_proto(file, &event.to_proto()),
+        }
+    }
+}
+
+impl Default for RecordingFormat {
+    fn default() -> Self {
+        RecordingFormat::Json
+    }
+}
+
+impl FromStr for RecordingFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            s if s.eq_ignore_ascii_case("json") => Ok(RecordingFormat::Json),
+            s if s.eq_ignore_ascii_case("binary") => Ok(RecordingFormat::Binary),
+            _ => Err("invalid recording format, expected one of `json` or `binary`"),
+        }
+    }
+}
+
+// === impl RecordingCompression ===
+
+impl RecordingCompression {
+    /// Returns the extension appended to the names of files compressed in
+    /// this format.
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            RecordingCompression::None => None,
+            RecordingCompression::Gzip => Some(".gz"),
+            RecordingCompression::Zstd => Some(".zst"),
+        }
+    }
+
+    /// Returns the feature flag required for this compression format.
+    #[allow(dead_code)] // only used when a compression feature is disabled
+    fn feature(self) -> &'static str {
+        match self {
+            RecordingCompression::None => "",
+            RecordingCompression::Gzip => "gzip",
+            RecordingCompression::Zstd => "zstd",
+        }
+    }
+}
+
+impl Default for RecordingCompression {
+    fn default() -> Self {
+        RecordingCompression::None
+    }
+}
+
+impl FromStr for RecordingCompression {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            s if s.eq_ignore_ascii_case("none") => Ok(RecordingCompression::None),
+            s if s.eq_ignore_ascii_case("gzip") => Ok(RecordingCompression::Gzip),
+            s if s.eq_ignore_ascii_case("zstd") => Ok(RecordingCompression::Zstd),
+            _ => Err("invalid recording compression, expected one of `none`, `gzip`, or `zstd`"),
+        }
+    }
+}
+
+fn write_json<T: Serialize>(mut file: &mut impl Write, val: &T) -> io::Result<()> {
+    serde_json::to_writer(&mut file, val)?;
+    file.write_all(b"\n")
+}
+
+fn write_proto<T: prost::Message>(file: &mut impl Write, val: &T) -> io::Result<()> {
+    file.write_all(&val.encode_length_delimited_to_vec())
+}
+
+// === impl Event ===
+
+impl Event {
+    pub(super) fn at(&self) -> SystemTime {
+        match *self {
+            Event::Metadata { at, .. }
+            | Event::Spawn { at, .. }
+            | Event::Resource { at, .. }
+            | Event::AsyncOp { at, .. }
+            | Event::PollOp { at, .. }
+            | Event::StateUpdate { at, .. }
+            | Event::Enter { at, .. }
+            | Event::Exit { at, .. }
+            | Event::Close { at, .. }
+            | Event::Waker { at, .. }
+            | Event::Trailer { at, .. } => at,
+        }
+    }
+}
+
+impl ToProto for Event {
+    type Output = proto::recording::Event;
+
+    fn to_proto(&self) -> Self::Output {
+        use proto::recording::{event::Event as Kind, state_update, waker};
+
+        fn id(id: u64) -> Option<proto::Id> {
+            Some(proto::Id { id })
+        }
+
+        fn meta_id(id: u64) -> Option<proto::MetaId> {
+            Some(proto::MetaId { id })
+        }
+
+        fn span_id(id: u64) -> proto::SpanId {
+            proto::SpanId { id }
+        }
+
+        let event = match self {
+            Event::Metadata { metadata, .. } => Kind::Metadata(metadata.0.into()),
+            Event::Spawn {
+                id: task_id,
+                metadata_id,
+                fields,
+                location,
+                ..
+            } => Kind::Spawn(proto::tasks::Task {
+                id: id(*task_id),
+                metadata: meta_id(*metadata_id),
+                kind: proto::tasks::task::Kind::Spawn as i32,
+                fields: fields.0.clone(),
+                parents: Vec::new(),
+                location: location.as_ref().map(|location| location.0.clone()),
+            }),
+            Event::Resource {
+                id: resource_id,
+                parent_id,
+                metadata_id,
+                concrete_type,
+                kind,
+                location,
+                is_internal,
+                inherit_child_attrs,
+                ..
+            } => Kind::Resource(proto::recording::Resource {
+                resource: Some(proto::resources::Resource {
+                    id: id(*resource_id),
+                    metadata: meta_id(*metadata_id),
+                    concrete_type: concrete_type.clone(),
+                    kind: Some(kind.0.clone()),
+                    location: location.as_ref().map(|location| location.0.clone()),
+                    parent_resource_id: parent_id.and_then(id),
+                    is_internal: *is_internal,
+                }),
+                inherit_child_attrs: *inherit_child_attrs,
+            }),
+            Event::AsyncOp {
+                id: async_op_id,
+                parent_id,
+                resource_id,
+                metadata_id,
+                source,
+                inherit_child_attrs,
+                ..
+            } => Kind::AsyncOp(proto::recording::AsyncOp {
+                async_op: Some(proto::async_ops::AsyncOp {
+                    id: id(*async_op_id),
+                    metadata: meta_id(*metadata_id),
+                    source: source.clone(),
+                    parent_async_op_id: parent_id.and_then(id),
+                    resource_id: id(*resource_id),
+                }),
+                inherit_child_attrs: *inherit_child_attrs,
+            }),
+            Event::PollOp {
+                metadata_id,
+                resource_id,
+                async_op_id,
+                task_id,
+                op_name,
+                is_ready,
+                ..
+            } => Kind::PollOp(proto::resources::PollOp {
+                metadata: meta_id(*metadata_id),
+                resource_id: id(*resource_id),
+                name: op_name.clone(),
+                task_id: id(*task_id),
+                async_op_id: id(*async_op_id),
+                is_ready: *is_ready,
+            }),
+            Event::StateUpdate {
+                id,
+                field,
+                op,
+                unit,
+                ..
+            } => Kind::StateUpdate(proto::recording::StateUpdate {
+                id: Some(span_id(*id)),
+                field: Some(field.0.clone()),
+                op: op.as_ref().map(|op| {
+                    let op = match op {
+                        attribute::UpdateOp::Add => state_update::Op::Add,
+                        attribute::UpdateOp::Override => state_update::Op::Override,
+                        attribute::UpdateOp::Sub => state_update::Op::Sub,
+                    };
+                    op as i32
+                }),
+                unit: unit.clone(),
+            }),
+            Event::Enter { id, .. } => Kind::Enter(span_id(*id)),
+            Event::Exit { id, .. } => Kind::Exit(span_id(*id)),
+            Event::Close { id, .. } => Kind::Close(span_id(*id)),
+            Event::Waker { id, op, .. } => {
+                let (op, self_wake) = match *op {
+                    WakeOp::Wake { self_wake } => (waker::Op::Wake, self_wake),
+                    WakeOp::WakeByRef { self_wake } => (waker::Op::WakeByRef, self_wake),
+                    WakeOp::Clone => (waker::Op::Clone, false),
+                    WakeOp::Drop => (waker::Op::Drop, false),
+                };
+                Kind::Waker(proto::recording::Waker {
+                    id: Some(span_id(*id)),
+                    op: op as i32,
+                    self_wake,
+                })
+            }
+            Event::Trailer { events, .. } => {
+                Kind::Trailer(proto::recording::Trailer { events: *events })
+            }
+        };
+
+        proto::recording::Event {
+            at: Some(self.at().into()),
+            event: Some(event),
+        }
+    }
+}
+
+impl serde::Serialize for SerializeFields {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for element in &self.0 {
+            seq.serialize_element(&SerializeFieldRef(element))?;
+        }
+        seq.end()
+    }
+}
+
+impl serde::Serialize for SerializeField {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        SerializeFieldRef(&self.0).serialize(serializer)
+    }
+}
+
+impl serde::Serialize for SerializeFieldRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser = serializer.serialize_struct("Field", 3)?;
+        // Field names are usually recorded as strings, but a name may also be
+        // an index into the field names of the callsite's `Metadata` event.
+        match self.0.name.as_ref().expect("name") {
+            proto::field::Name::StrName(ref n) => ser.serialize_field("name", n)?,
+            proto::field::Name::NameIdx(idx) => ser.serialize_field("name_idx", idx)?,
+        }
+
+        // JSON can't tell a non-negative `i64` from a `u64`, or a `Debug`
+        // value from a string, so the value's type is recorded as well.
+        match self.0.value.as_ref().expect("field value") {
+            proto::field::Value::DebugVal(v) => {
+                ser.serialize_field("type", "debug")?;
+                ser.serialize_field("value", v)?;
+            }
+            proto::field::Value::StrVal(v) => {
+                ser.serialize_field("type", "str")?;
+                ser.serialize_field("value", v)?;
+            }
+            proto::field::Value::U64Val(v) => {
+                ser.serialize_field("type", "u64")?;
+                ser.serialize_field("value", v)?;
+            }
+            proto::field::Value::I64Val(v) => {
+                ser.serialize_field("type", "i64")?;
+                ser.serialize_field("value", v)?;
+            }
+            proto::field::Value::BoolVal(v) => {
+                ser.serialize_field("type", "bool")?;
+                ser.serialize_field("value", v)?;
+            }
+        }
+        ser.end()
+    }
+}
+
+impl serde::Serialize for SerializeMetadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        struct FieldNames<'a>(&'a tracing_core::field::FieldSet);
+
+        impl serde::Serialize for FieldNames<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+                for field in self.0.iter() {
+                    seq.serialize_element(field.name())?;
+                }
+                seq.end()
+            }
+        }
+
+        let meta = self.0;
+        let mut ser = serializer.serialize_struct("Metadata", 8)?;
+        ser.serialize_field("name", meta.name())?;
+        ser.serialize_field("target", meta.target())?;
+        ser.serialize_field("module_path", &meta.module_path())?;
+        ser.serialize_field("file", &meta.file())?;
+        ser.serialize_field("line", &meta.line())?;
+        ser.serialize_field("level", meta.level().as_str())?;
+        ser.serialize_field("kind", if meta.is_span() { "span" } else { "event" })?;
+        ser.serialize_field("field_names", &FieldNames(meta.fields()))?;
+        ser.end()
+    }
+}
+
+impl serde::Serialize for SerializeLocation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut ser = serializer.serialize_struct("Location", 4)?;
+        ser.serialize_field("file", &self.0.file)?;
+        ser.serialize_field("module_path", &self.0.module_path)?;
+        ser.serialize_field("line", &self.0.line)?;
+        ser.serialize_field("column", &self.0.column)?;
+        ser.end()
+    }
+}
+
+impl serde::Serialize for SerializeKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use proto::resources::resource::kind::{Kind, Known};
+        // Resource kinds are recorded as the string the runtime emitted for
+        // the `kind` field, which is how `ResourceVisitor` parses them.
+        match self.0.kind.as_ref() {
+            Some(Kind::Known(known)) if *known == Known::Timer as i32 => {
+                serializer.serialize_str("timer")
+            }
+            Some(Kind::Known(known)) => serializer.serialize_str(&format!("known({})", known)),
+            Some(Kind::Other(other)) => serializer.serialize_str(other),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::recording::{event::Event as Kind, Format, Reader};
+    use tracing_core::{
+        callsite::{Callsite, Identifier},
+        field::FieldSet,
+        metadata::Kind as MetaKind,
+        subscriber::Interest,
+        Level,
+    };
+
+    struct TestCallsite;
+
+    static CALLSITE: TestCallsite = TestCallsite;
+
+    static META: Metadata<'static> = Metadata::new(
+        "test",
+        "console_subscriber::record::tests",
+        Level::INFO,
+        Some(file!()),
+        Some(line!()),
+        Some(module_path!()),
+        FieldSet::new(&["answer"], Identifier(&CALLSITE)),
+        MetaKind::SPAN,
+    );
+
+    impl Callsite for TestCallsite {
+        fn set_interest(&self, _: Interest) {}
+
+        fn metadata(&self) -> &Metadata<'_> {
+            &META
+        }
+    }
+
+    fn field(name: &str, value: proto::field::Value) -> proto::Field {
+        proto::Field {
+            name: Some(proto::field::Name::StrName(name.to_string())),
+            value: Some(value),
+            metadata_id: None,
+        }
+    }
+
+    fn location() -> SerializeLocation {
+        SerializeLocation(proto::Location {
+            file: Some("src/main.rs".to_string()),
+            module_path: Some("app".to_string()),
+            line: Some(42),
+            column: Some(7),
+        })
+    }
+
+    /// Returns one of each kind of event, with each type of field value.
+    fn events() -> Vec<Event> {
+        use proto::{
+            field::Value,
+            resources::resource::{kind, Kind},
+        };
+        let at = SystemTime::now();
+        let metadata_id = proto::MetaId::from(&META).id;
+        vec![
+            Event::Metadata {
+                id: metadata_id,
+                at,
+                metadata: SerializeMetadata(&META),
+            },
+            Event::Spawn {
+                id: 1,
+                at,
+                metadata_id,
+                fields: SerializeFields(vec![
+                    field("debug", Value::DebugVal("Some(1)".to_string())),
+                    field("str", Value::StrVal("hello".to_string())),
+                    field("u64", Value::U64Val(u64::MAX)),
+                    field("i64", Value::I64Val(5)),
+                    field("negative", Value::I64Val(-5)),
+                    field("bool", Value::BoolVal(true)),
+                ]),
+                location: Some(location()),
+            },
+            Event::Resource {
+                id: 2,
+                at,
+                parent_id: None,
+                metadata_id,
+                concrete_type: "Sleep".to_string(),
+                kind: SerializeKind(Kind {
+                    kind: Some(kind::Kind::Known(kind::Known::Timer as i32)),
+                }),
+                location: Some(location()),
+                is_internal: false,
+                inherit_child_attrs: false,
+            },
+            Event::Resource {
+                id: 3,
+                at,
+                parent_id: Some(2),
+                metadata_id,
+                concrete_type: "Semaphore".to_string(),
+                kind: SerializeKind(Kind {
+                    kind: Some(kind::Kind::Other("Sync".to_string())),
+                }),
+                location: None,
+                is_internal: true,
+                inherit_child_attrs: true,
+            },
+            Event::AsyncOp {
+                id: 4,
+                at,
+                parent_id: None,
+                resource_id: 3,
+                metadata_id,
+                source: "Semaphore::acquire".to_string(),
+                inherit_child_attrs: true,
+            },
+            Event::PollOp {
+                at,
+                metadata_id,
+                resource_id: 3,
+                async_op_id: 4,
+                task_id: 1,
+                op_name: "poll_acquire".to_string(),
+                is_ready: false,
+            },
+            Event::StateUpdate {
+                id: 3,
+                at,
+                field: SerializeField(field("permits", Value::U64Val(3))),
+                op: Some(attribute::UpdateOp::Sub),
+                unit: Some("permits".to_string()),
+            },
+            Event::Enter { id: 1, at },
+            Event::Waker {
+                id: 1,
+                op: WakeOp::Wake { self_wake: true },
+                at,
+            },
+            Event::Waker {
+                id: 1,
+                op: WakeOp::Clone,
+                at,
+            },
+            Event::Exit { id: 1, at },
+            Event::Close { id: 1, at },
+        ]
+    }
+
+    /// Writes every event with the subscriber's writer, and checks that the
+    /// reader in `console-api` reads back the same events.
+    fn round_trip(name: &str, format: RecordingFormat, compression: RecordingCompression) {
+        let path = std::env::temp_dir().join(format!(
+            "console-subscriber-{}-{}.rec",
+            name,
+            std::process::id()
+        ));
+        let config = Config {
+            path,
+            format,
+            compression,
+            max_file_size: None,
+            rotation_interval: None,
+            max_files: None,
+            flight_recorder: None,
+            flight_recorder_max_events: 0,
+            dump_on_panic: false,
+            on_error: ErrorHandler::default(),
+        };
+        let events = events();
+        let mut file = RecordingFile::create(config).unwrap();
+        for event in &events {
+            file.write_event(event).unwrap();
+        }
+        let path = file.path();
+        file.finish().unwrap();
+
+        let reader = Reader::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let reader = reader.unwrap();
+        assert_eq!(reader.version(), proto::recording::VERSION);
+        assert_eq!(
+            reader.format(),
+            match format {
+                RecordingFormat::Json => Format::Json,
+                RecordingFormat::Binary => Format::Binary,
+            }
+        );
+        let mut read = reader.collect::<io::Result<Vec<_>>>().unwrap();
+
+        let trailer = read.pop().and_then(|event| event.event);
+        match trailer {
+            Some(Kind::Trailer(trailer)) => assert_eq!(trailer.events, events.len() as u64),
+            event => panic!("expected a trailer, got {:?}", event),
+        }
+        let expected = events.iter().map(ToProto::to_proto).collect::<Vec<_>>();
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("json", RecordingFormat::Json, RecordingCompression::None);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        round_trip(
+            "binary",
+            RecordingFormat::Binary,
+            RecordingCompression::None,
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn gzip_round_trip() {
+        round_trip(
+            "json-gzip",
+            RecordingFormat::Json,
+            RecordingCompression::Gzip,
+        );
+        round_trip(
+            "binary-gzip",
+            RecordingFormat::Binary,
+            RecordingCompression::Gzip,
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn zstd_round_trip() {
+        round_trip(
+            "json-zstd",
+            RecordingFormat::Json,
+            RecordingCompression::Zstd,
+        );
+        round_trip(
+            "binary-zstd",
+            RecordingFormat::Binary,
+            RecordingCompression::Zstd,
+        );
+    }
+}
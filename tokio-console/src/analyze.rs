@@ -0,0 +1,566 @@
+//! Offline analysis of recordings written by `console-subscriber`.
+use crate::{
+    config::{AnalyzeArgs, Lints, OutputFormat},
+    replay::aggregator::Aggregator,
+    state::{format_location, tasks::Task},
+    util::{serialize_secs, serialize_time},
+    view,
+};
+use color_eyre::{eyre::eyre, Help, SectionExt};
+use console_api as proto;
+use proto::recording::{event::Event as Kind, Reader};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+    convert::TryFrom,
+    fmt::Write as _,
+    io::{self, Write},
+    time::{Duration, SystemTime},
+};
+
+/// A report summarizing a recording.
+#[derive(Debug, Serialize)]
+struct Report {
+    recording: String,
+    version: u32,
+    #[serde(serialize_with = "serialize_time")]
+    start: SystemTime,
+    #[serde(serialize_with = "serialize_time")]
+    end: SystemTime,
+    tasks: TaskCounts,
+    /// Tasks grouped by the location at which they were spawned, with the
+    /// most tasks first.
+    locations: Vec<LocationReport>,
+    /// The longest individual polls of any task, with the longest first.
+    longest_polls: Vec<PollReport>,
+    /// Tasks that were still running when the recording ended.
+    never_completed: Vec<TaskReport>,
+    /// The warnings detected for the state of each task at the end of the
+    /// recording.
+    warnings: Vec<WarningReport>,
+    /// The resources which were most often polled without being ready, with
+    /// the most contended first.
+    contended_resources: Vec<ResourceReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskCounts {
+    spawned: usize,
+    completed: usize,
+    never_completed: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct LocationReport {
+    location: String,
+    tasks: usize,
+    completed: usize,
+    /// How long tasks spawned here existed for.
+    lifetime: Percentiles,
+    /// How long tasks spawned here spent being polled.
+    busy: Percentiles,
+}
+
+/// Duration percentiles, in seconds.
+#[derive(Debug, Serialize)]
+struct Percentiles {
+    #[serde(serialize_with = "serialize_secs")]
+    p50: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    p90: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    p99: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    max: Duration,
+}
+
+#[derive(Debug, Serialize)]
+struct PollReport {
+    #[serde(flatten)]
+    task: TaskRef,
+    #[serde(serialize_with = "serialize_time")]
+    started_at: SystemTime,
+    #[serde(serialize_with = "serialize_secs")]
+    duration: Duration,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskReport {
+    #[serde(flatten)]
+    task: TaskRef,
+    state: &'static str,
+    /// How long the task had existed for when the recording ended.
+    #[serde(serialize_with = "serialize_secs")]
+    age: Duration,
+    polls: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct WarningReport {
+    summary: String,
+    tasks: Vec<TaskWarning>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskWarning {
+    #[serde(flatten)]
+    task: TaskRef,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResourceReport {
+    concrete_type: String,
+    location: String,
+    /// The number of times the resource was polled.
+    polls: u64,
+    /// The number of polls which were not ready.
+    pending: u64,
+    /// The number of distinct tasks which polled the resource.
+    tasks: usize,
+}
+
+/// Identifies a task in a report.
+#[derive(Clone, Debug, Serialize)]
+struct TaskRef {
+    /// The task's console ID, as displayed when the recording is replayed.
+    id: u64,
+    name: Option<String>,
+    location: String,
+}
+
+/// Tracks what the aggregator doesn't: individual poll durations and the
+/// outcome of each resource poll.
+#[derive(Default)]
+struct Tracker {
+    /// The task span IDs which are currently being polled, with the time at
+    /// which the outermost poll started and the number of nested polls.
+    polling: HashMap<u64, (SystemTime, usize)>,
+    tasks: HashSet<u64>,
+    /// The longest polls so far, as a min-heap of (duration, started at, task
+    /// span ID).
+    longest: BinaryHeap<Reverse<(Duration, SystemTime, u64)>>,
+    resources: HashMap<u64, Resource>,
+}
+
+#[derive(Default)]
+struct Resource {
+    concrete_type: String,
+    location: String,
+    polls: u64,
+    pending: u64,
+    tasks: HashSet<u64>,
+}
+
+/// Reads the recording named by `args`, and prints a report summarizing it.
+pub(crate) fn run(
+    args: AnalyzeArgs,
+    lints: &Lints,
+    styles: &view::Styles,
+) -> color_eyre::Result<()> {
+    let path = args.path.display().to_string();
+    let section = || path.clone().header("Recording:");
+    let reader = Reader::open(&args.path)
+        .map_err(|error| eyre!("failed to open recording: {}", error))
+        .with_section(section)?;
+    let version = reader.version();
+
+    let mut aggregator = Aggregator::default();
+    let mut tracker = Tracker::default();
+    let mut start = None;
+    let mut end = None;
+    for event in reader {
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                // Analyze as much of the recording as can be read, such as if
+                // it was not finished.
+                tracing::warn!(%error, "error reading recording");
+                eprintln!("warning: recording ends early after an error: {}", error);
+                break;
+            }
+        };
+        if let Some(at) = event
+            .at
+            .clone()
+            .and_then(|at| SystemTime::try_from(at).ok())
+        {
+            start.get_or_insert(at);
+            end = Some(at);
+            tracker.observe(at, &event);
+        }
+        aggregator.apply(event);
+    }
+    let (start, end) = start
+        .zip(end)
+        .ok_or_else(|| eyre!("recording is empty"))
+        .with_section(section)?;
+
+    let mut state = crate::new_state(lints, None);
+    state.update(
+        styles,
+        &view::ViewState::TasksList,
+        aggregator.initial_update(end),
+    );
+    let tasks = state
+        .tasks_state_mut()
+        .take_new_tasks()
+        .filter_map(|task| task.upgrade())
+        .collect::<Vec<_>>();
+    let tasks = tasks.iter().map(|task| task.borrow()).collect::<Vec<_>>();
+
+    let report = Report::new(path, version, start, end, &tasks, tracker, args.top);
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    match args.output {
+        OutputFormat::Text => stdout.write_all(report.to_text().as_bytes())?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &report)?;
+            writeln!(stdout)?;
+        }
+    }
+    Ok(())
+}
+
+// === impl Tracker ===
+
+impl Tracker {
+    fn observe(&mut self, at: SystemTime, event: &proto::recording::Event) {
+        match event.event {
+            Some(Kind::Spawn(ref task)) => {
+                if let Some(ref id) = task.id {
+                    // span IDs may be reused once the span has closed.
+                    self.tasks.insert(id.id);
+                    self.polling.remove(&id.id);
+                }
+            }
+            Some(Kind::Enter(ref id)) if self.tasks.contains(&id.id) => {
+                self.polling.entry(id.id).or_insert((at, 0)).1 += 1;
+            }
+            Some(Kind::Exit(ref id)) => {
+                if let Some((started_at, depth)) = self.polling.get_mut(&id.id) {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        let started_at = *started_at;
+                        self.polling.remove(&id.id);
+                        let duration = at.duration_since(started_at).unwrap_or_default();
+                        self.longest.push(Reverse((duration, started_at, id.id)));
+                        if self.longest.len() > MAX_LONGEST_POLLS {
+                            self.longest.pop();
+                        }
+                    }
+                }
+            }
+            Some(Kind::Close(ref id)) => {
+                self.tasks.remove(&id.id);
+                self.polling.remove(&id.id);
+            }
+            Some(Kind::Resource(ref resource)) => {
+                if let Some(resource) = resource.resource.as_ref() {
+                    if let Some(ref id) = resource.id {
+                        self.resources.insert(
+                            id.id,
+                            Resource {
+                                concrete_type: resource.concrete_type.clone(),
+                                location: format_location(resource.location.clone())
+                                    .trim()
+                                    .to_string(),
+                                ..Resource::default()
+                            },
+                        );
+                    }
+                }
+            }
+            Some(Kind::PollOp(ref poll_op)) => {
+                let resource = poll_op
+                    .resource_id
+                    .as_ref()
+                    .and_then(|id| self.resources.get_mut(&id.id));
+                if let Some(resource) = resource {
+                    resource.polls += 1;
+                    if !poll_op.is_ready {
+                        resource.pending += 1;
+                    }
+                    if let Some(ref task_id) = poll_op.task_id {
+                        resource.tasks.insert(task_id.id);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The number of longest polls tracked, regardless of how many are reported.
+const MAX_LONGEST_POLLS: usize = 1000;
+
+// === impl Report ===
+
+impl Report {
+    fn new(
+        recording: String,
+        version: u32,
+        start: SystemTime,
+        end: SystemTime,
+        tasks: &[std::cell::Ref<'_, Task>],
+        tracker: Tracker,
+        top: usize,
+    ) -> Self {
+        let by_span_id = tasks
+            .iter()
+            .map(|task| (task.span_id(), task_ref(task)))
+            .collect::<HashMap<_, _>>();
+
+        let mut locations = BTreeMap::<&str, Vec<&Task>>::new();
+        for task in tasks {
+            locations
+                .entry(task.location().trim())
+                .or_default()
+                .push(task);
+        }
+        let mut locations = locations
+            .into_iter()
+            .map(|(location, tasks)| LocationReport {
+                location: location.to_string(),
+                tasks: tasks.len(),
+                completed: tasks.iter().filter(|task| task.is_completed()).count(),
+                lifetime: Percentiles::of(tasks.iter().map(|task| task.total(end)).collect()),
+                busy: Percentiles::of(tasks.iter().map(|task| task.busy(end)).collect()),
+            })
+            .collect::<Vec<_>>();
+        locations.sort_by_key(|location| Reverse(location.tasks));
+
+        let longest_polls = tracker
+            .longest
+            .into_sorted_vec()
+            .into_iter()
+            .filter_map(|Reverse((duration, started_at, span_id))| {
+                Some(PollReport {
+                    task: by_span_id.get(&span_id)?.clone(),
+                    started_at,
+                    duration,
+                })
+            })
+            .take(top)
+            .collect();
+
+        let mut never_completed = tasks
+            .iter()
+            .filter(|task| !task.is_completed())
+            .map(|task| TaskReport {
+                task: task_ref(task),
+                state: if task.is_running() { "running" } else { "idle" },
+                age: task.total(end),
+                polls: task.total_polls(),
+            })
+            .collect::<Vec<_>>();
+        never_completed.sort_by_key(|task| Reverse(task.age));
+
+        let mut warnings = Vec::<WarningReport>::new();
+        for task in tasks {
+            for warning in task.warnings() {
+                let summary = warning.summary();
+                let message = warning.format(task, end);
+                let task = task_ref(task);
+                match warnings.iter_mut().find(|w| w.summary == summary) {
+                    Some(report) => report.tasks.push(TaskWarning { task, message }),
+                    None => warnings.push(WarningReport {
+                        summary: summary.to_string(),
+                        tasks: vec![TaskWarning { task, message }],
+                    }),
+                }
+            }
+        }
+        for warning in &mut warnings {
+            warning.tasks.sort_by_key(|warning| warning.task.id);
+        }
+
+        let mut contended_resources = tracker
+            .resources
+            .into_values()
+            .filter(|resource| resource.pending > 0)
+            .map(|resource| ResourceReport {
+                concrete_type: resource.concrete_type,
+                location: resource.location,
+                polls: resource.polls,
+                pending: resource.pending,
+                tasks: resource.tasks.len(),
+            })
+            .collect::<Vec<_>>();
+        contended_resources.sort_by_key(|resource| Reverse((resource.pending, resource.polls)));
+        contended_resources.truncate(top);
+
+        let completed = tasks.iter().filter(|task| task.is_completed()).count();
+        Self {
+            recording,
+            version,
+            start,
+            end,
+            tasks: TaskCounts {
+                spawned: tasks.len(),
+                completed,
+                never_completed: tasks.len() - completed,
+            },
+            locations,
+            longest_polls,
+            never_completed,
+            warnings,
+            contended_resources,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        // Writing to a `String` never fails.
+        let mut out = String::new();
+        let _ = self.write_text(&mut out);
+        out
+    }
+
+    fn write_text(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(
+            out,
+            "Recording: {} (format version {})",
+            self.recording, self.version
+        )?;
+        writeln!(
+            out,
+            "Duration:  {} ({} to {})",
+            format_duration(self.end.duration_since(self.start).unwrap_or_default()),
+            humantime::format_rfc3339_millis(self.start),
+            humantime::format_rfc3339_millis(self.end),
+        )?;
+        writeln!(
+            out,
+            "Tasks:     {} spawned, {} completed, {} never completed",
+            self.tasks.spawned, self.tasks.completed, self.tasks.never_completed,
+        )?;
+
+        writeln!(out, "\nTasks by spawn location:")?;
+        for location in &self.locations {
+            writeln!(
+                out,
+                "  {} ({} tasks, {} completed)",
+                location.location, location.tasks, location.completed
+            )?;
+            writeln!(out, "      lifetime: {}", location.lifetime)?;
+            writeln!(out, "      busy:     {}", location.busy)?;
+        }
+
+        writeln!(out, "\nLongest polls:")?;
+        if self.longest_polls.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for poll in &self.longest_polls {
+            writeln!(
+                out,
+                "  {:>10}  {} at {}",
+                format_duration(poll.duration),
+                poll.task,
+                humantime::format_rfc3339_millis(poll.started_at),
+            )?;
+        }
+
+        writeln!(out, "\nTasks that never completed:")?;
+        if self.never_completed.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for task in &self.never_completed {
+            writeln!(
+                out,
+                "  {} ({}, {} old, {} polls)",
+                task.task,
+                task.state,
+                format_duration(task.age),
+                task.polls,
+            )?;
+        }
+
+        writeln!(out, "\nWarnings:")?;
+        if self.warnings.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for warning in &self.warnings {
+            writeln!(out, "  {} {}", warning.tasks.len(), warning.summary)?;
+            for task in &warning.tasks {
+                writeln!(out, "      {}: {}", task.task, task.message)?;
+            }
+        }
+
+        writeln!(out, "\nContended resources:")?;
+        if self.contended_resources.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for resource in &self.contended_resources {
+            writeln!(
+                out,
+                "  {} at {}: {} of {} polls pending, from {} tasks",
+                resource.concrete_type,
+                resource.location,
+                resource.pending,
+                resource.polls,
+                resource.tasks,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// === impl Percentiles ===
+
+impl Percentiles {
+    fn of(mut durations: Vec<Duration>) -> Self {
+        durations.sort_unstable();
+        // Nearest-rank percentiles.
+        let percentile = |p: usize| {
+            let rank = (p * durations.len() + 99) / 100;
+            durations
+                .get(rank.saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        Self {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: durations.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl std::fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "p50 {}, p90 {}, p99 {}, max {}",
+            format_duration(self.p50),
+            format_duration(self.p90),
+            format_duration(self.p99),
+            format_duration(self.max),
+        )
+    }
+}
+
+// === impl TaskRef ===
+
+impl std::fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "task {}", self.id)?;
+        if let Some(ref name) = self.name {
+            write!(f, " ({})", name)?;
+        }
+        write!(f, " spawned at {}", self.location)
+    }
+}
+
+fn task_ref(task: &Task) -> TaskRef {
+    TaskRef {
+        id: task.id(),
+        name: task.name().map(String::from),
+        location: task.location().trim().to_string(),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2?}", duration)
+}
@@ -0,0 +1,261 @@
+//! Checks a process or recording for tasks that trigger warnings, for use in
+//! CI.
+use crate::{
+    config::{CheckArgs, Lints},
+    conn::Connection,
+    replay::aggregator::Aggregator,
+    state::{async_ops::AsyncOpRef, resources::ResourceRef, tasks::TaskRef, State},
+    view,
+    warnings::{Linter, Severity},
+};
+use color_eyre::{eyre::eyre, Help, SectionExt};
+use console_api::recording::Reader;
+use std::{convert::TryFrom, path::Path, time::SystemTime};
+
+/// The tasks, resources, and async ops of the process or recording being
+/// checked.
+#[derive(Default)]
+struct Entities {
+    tasks: Vec<TaskRef>,
+    resources: Vec<ResourceRef>,
+    async_ops: Vec<AsyncOpRef>,
+}
+
+/// How many of one kind of entity were checked, and how many of them
+/// triggered warnings.
+#[derive(Default)]
+struct Counts {
+    total: usize,
+    warned: usize,
+    failed: usize,
+}
+
+/// Collects the state of the process or recording named by `args`, and prints
+/// every task, resource, and async op that triggered a warning.
+///
+/// Returns whether the check passed, i.e. whether nothing triggered a lint
+/// that fails the check.
+pub(crate) async fn run(
+    args: CheckArgs,
+    lints: &Lints,
+    styles: &view::Styles,
+) -> color_eyre::Result<bool> {
+    let mut state = crate::new_state(lints, None);
+    let entities = match args.recording {
+        Some(ref path) => read_recording(path, &mut state, styles)?,
+        None => watch(&args, &mut state, styles).await?,
+    };
+    // Both sources of entities fail if there were no updates.
+    let now = state.last_updated_at().unwrap_or_else(SystemTime::now);
+
+    let mut tasks = Counts::default();
+    for task in entities.tasks.iter().filter_map(|task| task.upgrade()) {
+        let task = task.borrow();
+        let what = match task.name() {
+            Some(name) => format!("task {} ({})", task.id(), name),
+            None => format!("task {}", task.id()),
+        };
+        tasks.report(&args, task.location(), &what, &*task, task.warnings(), now);
+    }
+
+    let mut resources = Counts::default();
+    for resource in entities.resources.iter().filter_map(|r| r.upgrade()) {
+        let resource = resource.borrow();
+        let what = format!("resource {} ({})", resource.id(), resource.concrete_type());
+        resources.report(
+            &args,
+            resource.location(),
+            &what,
+            &*resource,
+            resource.warnings(),
+            now,
+        );
+    }
+
+    let mut async_ops = Counts::default();
+    for op in entities.async_ops.iter().filter_map(|op| op.upgrade()) {
+        let op = op.borrow();
+        // Async ops don't have a location of their own, so use their
+        // resource's.
+        let location = state
+            .resources_state()
+            .resource(op.resource_id())
+            .and_then(|resource| resource.upgrade())
+            .map(|resource| resource.borrow().location().to_string())
+            .unwrap_or_default();
+        let what = format!("async op {} ({})", op.id(), op.source());
+        async_ops.report(&args, &location, &what, &*op, op.warnings(), now);
+    }
+
+    let summary = format!(
+        "{} tasks, {} resources, and {} async ops",
+        tasks.summary(),
+        resources.summary(),
+        async_ops.summary()
+    );
+    let all = [tasks, resources, async_ops];
+    let failed = all.iter().any(|counts| counts.failed > 0);
+    if failed {
+        println!("check failed: {} triggered warnings", summary);
+    } else if all.iter().any(|counts| counts.warned > 0) {
+        println!(
+            "check passed: {} triggered warnings, but none were errors",
+            summary
+        );
+    } else {
+        println!(
+            "check passed: {} tasks, {} resources, and {} async ops, no warnings",
+            all[0].total, all[1].total, all[2].total
+        );
+    }
+    Ok(!failed)
+}
+
+/// Applies updates from the target process until `--duration` has elapsed or
+/// the process exits, returning the process's tasks, resources, and async ops.
+async fn watch(
+    args: &CheckArgs,
+    state: &mut State,
+    styles: &view::Styles,
+) -> color_eyre::Result<Entities> {
+    let target = args.target_addr.clone();
+    tracing::info!(?target, duration = ?args.duration, "checking target");
+    let mut conn = Connection::new(target.clone());
+    let deadline = async {
+        match args.duration {
+            Some(duration) => tokio::time::sleep(duration.into()).await,
+            None => futures::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+
+    let mut entities = Entities::default();
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            update = conn.try_next_update() => match update {
+                Some(update) => {
+                    state.update(styles, &view::ViewState::TasksList, update);
+                    // New entities must be taken after every update, as
+                    // they are discarded by the next one.
+                    entities.take_new(state);
+                }
+                // If we were never connected, keep trying; otherwise, the
+                // process has exited.
+                None if state.last_updated_at().is_some() => {
+                    tracing::debug!("target disconnected");
+                    break;
+                }
+                None => {}
+            },
+        }
+    }
+
+    if state.last_updated_at().is_none() {
+        return Err(eyre!("never received any updates from the target"))
+            .with_section(|| target.to_string().header("Target:"));
+    }
+    Ok(entities)
+}
+
+/// Applies every event in a recording, returning the recorded tasks,
+/// resources, and async ops.
+fn read_recording(
+    path: &Path,
+    state: &mut State,
+    styles: &view::Styles,
+) -> color_eyre::Result<Entities> {
+    let section = || path.display().to_string().header("Recording:");
+    let reader = Reader::open(path)
+        .map_err(|error| eyre!("failed to open recording: {}", error))
+        .with_section(section)?;
+
+    let mut aggregator = Aggregator::default();
+    let mut end = None;
+    for event in reader {
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                // Check as much of the recording as can be read, such as if
+                // the process was killed before it was finished.
+                tracing::warn!(%error, "error reading recording");
+                eprintln!("warning: recording ends early after an error: {}", error);
+                break;
+            }
+        };
+        if let Some(at) = event
+            .at
+            .clone()
+            .and_then(|at| SystemTime::try_from(at).ok())
+        {
+            end = Some(at);
+        }
+        aggregator.apply(event);
+    }
+    let end = end
+        .ok_or_else(|| eyre!("recording is empty"))
+        .with_section(section)?;
+
+    state.update(
+        styles,
+        &view::ViewState::TasksList,
+        aggregator.initial_update(end),
+    );
+    let mut entities = Entities::default();
+    entities.take_new(state);
+    Ok(entities)
+}
+
+// === impl Entities ===
+
+impl Entities {
+    fn take_new(&mut self, state: &mut State) {
+        self.tasks.extend(state.tasks_state_mut().take_new_tasks());
+        self.resources
+            .extend(state.resources_state_mut().take_new_resources());
+        self.async_ops
+            .extend(state.async_ops_state_mut().take_new_async_ops());
+    }
+}
+
+// === impl Counts ===
+
+impl Counts {
+    /// Prints each of an entity's warnings, and counts whether the entity
+    /// triggered warnings and whether they fail the check.
+    fn report<T>(
+        &mut self,
+        args: &CheckArgs,
+        location: &str,
+        what: &str,
+        val: &T,
+        warnings: &[Linter<T>],
+        now: SystemTime,
+    ) {
+        self.total += 1;
+        if warnings.is_empty() {
+            return;
+        }
+        self.warned += 1;
+        let fails = warnings
+            .iter()
+            .any(|warning| warning.severity() == Severity::Error || !args.allow_warnings);
+        if fails {
+            self.failed += 1;
+        }
+        for warning in warnings {
+            println!(
+                "{}: {}[{}]: {}: {}",
+                location.trim(),
+                warning.severity().as_str(),
+                warning.lint(),
+                what,
+                warning.format(val, now)
+            );
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!("{} of {}", self.warned, self.total)
+    }
+}
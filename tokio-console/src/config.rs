@@ -1,6 +1,20 @@
-use crate::view::Palette;
-use clap::{ArgGroup, Parser as Clap, ValueHint};
-use std::process::Command;
+use crate::{
+    filter::{Expr, Subject},
+    replay::Seek,
+    state::{async_ops::AsyncOp, resources::Resource, tasks::Task},
+    view::Palette,
+    warnings::{
+        Custom, ExhaustedPermits, Lint, Linter, LongLock, LongPoll, LostWaker, NeverPolled,
+        SelfWakePercent, Severity, SlowAsyncOp, Stuck,
+    },
+};
+use clap::{ArgGroup, Args, Parser as Clap, Subcommand, ValueHint};
+use color_eyre::{eyre::eyre, Help, SectionExt};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process;
 use std::str::FromStr;
 use std::time::Duration;
 use tonic::transport::Uri;
@@ -31,6 +45,9 @@ pub struct Config {
     #[clap(flatten)]
     pub(crate) view_options: ViewOptions,
 
+    #[clap(flatten)]
+    pub(crate) lint_options: LintOptions,
+
     /// How long to continue displaying completed tasks and dropped resources
     /// after they have been closed.
     ///
@@ -61,11 +78,350 @@ pub struct Config {
     /// * `years`, `year`, `y` -- defined as 365.25 days
     #[clap(long = "retain-for", default_value = "6s")]
     retain_for: RetainFor,
+
+    /// How far back the overview view's history of task statistics goes.
+    ///
+    /// This is a time span in the same format as `--retain-for`, such as `5m`
+    /// or `1h 30m`.
+    #[clap(long = "history", default_value = "5m")]
+    pub(crate) history: humantime::Duration,
+
+    /// How to display the console's data.
+    ///
+    /// By default, the console runs an interactive terminal UI. If this is
+    /// `json` (or `ndjson`), the console instead writes a snapshot of the
+    /// task, resource, and async op tables to stdout as a single line of JSON
+    /// every `--interval`, so that it can be piped into other tools without a
+    /// terminal.
+    #[clap(
+        long = "output",
+        default_value = "tui",
+        possible_values = &["tui", "json", "ndjson"],
+    )]
+    pub(crate) output: Output,
+
+    /// How often to write a snapshot when the output is `json`.
+    #[clap(long, default_value = "1s")]
+    pub(crate) interval: humantime::Duration,
+
+    #[clap(subcommand)]
+    pub(crate) subcommand: Option<Command>,
+}
+
+/// How the console displays data from a live process.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Output {
+    /// The interactive terminal UI.
+    Tui,
+    /// Newline-delimited JSON snapshots on stdout.
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Replay a recording written by `console-subscriber`, rather than
+    /// connecting to a live process.
+    Replay(ReplayArgs),
+
+    /// Print a report summarizing a recording written by `console-subscriber`.
+    Analyze(AnalyzeArgs),
+
+    /// Check a process or recording for tasks that trigger warnings, exiting
+    /// with a non-zero status if any do.
+    Check(CheckArgs),
+}
+
+#[derive(Args, Debug)]
+#[deny(missing_docs)]
+pub(crate) struct ReplayArgs {
+    /// The path of the recording to replay.
+    ///
+    /// Recordings in either the JSON or binary format, optionally compressed
+    /// with gzip or zstd, can be replayed.
+    #[clap(value_hint = ValueHint::FilePath)]
+    pub(crate) path: PathBuf,
+
+    /// The speed at which to play back the recording, relative to the speed
+    /// at which it was recorded.
+    #[clap(long, default_value = "1", parse(try_from_str = parse_speed))]
+    pub(crate) speed: f64,
+
+    /// Where in the recording to start playing back.
+    ///
+    /// This accepts either a duration from the start of the recording (such
+    /// as `1m30s`), or an RFC 3339 timestamp (such as `2021-12-01T12:00:00Z`).
+    #[clap(long)]
+    pub(crate) start: Option<Seek>,
+
+    /// Start with playback paused.
+    #[clap(long)]
+    pub(crate) paused: bool,
+
+    /// Serve the recording over the console's gRPC API on this address,
+    /// rather than displaying it.
+    ///
+    /// Any console, including older versions that cannot read recordings,
+    /// can then connect to this address to inspect the recording as though
+    /// it were a live process. Pausing the console pauses playback.
+    #[clap(long, value_name = "ADDR")]
+    pub(crate) serve: Option<SocketAddr>,
+}
+
+#[derive(Args, Debug)]
+#[deny(missing_docs)]
+pub(crate) struct AnalyzeArgs {
+    /// The path of the recording to analyze.
+    #[clap(value_hint = ValueHint::FilePath)]
+    pub(crate) path: PathBuf,
+
+    /// The format in which to print the report.
+    #[clap(long, default_value = "text", possible_values = &["text", "json"])]
+    pub(crate) output: OutputFormat,
+
+    /// The maximum number of entries to include in each ranked section of the
+    /// report, such as the longest polls.
+    #[clap(long, default_value = "10")]
+    pub(crate) top: usize,
+}
+
+#[derive(Args, Debug)]
+#[deny(missing_docs)]
+pub(crate) struct CheckArgs {
+    /// The address of a console-enabled process to check.
+    #[clap(default_value = "http://127.0.0.1:6669", value_hint = ValueHint::Url)]
+    pub(crate) target_addr: Uri,
+
+    /// Check a recording written by `console-subscriber`, rather than
+    /// connecting to a live process.
+    #[clap(long, value_hint = ValueHint::FilePath, conflicts_with = "duration")]
+    pub(crate) recording: Option<PathBuf>,
+
+    /// How long to watch the process for before checking it.
+    ///
+    /// If this is not set, the process is checked once it exits.
+    #[clap(long)]
+    pub(crate) duration: Option<humantime::Duration>,
+
+    /// Only fail the check for lints that are reported as errors (with
+    /// `--deny`), rather than for any lint.
+    #[clap(long)]
+    pub(crate) allow_warnings: bool,
+}
+
+/// The format of non-interactive output.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum OutputFormat {
+    /// Human-readable text.
+    Text,
+    /// JSON.
+    Json,
 }
 
 #[derive(Debug)]
 struct RetainFor(Option<Duration>);
 
+/// Configures which lints are checked for, and how severe they are.
+///
+/// These options override the `[lints]` section of the config file.
+#[derive(Clap, Debug, Clone)]
+pub struct LintOptions {
+    /// The path of a config file to read lint configuration from.
+    ///
+    /// If this is not set, `tokio-console/console.toml` in the user's config
+    /// directory (such as `~/.config` on Linux) is read, if it exists.
+    #[clap(long = "config", global = true, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    config_path: Option<PathBuf>,
+
+    /// Disable a lint.
+    ///
+    /// This is the name of a built-in lint, such as `long-poll`, or of a
+    /// custom lint defined in the config file. This may be repeated to disable multiple lints.
+    #[clap(
+        long = "allow",
+        global = true,
+        value_name = "LINT",
+        multiple_occurrences = true
+    )]
+    allow: Vec<String>,
+
+    /// Enable a lint, reporting it as a warning.
+    ///
+    /// This is the name of a built-in lint, such as `long-poll`, or of a
+    /// custom lint defined in the config file. This may be repeated to enable multiple lints.
+    #[clap(
+        long = "warn",
+        global = true,
+        value_name = "LINT",
+        multiple_occurrences = true
+    )]
+    warn: Vec<String>,
+
+    /// Enable a lint, reporting it as an error.
+    ///
+    /// This is the name of a built-in lint, such as `long-poll`, or of a
+    /// custom lint defined in the config file. This may be repeated to enable multiple lints.
+    #[clap(
+        long = "deny",
+        global = true,
+        value_name = "LINT",
+        multiple_occurrences = true
+    )]
+    deny: Vec<String>,
+
+    /// The percentage of a task's wakeups that it must have woken itself for
+    /// the `self-wake-percent` lint to fire.
+    #[clap(long, global = true, value_name = "PERCENT")]
+    self_wake_percent: Option<u64>,
+
+    /// How long a single poll of a task must take for the `long-poll` lint
+    /// to fire.
+    #[clap(long, global = true, value_name = "DURATION")]
+    long_poll_duration: Option<humantime::Duration>,
+
+    /// How long a task must have existed without being polled for the
+    /// `never-polled` lint to fire.
+    #[clap(long, global = true, value_name = "DURATION")]
+    never_polled_duration: Option<humantime::Duration>,
+
+    /// How long a task must have been idle, while other tasks use a resource
+    /// it is waiting on, for the `stuck` lint to fire.
+    #[clap(long, global = true, value_name = "DURATION")]
+    stuck_duration: Option<humantime::Duration>,
+
+    /// How long a semaphore must have had no permits available for the
+    /// `exhausted-permits` lint to fire.
+    #[clap(long, global = true, value_name = "DURATION")]
+    exhausted_permits_duration: Option<humantime::Duration>,
+
+    /// How long a lock must have been held for the `long-lock` lint to fire.
+    #[clap(long, global = true, value_name = "DURATION")]
+    long_lock_duration: Option<humantime::Duration>,
+
+    /// How many times longer than the median of the other async ops on its
+    /// resource an async op must have been pending for the `slow-async-op`
+    /// lint to fire.
+    #[clap(long, global = true, value_name = "RATIO")]
+    slow_async_op_ratio: Option<u32>,
+}
+
+/// Whether a lint is enabled, and how severe it is when it fires.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LintLevel {
+    /// The lint is disabled.
+    Allow,
+    /// The lint is reported as a warning.
+    Warn,
+    /// The lint is reported as an error.
+    Deny,
+}
+
+/// The configuration of every lint, after merging the config file and the
+/// command line.
+#[derive(Clone, Debug)]
+pub(crate) struct Lints {
+    levels: BTreeMap<Lint, LintLevel>,
+    self_wake_percent: u64,
+    long_poll_duration: Duration,
+    never_polled_duration: Duration,
+    stuck_duration: Duration,
+    exhausted_permits_duration: Duration,
+    long_lock_duration: Duration,
+    slow_async_op_ratio: u32,
+    custom: Vec<CustomLint>,
+}
+
+/// A lint defined by a filter expression in the config file.
+#[derive(Clone, Debug)]
+struct CustomLint {
+    lint: Lint,
+    on: CustomLintOn,
+    warning: Custom,
+}
+
+/// The kind of entity a custom lint checks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum CustomLintOn {
+    Tasks,
+    Resources,
+    AsyncOps,
+}
+
+/// The contents of the config file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    lints: LintsFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct LintsFile {
+    #[serde(default)]
+    self_wake_percent: SelfWakePercentFile,
+    #[serde(default)]
+    lost_waker: LintFile,
+    #[serde(default)]
+    long_poll: DurationLintFile,
+    #[serde(default)]
+    never_polled: DurationLintFile,
+    #[serde(default)]
+    stuck: DurationLintFile,
+    #[serde(default)]
+    exhausted_permits: DurationLintFile,
+    #[serde(default)]
+    long_lock: DurationLintFile,
+    #[serde(default)]
+    slow_async_op: SlowAsyncOpFile,
+    #[serde(default)]
+    custom: BTreeMap<String, CustomLintFile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct LintFile {
+    level: Option<LintLevel>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct SelfWakePercentFile {
+    level: Option<LintLevel>,
+    min_percent: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct SlowAsyncOpFile {
+    level: Option<LintLevel>,
+    min_ratio: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct CustomLintFile {
+    level: Option<LintLevel>,
+    #[serde(default)]
+    on: CustomLintOn,
+    /// A filter expression, such as `state == idle && polls > 1000`.
+    filter: String,
+    /// The message for each entity the lint fires for.
+    message: Option<String>,
+    /// A summary of the lint, which follows a count of the entities it fired
+    /// for.
+    summary: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct DurationLintFile {
+    level: Option<LintLevel>,
+    /// A duration, such as `10ms`.
+    min_duration: Option<String>,
+}
+
 #[derive(Clap, Debug, Clone)]
 #[clap(group = ArgGroup::new("colors").conflicts_with("no-colors"))]
 pub struct ViewOptions {
@@ -163,6 +519,307 @@ impl Config {
     }
 }
 
+// === impl LintOptions ===
+
+impl LintOptions {
+    /// Returns the configuration of every lint, reading the config file if
+    /// there is one.
+    pub(crate) fn lints(&self) -> color_eyre::Result<Lints> {
+        let path = self.config_path.clone().or_else(|| {
+            let path = dirs::config_dir()?
+                .join("tokio-console")
+                .join("console.toml");
+            path.exists().then(|| path)
+        });
+        let mut lints = Lints::default();
+        if let Some(path) = path {
+            tracing::debug!(?path, "reading config file");
+            let section = || path.display().to_string().header("Config file:");
+            let text = std::fs::read_to_string(&path)
+                .map_err(|error| eyre!("failed to read config file: {}", error))
+                .with_section(section)?;
+            let file: ConfigFile = toml::from_str(&text)
+                .map_err(|error| eyre!("invalid config file: {}", error))
+                .with_section(section)?;
+            lints.apply_file(file.lints).with_section(section)?;
+        }
+        lints.apply_options(self)?;
+        tracing::debug!(?lints);
+        Ok(lints)
+    }
+}
+
+// === impl CustomLintOn ===
+
+impl Default for CustomLintOn {
+    fn default() -> Self {
+        Self::Tasks
+    }
+}
+
+// === impl Lints ===
+
+impl Lints {
+    /// Returns how severe `lint` is when it fires, or `None` if it is
+    /// disabled.
+    pub(crate) fn severity(&self, lint: Lint) -> Option<Severity> {
+        match self.levels.get(&lint) {
+            Some(LintLevel::Allow) => None,
+            Some(LintLevel::Warn) | None => Some(Severity::Warning),
+            Some(LintLevel::Deny) => Some(Severity::Error),
+        }
+    }
+
+    /// Returns a linter for each enabled lint that applies to tasks.
+    pub(crate) fn task_linters(&self) -> Vec<Linter<Task>> {
+        Lint::ALL
+            .iter()
+            .filter_map(|&lint| {
+                let severity = self.severity(lint)?;
+                let linter = match lint {
+                    Lint::SelfWakePercent => {
+                        Linter::new(lint, severity, SelfWakePercent::new(self.self_wake_percent))
+                    }
+                    Lint::LostWaker => Linter::new(lint, severity, LostWaker),
+                    Lint::LongPoll => {
+                        Linter::new(lint, severity, LongPoll::new(self.long_poll_duration))
+                    }
+                    Lint::NeverPolled => {
+                        Linter::new(lint, severity, NeverPolled::new(self.never_polled_duration))
+                    }
+                    Lint::Stuck => Linter::new(lint, severity, Stuck::new(self.stuck_duration)),
+                    Lint::ExhaustedPermits
+                    | Lint::LongLock
+                    | Lint::SlowAsyncOp
+                    | Lint::Custom(_) => return None,
+                };
+                Some(linter)
+            })
+            .chain(self.custom_linters(CustomLintOn::Tasks))
+            .collect()
+    }
+
+    /// Returns a linter for each enabled lint that applies to resources.
+    pub(crate) fn resource_linters(&self) -> Vec<Linter<Resource>> {
+        Lint::ALL
+            .iter()
+            .filter_map(|&lint| {
+                let severity = self.severity(lint)?;
+                let linter = match lint {
+                    Lint::ExhaustedPermits => Linter::new(
+                        lint,
+                        severity,
+                        ExhaustedPermits::new(self.exhausted_permits_duration),
+                    ),
+                    Lint::LongLock => {
+                        Linter::new(lint, severity, LongLock::new(self.long_lock_duration))
+                    }
+                    _ => return None,
+                };
+                Some(linter)
+            })
+            .chain(self.custom_linters(CustomLintOn::Resources))
+            .collect()
+    }
+
+    /// Returns a linter for each enabled lint that applies to async ops.
+    pub(crate) fn async_op_linters(&self) -> Vec<Linter<AsyncOp>> {
+        Lint::ALL
+            .iter()
+            .filter_map(|&lint| {
+                let severity = self.severity(lint)?;
+                let linter = match lint {
+                    Lint::SlowAsyncOp => {
+                        Linter::new(lint, severity, SlowAsyncOp::new(self.slow_async_op_ratio))
+                    }
+                    _ => return None,
+                };
+                Some(linter)
+            })
+            .chain(self.custom_linters(CustomLintOn::AsyncOps))
+            .collect()
+    }
+
+    /// Returns a linter for each enabled custom lint that checks `on`.
+    fn custom_linters<T: Subject>(&self, on: CustomLintOn) -> impl Iterator<Item = Linter<T>> + '_ {
+        self.custom
+            .iter()
+            .filter(move |custom| custom.on == on)
+            .filter_map(move |custom| {
+                let severity = self.severity(custom.lint)?;
+                Some(Linter::new(custom.lint, severity, custom.warning.clone()))
+            })
+    }
+
+    fn apply_file(&mut self, file: LintsFile) -> color_eyre::Result<()> {
+        let levels = [
+            (Lint::SelfWakePercent, file.self_wake_percent.level),
+            (Lint::LostWaker, file.lost_waker.level),
+            (Lint::LongPoll, file.long_poll.level),
+            (Lint::NeverPolled, file.never_polled.level),
+            (Lint::Stuck, file.stuck.level),
+            (Lint::ExhaustedPermits, file.exhausted_permits.level),
+            (Lint::LongLock, file.long_lock.level),
+            (Lint::SlowAsyncOp, file.slow_async_op.level),
+        ];
+        for (lint, level) in levels {
+            if let Some(level) = level {
+                self.levels.insert(lint, level);
+            }
+        }
+        if let Some(percent) = file.self_wake_percent.min_percent {
+            self.self_wake_percent = percent;
+        }
+        if let Some(ratio) = file.slow_async_op.min_ratio {
+            self.slow_async_op_ratio = ratio;
+        }
+        let durations = [
+            (Lint::LongPoll, file.long_poll, &mut self.long_poll_duration),
+            (
+                Lint::NeverPolled,
+                file.never_polled,
+                &mut self.never_polled_duration,
+            ),
+            (Lint::Stuck, file.stuck, &mut self.stuck_duration),
+            (
+                Lint::ExhaustedPermits,
+                file.exhausted_permits,
+                &mut self.exhausted_permits_duration,
+            ),
+            (Lint::LongLock, file.long_lock, &mut self.long_lock_duration),
+        ];
+        for (lint, file, min_duration) in durations {
+            if let Some(duration) = file.min_duration {
+                let duration = duration
+                    .parse::<humantime::Duration>()
+                    .map_err(|error| eyre!("invalid `lints.{}.min-duration`: {}", lint, error))?;
+                *min_duration = duration.into();
+            }
+        }
+        for (name, file) in file.custom {
+            if name.parse::<Lint>().is_ok() {
+                return Err(eyre!(
+                    "custom lint `{}` has the same name as a built-in lint",
+                    name
+                ));
+            }
+            let filter = file
+                .filter
+                .parse::<Expr>()
+                .map_err(|error| eyre!("invalid `lints.custom.{}.filter`: {}", name, error))?;
+            // The warnings are recomputed by the lints themselves, so a lint
+            // can't see a consistent count of them.
+            if filter.uses_field("warnings") {
+                return Err(eyre!(
+                    "invalid `lints.custom.{}.filter`: custom lints can't use the `warnings` field",
+                    name
+                ));
+            }
+            let (noun, plural) = match file.on {
+                CustomLintOn::Tasks => ("task", "tasks"),
+                CustomLintOn::Resources => ("resource", "resources"),
+                CustomLintOn::AsyncOps => ("async op", "async ops"),
+            };
+            let message = file
+                .message
+                .unwrap_or_else(|| format!("This {} matches `{}`", noun, filter));
+            let summary = file
+                .summary
+                .unwrap_or_else(|| format!("{} match `{}`", plural, filter));
+            let lint = Lint::Custom(Box::leak(name.into_boxed_str()));
+            if let Some(level) = file.level {
+                self.levels.insert(lint, level);
+            }
+            self.custom.push(CustomLint {
+                lint,
+                on: file.on,
+                warning: Custom::new(filter, message, summary),
+            });
+        }
+        Ok(())
+    }
+
+    fn apply_options(&mut self, options: &LintOptions) -> color_eyre::Result<()> {
+        let levels = [
+            (&options.allow, LintLevel::Allow),
+            (&options.warn, LintLevel::Warn),
+            (&options.deny, LintLevel::Deny),
+        ];
+        for (names, level) in levels {
+            for name in names {
+                let lint = self.lint(name)?;
+                self.levels.insert(lint, level);
+            }
+        }
+        if let Some(percent) = options.self_wake_percent {
+            self.self_wake_percent = percent;
+        }
+        if let Some(duration) = options.long_poll_duration {
+            self.long_poll_duration = duration.into();
+        }
+        if let Some(duration) = options.never_polled_duration {
+            self.never_polled_duration = duration.into();
+        }
+        if let Some(duration) = options.stuck_duration {
+            self.stuck_duration = duration.into();
+        }
+        if let Some(duration) = options.exhausted_permits_duration {
+            self.exhausted_permits_duration = duration.into();
+        }
+        if let Some(duration) = options.long_lock_duration {
+            self.long_lock_duration = duration.into();
+        }
+        if let Some(ratio) = options.slow_async_op_ratio {
+            self.slow_async_op_ratio = ratio;
+        }
+        Ok(())
+    }
+
+    /// Returns the built-in or custom lint with the given name.
+    ///
+    /// Custom lints are defined in the config file, so lint names given on
+    /// the command line can only be checked once it has been read.
+    fn lint(&self, name: &str) -> color_eyre::Result<Lint> {
+        if let Ok(lint) = name.parse::<Lint>() {
+            return Ok(lint);
+        }
+        if let Some(custom) = self
+            .custom
+            .iter()
+            .find(|custom| custom.lint.as_str() == name)
+        {
+            return Ok(custom.lint);
+        }
+        let names = Lint::ALL
+            .iter()
+            .chain(self.custom.iter().map(|custom| &custom.lint))
+            .map(|lint| lint.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(eyre!(
+            "unknown lint `{}` (expected one of: {})",
+            name,
+            names
+        ))
+    }
+}
+
+impl Default for Lints {
+    fn default() -> Self {
+        Self {
+            levels: BTreeMap::new(),
+            self_wake_percent: SelfWakePercent::DEFAULT_PERCENT,
+            long_poll_duration: LongPoll::DEFAULT_DURATION,
+            never_polled_duration: NeverPolled::DEFAULT_DURATION,
+            stuck_duration: Stuck::DEFAULT_DURATION,
+            exhausted_permits_duration: ExhaustedPermits::DEFAULT_DURATION,
+            long_lock_duration: LongLock::DEFAULT_DURATION,
+            slow_async_op_ratio: SlowAsyncOp::DEFAULT_RATIO,
+            custom: Vec::new(),
+        }
+    }
+}
+
 // === impl ViewOptions ===
 
 impl ViewOptions {
@@ -198,7 +855,7 @@ impl ViewOptions {
 
         // Okay, try to use `tput` to ask the terminfo database how many colors
         // are supported...
-        let tput = Command::new("tput").arg("colors").output();
+        let tput = process::Command::new("tput").arg("colors").output();
         tracing::debug!(?tput, "checking `tput colors`");
         if let Ok(output) = tput {
             let stdout = String::from_utf8(output.stdout);
@@ -226,6 +883,48 @@ fn parse_true_color(s: &str) -> bool {
     s.eq_ignore_ascii_case("truecolor") || s.eq_ignore_ascii_case("24bit")
 }
 
+fn parse_speed(s: &str) -> Result<f64, String> {
+    let speed = s.trim().parse::<f64>().map_err(|error| error.to_string())?;
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(format!(
+            "speed must be a number greater than zero, not {}",
+            s
+        ))
+    }
+}
+
+// === impl Output ===
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            s if s.eq_ignore_ascii_case("tui") => Ok(Output::Tui),
+            s if s.eq_ignore_ascii_case("json") || s.eq_ignore_ascii_case("ndjson") => {
+                Ok(Output::Json)
+            }
+            _ => Err(format!("unknown output `{}`", s)),
+        }
+    }
+}
+
+// === impl OutputFormat ===
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            s if s.eq_ignore_ascii_case("text") => Ok(OutputFormat::Text),
+            s if s.eq_ignore_ascii_case("json") => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format `{}`", s)),
+        }
+    }
+}
+
 impl FromStr for RetainFor {
     type Err = humantime::DurationError;
 
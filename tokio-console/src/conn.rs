@@ -99,21 +99,33 @@ impl Connection {
 
     pub async fn next_update(&mut self) -> Update {
         loop {
-            match self.state {
-                State::Connected { ref mut stream, .. } => match Pin::new(stream).next().await {
-                    Some(Ok(update)) => return update,
-                    Some(Err(status)) => {
-                        tracing::warn!(%status, "error from stream");
-                        self.state = State::Disconnected(Self::BACKOFF);
-                    }
-                    None => {
-                        tracing::error!("stream closed by server");
-                        self.state = State::Disconnected(Self::BACKOFF);
-                    }
-                },
-                State::Disconnected(_) => self.connect().await,
+            if let Some(update) = self.try_next_update().await {
+                return update;
+            }
+        }
+    }
+
+    /// Returns the next update, or `None` if the connection was lost after
+    /// connecting, such as when the remote process exits.
+    ///
+    /// If the connection was lost, the next call will try to reconnect.
+    pub async fn try_next_update(&mut self) -> Option<Update> {
+        if let State::Disconnected(_) = self.state {
+            self.connect().await;
+        }
+        if let State::Connected { ref mut stream, .. } = self.state {
+            match Pin::new(stream).next().await {
+                Some(Ok(update)) => return Some(update),
+                Some(Err(status)) => {
+                    tracing::warn!(%status, "error from stream");
+                }
+                None => {
+                    tracing::error!("stream closed by server");
+                }
             }
         }
+        self.state = State::Disconnected(Self::BACKOFF);
+        None
     }
 
     #[tracing::instrument(skip(self))]
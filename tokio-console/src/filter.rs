@@ -0,0 +1,796 @@
+//! A small expression language for filtering tasks, resources and async ops.
+//!
+//! An expression compares the fields of an entity with values, such as
+//!
+//! ```text
+//! state == idle && polls > 1000 && location ~ "hyper"
+//! ```
+//!
+//! or `busy > 500ms`. Expressions are used both to filter the console's tables
+//! and to define custom lints in the config file.
+//!
+//! The grammar is:
+//!
+//! ```text
+//! expr       = and ( "||" and )*
+//! and        = unary ( "&&" unary )*
+//! unary      = "!" unary | "(" expr ")" | comparison
+//! comparison = FIELD ( "==" | "!=" | "<" | "<=" | ">" | ">=" | "~" | "!~" ) value
+//! value      = NUMBER | DURATION | STRING | WORD
+//! ```
+//!
+//! where a `DURATION` is a non-negative number followed by one of the units
+//! `ns`, `us`, `ms`, `s`, `m` or `h`, and a `STRING` is quoted with `"`. `~`
+//! and `!~` match a field against a regular expression.
+use crate::state::{
+    async_ops::AsyncOp,
+    resources::{Resource, TypeVisibility},
+    tasks::{Task, TaskState},
+};
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use tui::text::Span;
+
+/// A parsed filter expression.
+#[derive(Clone, Debug)]
+pub(crate) struct Expr {
+    text: String,
+    node: Box<Node>,
+}
+
+/// Something that can be matched against an [`Expr`].
+pub(crate) trait Subject {
+    /// Returns the value of the field called `name`, as of `now`, or `None`
+    /// if there is no such field.
+    fn field(&self, name: &str, now: SystemTime) -> Option<Value<'_>>;
+}
+
+/// The value of a field of a [`Subject`].
+#[derive(Clone, Debug)]
+pub(crate) enum Value<'a> {
+    Bool(bool),
+    Number(f64),
+    Duration(Duration),
+    Str(Cow<'a, str>),
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Compare {
+        field: String,
+        op: Op,
+        value: Literal,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+#[derive(Clone, Debug)]
+struct Literal {
+    /// The literal as written, without quotes, which string fields are
+    /// compared with.
+    text: String,
+    kind: LiteralKind,
+}
+
+#[derive(Clone, Debug)]
+enum LiteralKind {
+    Bool(bool),
+    Number(f64),
+    Duration(Duration),
+    Str,
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64, Option<String>),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+// === impl Expr ===
+
+impl Expr {
+    /// Returns `true` if `subject` matches this expression, as of `now`.
+    pub(crate) fn matches(&self, subject: &impl Subject, now: SystemTime) -> bool {
+        self.node.eval(subject, now)
+    }
+
+    /// Returns `true` if this expression compares the field called `name`.
+    pub(crate) fn uses_field(&self, name: &str) -> bool {
+        self.node.uses_field(name)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let node = parser.expr()?;
+        if let Some(token) = parser.next() {
+            return Err(format!("unexpected {}", token));
+        }
+        Ok(Self {
+            text: s.trim().to_owned(),
+            node: Box::new(node),
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// === impl Node ===
+
+impl Node {
+    fn eval(&self, subject: &impl Subject, now: SystemTime) -> bool {
+        match self {
+            Node::And(lhs, rhs) => lhs.eval(subject, now) && rhs.eval(subject, now),
+            Node::Or(lhs, rhs) => lhs.eval(subject, now) || rhs.eval(subject, now),
+            Node::Not(node) => !node.eval(subject, now),
+            Node::Compare { field, op, value } => {
+                // A field that the subject doesn't have is unequal to, and not
+                // ordered with, anything.
+                match subject.field(field, now) {
+                    Some(field) => value.compare(&field, *op),
+                    None => matches!(op, Op::Ne | Op::NotMatch),
+                }
+            }
+        }
+    }
+
+    fn uses_field(&self, name: &str) -> bool {
+        match self {
+            Node::And(lhs, rhs) | Node::Or(lhs, rhs) => {
+                lhs.uses_field(name) || rhs.uses_field(name)
+            }
+            Node::Not(node) => node.uses_field(name),
+            Node::Compare { field, .. } => field == name,
+        }
+    }
+}
+
+// === impl Literal ===
+
+impl Literal {
+    fn compare(&self, value: &Value<'_>, op: Op) -> bool {
+        if let LiteralKind::Regex(ref regex) = self.kind {
+            let is_match = regex.is_match(&value.to_string());
+            return (op == Op::Match) == is_match;
+        }
+
+        let ordering = match (value, &self.kind) {
+            (Value::Bool(value), LiteralKind::Bool(literal)) => Some(value.cmp(literal)),
+            (Value::Number(value), LiteralKind::Number(literal)) => value.partial_cmp(literal),
+            (Value::Duration(value), LiteralKind::Duration(literal)) => Some(value.cmp(literal)),
+            (Value::Str(value), _) => Some(value.as_ref().cmp(self.text.as_str())),
+            // Values of different types can't be compared.
+            _ => None,
+        };
+        match ordering {
+            Some(ordering) => match op {
+                Op::Eq => ordering == Ordering::Equal,
+                Op::Ne => ordering != Ordering::Equal,
+                Op::Lt => ordering == Ordering::Less,
+                Op::Le => ordering != Ordering::Greater,
+                Op::Gt => ordering == Ordering::Greater,
+                Op::Ge => ordering != Ordering::Less,
+                Op::Match | Op::NotMatch => unreachable!("regex literals are matched above"),
+            },
+            None => op == Op::Ne,
+        }
+    }
+}
+
+// === impl Value ===
+
+impl<'a> Value<'a> {
+    /// Returns the value of a formatted field or attribute, inferring its type
+    /// from its text.
+    fn parse(text: &'a str) -> Self {
+        if let Ok(value) = text.parse::<bool>() {
+            return Value::Bool(value);
+        }
+        match text.parse::<f64>() {
+            Ok(value) => Value::Number(value),
+            Err(_) => Value::Str(Cow::Borrowed(text)),
+        }
+    }
+
+    pub(crate) fn str(text: &'a str) -> Self {
+        Value::Str(Cow::Borrowed(text))
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => fmt::Display::fmt(value, f),
+            Value::Number(value) => fmt::Display::fmt(value, f),
+            Value::Duration(value) => fmt::Debug::fmt(value, f),
+            Value::Str(value) => f.write_str(value),
+        }
+    }
+}
+
+/// Returns `s` quoted as a string in a filter expression.
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Returns the value of the formatted field or attribute called `name`, if
+/// there is one.
+///
+/// Fields are formatted as a name span, a `=` span, and a value span, which
+/// may be followed by a unit.
+fn formatted_field<'a>(fields: &'a [Vec<Span<'static>>], name: &str) -> Option<Value<'a>> {
+    fields.iter().find_map(|field| match field.as_slice() {
+        [key, _, value, ..] if key.content == name => Some(Value::parse(value.content.trim())),
+        _ => None,
+    })
+}
+
+// === impl Subject ===
+
+impl Subject for Task {
+    fn field(&self, name: &str, now: SystemTime) -> Option<Value<'_>> {
+        let value = match name {
+            "id" => Value::Number(self.id() as f64),
+            "name" => Value::str(self.name()?),
+            "state" => Value::str(match self.state() {
+                TaskState::Running => "running",
+                TaskState::Idle => "idle",
+                TaskState::Completed => "completed",
+            }),
+            "target" => Value::str(self.target()),
+            "location" => Value::str(self.location()),
+            "total" => Value::Duration(self.total(now)),
+            "busy" => Value::Duration(self.busy(now)),
+            "idle" => Value::Duration(self.idle(now)),
+            "polls" => Value::Number(self.total_polls() as f64),
+            "wakes" => Value::Number(self.wakes() as f64),
+            "self_wakes" => Value::Number(self.self_wakes() as f64),
+            "wakers" => Value::Number(self.waker_count() as f64),
+            "warnings" => Value::Number(self.warnings().len() as f64),
+            name => return formatted_field(self.formatted_fields(), name),
+        };
+        Some(value)
+    }
+}
+
+impl Subject for Resource {
+    fn field(&self, name: &str, now: SystemTime) -> Option<Value<'_>> {
+        let value = match name {
+            "id" => Value::Number(self.id() as f64),
+            "parent" => Value::str(self.parent_id()),
+            "kind" => Value::str(self.kind()),
+            "type" => Value::str(self.concrete_type()),
+            "target" => Value::str(self.target()),
+            "location" => Value::str(self.location()),
+            "visibility" => Value::str(match self.type_visibility() {
+                TypeVisibility::Public => "public",
+                TypeVisibility::Internal => "internal",
+            }),
+            "total" => Value::Duration(self.total(now)),
+            "dropped" => Value::Bool(self.dropped()),
+            "warnings" => Value::Number(self.warnings().len() as f64),
+            name => return formatted_field(self.formatted_attributes(), name),
+        };
+        Some(value)
+    }
+}
+
+impl Subject for AsyncOp {
+    fn field(&self, name: &str, now: SystemTime) -> Option<Value<'_>> {
+        let value = match name {
+            "id" => Value::Number(self.id() as f64),
+            "parent" => Value::str(self.parent_id()),
+            "resource" => Value::Number(self.resource_id() as f64),
+            "task" => Value::Number(self.task_id()? as f64),
+            "source" => Value::str(self.source()),
+            "total" => Value::Duration(self.total(now)),
+            "busy" => Value::Duration(self.busy(now)),
+            "idle" => Value::Duration(self.idle(now)),
+            "polls" => Value::Number(self.total_polls() as f64),
+            "dropped" => Value::Bool(self.dropped()),
+            "warnings" => Value::Number(self.warnings().len() as f64),
+            name => return formatted_field(self.formatted_attributes(), name),
+        };
+        Some(value)
+    }
+}
+
+// === impl Parser ===
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.eat(&Token::Or) {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while self.eat(&Token::And) {
+            node = Node::And(Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Node::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let node = self.expr()?;
+                if !self.eat(&Token::Close) {
+                    return Err("expected `)`".to_owned());
+                }
+                Ok(node)
+            }
+            Some(Token::Ident(field)) => {
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    Some(token) => {
+                        return Err(format!(
+                            "expected a comparison after `{}`, found {}",
+                            field, token
+                        ))
+                    }
+                    None => return Err(format!("expected a comparison after `{}`", field)),
+                };
+                let value = self.literal(op)?;
+                Ok(Node::Compare { field, op, value })
+            }
+            Some(token) => Err(format!("expected a field name, found {}", token)),
+            None => Err("expected a field name".to_owned()),
+        }
+    }
+
+    fn literal(&mut self, op: Op) -> Result<Literal, String> {
+        let (text, kind) = match self.next() {
+            Some(Token::Str(text)) | Some(Token::Ident(text)) => {
+                let kind = match text.as_str() {
+                    "true" => LiteralKind::Bool(true),
+                    "false" => LiteralKind::Bool(false),
+                    _ => LiteralKind::Str,
+                };
+                (text, kind)
+            }
+            Some(Token::Number(number, None)) => (number.to_string(), LiteralKind::Number(number)),
+            Some(Token::Number(number, Some(unit))) => {
+                let duration = duration(number, &unit)?;
+                (
+                    format!("{}{}", number, unit),
+                    LiteralKind::Duration(duration),
+                )
+            }
+            Some(token) => return Err(format!("expected a value, found {}", token)),
+            None => return Err("expected a value".to_owned()),
+        };
+        let kind = match op {
+            Op::Match | Op::NotMatch => LiteralKind::Regex(
+                Regex::new(&text)
+                    .map_err(|error| format!("invalid regex `{}`: {}", text, error))?,
+            ),
+            _ => kind,
+        };
+        Ok(Literal { text, kind })
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::Number(number, unit) => {
+                write!(f, "`{}{}`", number, unit.as_deref().unwrap_or(""))
+            }
+            Token::Str(string) => write!(f, "`{:?}`", string),
+            Token::Op(op) => {
+                let op = match op {
+                    Op::Eq => "==",
+                    Op::Ne => "!=",
+                    Op::Lt => "<",
+                    Op::Le => "<=",
+                    Op::Gt => ">",
+                    Op::Ge => ">=",
+                    Op::Match => "~",
+                    Op::NotMatch => "!~",
+                };
+                write!(f, "`{}`", op)
+            }
+            Token::And => f.write_str("`&&`"),
+            Token::Or => f.write_str("`||`"),
+            Token::Not => f.write_str("`!`"),
+            Token::Open => f.write_str("`(`"),
+            Token::Close => f.write_str("`)`"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let token = match (c, next) {
+            (c, _) if c.is_whitespace() => continue,
+            ('&', Some('&')) => {
+                chars.next();
+                Token::And
+            }
+            ('|', Some('|')) => {
+                chars.next();
+                Token::Or
+            }
+            ('=', Some('=')) => {
+                chars.next();
+                Token::Op(Op::Eq)
+            }
+            ('!', Some('=')) => {
+                chars.next();
+                Token::Op(Op::Ne)
+            }
+            ('!', Some('~')) => {
+                chars.next();
+                Token::Op(Op::NotMatch)
+            }
+            ('<', Some('=')) => {
+                chars.next();
+                Token::Op(Op::Le)
+            }
+            ('>', Some('=')) => {
+                chars.next();
+                Token::Op(Op::Ge)
+            }
+            ('!', _) => Token::Not,
+            ('<', _) => Token::Op(Op::Lt),
+            ('>', _) => Token::Op(Op::Gt),
+            ('~', _) => Token::Op(Op::Match),
+            ('(', _) => Token::Open,
+            (')', _) => Token::Close,
+            ('"', _) => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => string.push(c),
+                            None => return Err("unterminated string".to_owned()),
+                        },
+                        Some((_, c)) => string.push(c),
+                        None => return Err("unterminated string".to_owned()),
+                    }
+                }
+                Token::Str(string)
+            }
+            // A `-` followed by a digit starts a negative number, rather than
+            // an identifier.
+            (c, next)
+                if c.is_ascii_digit()
+                    || (c == '-' && next.map_or(false, |c| c.is_ascii_digit())) =>
+            {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let number = s[start..end]
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number `{}`", &s[start..end]))?;
+                let mut unit = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !c.is_alphabetic() {
+                        break;
+                    }
+                    unit.push(c);
+                    chars.next();
+                }
+                Token::Number(number, Some(unit).filter(|unit| !unit.is_empty()))
+            }
+            (c, _) if is_ident_char(c) => {
+                let mut ident = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !is_ident_char(c) {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            (c, _) => return Err(format!("unexpected `{}`", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || c == ':'
+}
+
+fn duration(number: f64, unit: &str) -> Result<Duration, String> {
+    let secs = match unit {
+        "ns" => number / 1_000_000_000.0,
+        "us" | "µs" => number / 1_000_000.0,
+        "ms" => number / 1_000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 60.0 * 60.0,
+        unit => return Err(format!("unknown duration unit `{}`", unit)),
+    };
+    // `Duration::from_secs_f64` panics on values that don't fit in a
+    // `Duration`, and expressions are parsed as they are typed.
+    if secs < 0.0 {
+        return Err(format!("duration `{}{}` is negative", number, unit));
+    }
+    if secs >= Duration::MAX.as_secs_f64() {
+        return Err(format!("duration `{}{}` is too long", number, unit));
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A subject with the given fields.
+    struct Fields(Vec<(&'static str, Value<'static>)>);
+
+    impl Subject for Fields {
+        fn field(&self, name: &str, _: SystemTime) -> Option<Value<'_>> {
+            self.0
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    fn subject() -> Fields {
+        Fields(vec![
+            ("state", Value::str("idle")),
+            ("name", Value::str("worker-1")),
+            ("polls", Value::Number(0.0)),
+            ("busy", Value::Duration(Duration::from_millis(1500))),
+            ("dropped", Value::Bool(false)),
+        ])
+    }
+
+    fn matches(expr: &str) -> bool {
+        let expr = expr.parse::<Expr>().unwrap();
+        expr.matches(&subject(), SystemTime::UNIX_EPOCH)
+    }
+
+    fn parse_literal(text: &str, op: Op) -> Literal {
+        let mut parser = Parser {
+            tokens: tokenize(text).unwrap(),
+            pos: 0,
+        };
+        parser.literal(op).unwrap()
+    }
+
+    #[test]
+    fn tokenizes_comparisons() {
+        assert_eq!(
+            tokenize("state == idle && polls >= 1000 || !(busy < 1.5ms)").unwrap(),
+            vec![
+                Token::Ident("state".to_owned()),
+                Token::Op(Op::Eq),
+                Token::Ident("idle".to_owned()),
+                Token::And,
+                Token::Ident("polls".to_owned()),
+                Token::Op(Op::Ge),
+                Token::Number(1000.0, None),
+                Token::Or,
+                Token::Not,
+                Token::Open,
+                Token::Ident("busy".to_owned()),
+                Token::Op(Op::Lt),
+                Token::Number(1.5, Some("ms".to_owned())),
+                Token::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_strings() {
+        assert_eq!(
+            tokenize(r#"location ~ "a \"b\" c""#).unwrap(),
+            vec![
+                Token::Ident("location".to_owned()),
+                Token::Op(Op::Match),
+                Token::Str(r#"a "b" c"#.to_owned()),
+            ]
+        );
+        assert!(tokenize(r#"name == "worker"#).is_err());
+    }
+
+    #[test]
+    fn tokenizes_negative_numbers() {
+        assert_eq!(
+            tokenize("polls > -1").unwrap(),
+            vec![
+                Token::Ident("polls".to_owned()),
+                Token::Op(Op::Gt),
+                Token::Number(-1.0, None),
+            ]
+        );
+        // A `-` inside an identifier is part of it.
+        assert_eq!(
+            tokenize("worker-1").unwrap(),
+            vec![Token::Ident("worker-1".to_owned())]
+        );
+    }
+
+    #[test]
+    fn rejects_unexpected_characters() {
+        assert!(tokenize("polls # 1").is_err());
+    }
+
+    #[test]
+    fn parses_precedence() {
+        // `&&` binds more tightly than `||`.
+        assert!(matches("state == idle || polls > 1 && polls > 2"));
+        assert!(!matches("(state == idle || polls > 1) && polls > 2"));
+        assert!(matches("!(polls > 1) && !dropped == true"));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expr in [
+            "",
+            "polls",
+            "polls >",
+            "polls 1",
+            "(polls > 1",
+            "polls > 1)",
+            "polls > 1 &&",
+            "== 1",
+            "name ~ \"(\"",
+        ] {
+            assert!(expr.parse::<Expr>().is_err(), "{:?} should not parse", expr);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for expr in ["busy > 1y", "busy > -1s", "busy > 99999999999999999999h"] {
+            assert!(expr.parse::<Expr>().is_err(), "{:?} should not parse", expr);
+        }
+    }
+
+    #[test]
+    fn compares_numbers() {
+        assert!(matches("polls > -1"));
+        assert!(matches("polls == 0"));
+        assert!(matches("polls <= 0"));
+        assert!(!matches("polls < 0"));
+        assert!(!matches("polls != 0"));
+    }
+
+    #[test]
+    fn compares_durations() {
+        assert!(matches("busy > 1s"));
+        assert!(matches("busy == 1500ms"));
+        assert!(matches("busy < 1m"));
+        assert!(!matches("busy >= 2s"));
+    }
+
+    #[test]
+    fn compares_strings() {
+        assert!(matches("state == idle"));
+        assert!(matches(r#"name == "worker-1""#));
+        assert!(matches("name != worker-2"));
+        assert!(matches(r#"name ~ "^work""#));
+        assert!(matches(r#"name !~ "^idle""#));
+        assert!(!matches(r#"name ~ "^idle""#));
+    }
+
+    #[test]
+    fn compares_values_of_different_types() {
+        let literal = parse_literal("1s", Op::Eq);
+        let number = Value::Number(1.0);
+        for op in [Op::Eq, Op::Lt, Op::Le, Op::Gt, Op::Ge] {
+            assert!(!literal.compare(&number, op), "{:?}", op);
+        }
+        assert!(literal.compare(&number, Op::Ne));
+
+        // String fields are compared with the literal as written.
+        let literal = parse_literal("10", Op::Eq);
+        assert!(literal.compare(&Value::str("10"), Op::Eq));
+        assert!(literal.compare(&Value::str("9"), Op::Gt));
+    }
+
+    #[test]
+    fn compares_bools() {
+        assert!(parse_literal("false", Op::Eq).compare(&Value::Bool(false), Op::Eq));
+        assert!(parse_literal("true", Op::Eq).compare(&Value::Bool(false), Op::Lt));
+        assert!(matches("dropped == false"));
+    }
+
+    #[test]
+    fn missing_fields_only_match_negations() {
+        assert!(!matches("target == tokio"));
+        assert!(!matches("target ~ tokio"));
+        assert!(matches("target != tokio"));
+        assert!(matches("target !~ tokio"));
+    }
+
+    #[test]
+    fn finds_used_fields() {
+        let expr = "state == idle || !(warnings > 0 && polls > 1)"
+            .parse::<Expr>()
+            .unwrap();
+        assert!(expr.uses_field("warnings"));
+        assert!(expr.uses_field("state"));
+        assert!(!expr.uses_field("idle"));
+    }
+}
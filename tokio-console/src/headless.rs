@@ -0,0 +1,289 @@
+//! Streams the console's state as JSON, rather than displaying it in the
+//! terminal UI.
+use crate::{
+    conn::Connection,
+    state::{
+        async_ops::AsyncOpRef,
+        resources::ResourceRef,
+        tasks::{TaskRef, TaskState},
+        State,
+    },
+    util::{serialize_secs, serialize_time},
+    view,
+};
+use color_eyre::eyre::eyre;
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::mpsc;
+use tui::text::Span;
+
+/// A snapshot of the task, resource, and async op tables, written as a single
+/// line of JSON.
+#[derive(Debug, Serialize)]
+struct Snapshot {
+    /// The time of the most recent update from the remote process.
+    #[serde(serialize_with = "serialize_time")]
+    at: SystemTime,
+    tasks: Vec<TaskRow>,
+    resources: Vec<ResourceRow>,
+    async_ops: Vec<AsyncOpRow>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskRow {
+    id: u64,
+    name: Option<String>,
+    state: &'static str,
+    target: String,
+    location: String,
+    fields: Vec<String>,
+    #[serde(serialize_with = "serialize_secs")]
+    total: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    busy: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    idle: Duration,
+    polls: u64,
+    wakes: u64,
+    self_wakes: u64,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ResourceRow {
+    id: u64,
+    parent_id: String,
+    kind: String,
+    concrete_type: String,
+    target: String,
+    location: String,
+    attributes: Vec<String>,
+    #[serde(serialize_with = "serialize_secs")]
+    total: Duration,
+    dropped: bool,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AsyncOpRow {
+    id: u64,
+    parent_id: String,
+    resource_id: u64,
+    task_id: Option<u64>,
+    source: String,
+    attributes: Vec<String>,
+    #[serde(serialize_with = "serialize_secs")]
+    total: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    busy: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    idle: Duration,
+    polls: u64,
+    dropped: bool,
+    warnings: Vec<String>,
+}
+
+/// The rows of each table, in the order in which they were first seen.
+#[derive(Default)]
+struct Tables {
+    tasks: Vec<TaskRef>,
+    resources: Vec<ResourceRef>,
+    async_ops: Vec<AsyncOpRef>,
+}
+
+/// Applies updates from `conn` to `state`, and writes a snapshot of the state
+/// to stdout every `interval`, until stdout is closed.
+pub(crate) async fn run(
+    mut conn: Connection,
+    mut state: State,
+    styles: view::Styles,
+    interval: Duration,
+) -> color_eyre::Result<()> {
+    let mut tables = Tables::default();
+    let mut ticks = tokio::time::interval(interval);
+    // Don't emit a burst of identical snapshots if the process is suspended.
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let stdout = io::stdout();
+
+    // Receive updates on a separate task, because `next_update` sleeps
+    // between reconnection attempts, and the sleep would start over every
+    // time a tick cancelled it.
+    let (updates_tx, mut updates) = mpsc::channel(1);
+    tokio::spawn(async move {
+        loop {
+            let update = conn.next_update().await;
+            if updates_tx.send(update).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => {
+                let update = match update {
+                    Some(update) => update,
+                    None => return Err(eyre!("connection task ended unexpectedly")),
+                };
+                state.update(&styles, &view::ViewState::TasksList, update);
+                // New rows must be taken after every update, as they are
+                // discarded by the next one.
+                tables.take_new(&mut state);
+            }
+            _ = ticks.tick() => {
+                let snapshot = match tables.snapshot(&mut state) {
+                    Some(snapshot) => snapshot,
+                    // If we have never gotten an update yet, there's nothing
+                    // to write.
+                    None => continue,
+                };
+                let mut stdout = stdout.lock();
+                let written = serde_json::to_writer(&mut stdout, &snapshot)
+                    .map_err(io::Error::from)
+                    .and_then(|_| writeln!(stdout))
+                    .and_then(|_| stdout.flush());
+                match written {
+                    Ok(()) => {}
+                    // Whatever was reading our output has gone away, such as
+                    // `head` exiting.
+                    Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {
+                        tracing::debug!("stdout closed");
+                        return Ok(());
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+                state.retain_active();
+            }
+        }
+    }
+}
+
+// === impl Tables ===
+
+impl Tables {
+    fn take_new(&mut self, state: &mut State) {
+        self.tasks.extend(state.tasks_state_mut().take_new_tasks());
+        self.resources
+            .extend(state.resources_state_mut().take_new_resources());
+        self.async_ops
+            .extend(state.async_ops_state_mut().take_new_async_ops());
+    }
+
+    fn snapshot(&mut self, state: &mut State) -> Option<Snapshot> {
+        let now = state.last_updated_at()?;
+
+        self.tasks.retain(|task| task.upgrade().is_some());
+        self.resources
+            .retain(|resource| resource.upgrade().is_some());
+        self.async_ops.retain(|op| op.upgrade().is_some());
+
+        let tasks = self
+            .tasks
+            .iter()
+            .filter_map(|task| {
+                let task = task.upgrade()?;
+                let task = task.borrow();
+                Some(TaskRow {
+                    id: task.id(),
+                    name: task.name().map(String::from),
+                    state: match task.state() {
+                        TaskState::Running => "running",
+                        TaskState::Idle => "idle",
+                        TaskState::Completed => "completed",
+                    },
+                    target: task.target().to_string(),
+                    location: task.location().trim().to_string(),
+                    fields: to_strings(task.formatted_fields()),
+                    total: task.total(now),
+                    busy: task.busy(now),
+                    idle: task.idle(now),
+                    polls: task.total_polls(),
+                    wakes: task.wakes(),
+                    self_wakes: task.self_wakes(),
+                    warnings: task
+                        .warnings()
+                        .iter()
+                        .map(|warning| warning.format(&task, now))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        let resources = self
+            .resources
+            .iter()
+            .filter_map(|resource| {
+                let resource = resource.upgrade()?;
+                let resource = resource.borrow();
+                Some(ResourceRow {
+                    id: resource.id(),
+                    parent_id: resource.parent_id().to_string(),
+                    kind: resource.kind().to_string(),
+                    concrete_type: resource.concrete_type().to_string(),
+                    target: resource.target().to_string(),
+                    location: resource.location().trim().to_string(),
+                    attributes: to_strings(resource.formatted_attributes()),
+                    total: resource.total(now),
+                    dropped: resource.dropped(),
+                    warnings: resource
+                        .warnings()
+                        .iter()
+                        .map(|warning| warning.format(&resource, now))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        let async_ops = self
+            .async_ops
+            .iter()
+            .filter_map(|op| {
+                let op = op.upgrade()?;
+                let op = op.borrow();
+                Some(AsyncOpRow {
+                    id: op.id(),
+                    parent_id: op.parent_id().to_string(),
+                    resource_id: op.resource_id(),
+                    task_id: op.task_id(),
+                    source: op.source().to_string(),
+                    attributes: to_strings(op.formatted_attributes()),
+                    total: op.total(now),
+                    busy: op.busy(now),
+                    idle: op.idle(now),
+                    polls: op.total_polls(),
+                    dropped: op.dropped(),
+                    warnings: op
+                        .warnings()
+                        .iter()
+                        .map(|warning| warning.format(&op, now))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Some(Snapshot {
+            at: now,
+            tasks,
+            resources,
+            async_ops,
+        })
+    }
+}
+
+/// Flattens formatted fields or attributes, each of which is rendered as a
+/// sequence of styled spans, into plain strings.
+fn to_strings(formatted: &[Vec<Span<'static>>]) -> Vec<String> {
+    formatted
+        .iter()
+        .map(|spans| {
+            let string = spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>();
+            string.trim().to_string()
+        })
+        .collect()
+}
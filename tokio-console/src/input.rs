@@ -4,22 +4,26 @@
 pub use crossterm::event::*;
 
 pub fn should_quit(input: &Event) -> bool {
-    use Event::*;
-    use KeyCode::*;
-    match input {
-        Key(KeyEvent {
-            code: Char('q'), ..
-        }) => true,
-        Key(KeyEvent {
-            code: Char('c'),
-            modifiers,
+    let is_q = matches!(
+        input,
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('q'),
+            ..
         })
-        | Key(KeyEvent {
-            code: Char('d'),
+    );
+    is_q || is_interrupt(input)
+}
+
+/// Returns `true` if the input should quit the console even while text is
+/// being entered.
+pub(crate) fn is_interrupt(input: &Event) -> bool {
+    matches!(
+        input,
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c') | KeyCode::Char('d'),
             modifiers,
-        }) if modifiers.contains(KeyModifiers::CONTROL) => true,
-        _ => false,
-    }
+        }) if modifiers.contains(KeyModifiers::CONTROL)
+    )
 }
 
 pub(crate) fn is_space(input: &Event) -> bool {
@@ -4,6 +4,7 @@ use state::State;
 
 use clap::Parser as Clap;
 use futures::stream::StreamExt;
+use std::{io, time::Duration};
 use tokio::sync::{mpsc, watch};
 use tui::{
     layout::{Constraint, Direction, Layout},
@@ -14,10 +15,15 @@ use tui::{
 
 use crate::view::{bold, UpdateKind};
 
+mod analyze;
+mod check;
 mod config;
 mod conn;
+mod filter;
+mod headless;
 mod input;
 mod intern;
+mod replay;
 mod state;
 mod term;
 mod util;
@@ -28,15 +34,44 @@ mod warnings;
 async fn main() -> color_eyre::Result<()> {
     let mut args = config::Config::parse();
     let retain_for = args.retain_for();
+    let history = args.history.into();
     args.trace_init()?;
     tracing::debug!(?args.target_addr, ?args.view_options);
 
     let styles = view::Styles::from_config(args.view_options);
     styles.error_init()?;
+    let lints = args.lint_options.lints()?;
+
+    match args.subcommand {
+        Some(config::Command::Replay(replay)) => {
+            return replay::run(replay, styles, &lints, retain_for, history).await;
+        }
+        Some(config::Command::Analyze(analyze)) => {
+            return analyze::run(analyze, &lints, &styles);
+        }
+        Some(config::Command::Check(check)) => {
+            if !check::run(check, &lints, &styles).await? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
 
     let target = args.target_addr;
     tracing::info!(?target, "using target addr");
 
+    if args.output == config::Output::Json {
+        let conn = conn::Connection::new(target);
+        return headless::run(
+            conn,
+            new_state(&lints, retain_for),
+            styles,
+            args.interval.into(),
+        )
+        .await;
+    }
+
     let (mut terminal, _cleanup) = term::init_crossterm()?;
     terminal.clear()?;
     let mut conn = conn::Connection::new(target);
@@ -45,14 +80,7 @@ async fn main() -> color_eyre::Result<()> {
     // A channel to send the task details update stream (no need to keep outdated details in the memory)
     let (details_tx, mut details_rx) = mpsc::channel::<TaskDetails>(2);
 
-    let mut state = State::default()
-        // TODO(eliza): allow configuring the list of linters via the
-        // CLI/possibly a config file?
-        .with_task_linters(vec![
-            warnings::Linter::new(warnings::SelfWakePercent::default()),
-            warnings::Linter::new(warnings::LostWaker),
-        ])
-        .with_retain_for(retain_for);
+    let mut state = new_state(&lints, retain_for).with_history_window(history);
     let mut input = input::EventStream::new();
     let mut view = view::View::new(styles);
 
@@ -62,11 +90,13 @@ async fn main() -> color_eyre::Result<()> {
                 let input = input
                     .ok_or_else(|| eyre!("keyboard input stream ended early"))
                     .with_section(|| "this is probably a bug".header("Note:"))??;
-                if input::should_quit(&input) {
+                // While a filter is being typed, `q` and space are part of it.
+                let editing = view.is_editing_filter();
+                if input::is_interrupt(&input) || (!editing && input::should_quit(&input)) {
                     return Ok(());
                 }
 
-                if input::is_space(&input) {
+                if !editing && input::is_space(&input) {
                     if state.is_paused() {
                         conn.resume().await;
                         state.resume();
@@ -107,43 +137,76 @@ async fn main() -> color_eyre::Result<()> {
                 }
             },
         }
-        terminal.draw(|f| {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(0)
-                .constraints(
-                    [
-                        Constraint::Length(1),
-                        Constraint::Length(1),
-                        Constraint::Percentage(95),
-                    ]
-                    .as_ref(),
-                )
-                .split(f.size());
-
-            let mut header_text = conn.render(&view.styles);
-            if state.is_paused() {
-                header_text
-                    .0
-                    .push(Span::styled(" PAUSED", view.styles.fg(Color::Red)));
-            }
-            let header = Paragraph::new(header_text).wrap(Wrap { trim: true });
-            let view_controls = Paragraph::new(Spans::from(vec![
-                Span::raw("views: "),
-                bold("t"),
-                Span::raw(" = tasks, "),
-                bold("r"),
-                Span::raw(" = resources"),
-            ]))
-            .wrap(Wrap { trim: true });
-
-            f.render_widget(header, chunks[0]);
-            f.render_widget(view_controls, chunks[1]);
-            view.render(f, chunks[2], &mut state);
-        })?;
+        let mut header_text = conn.render(&view.styles);
+        if state.is_paused() {
+            header_text
+                .0
+                .push(Span::styled(" PAUSED", view.styles.fg(Color::Red)));
+        }
+        draw(
+            &mut terminal,
+            vec![header_text, view_controls()],
+            &mut view,
+            &mut state,
+        )?;
     }
 }
 
+fn new_state(lints: &config::Lints, retain_for: Option<Duration>) -> State {
+    State::default()
+        .with_task_linters(lints.task_linters())
+        .with_resource_linters(lints.resource_linters())
+        .with_async_op_linters(lints.async_op_linters())
+        .with_retain_for(retain_for)
+}
+
+/// Draws the current view, below the given lines of header text.
+fn draw<B: tui::backend::Backend>(
+    terminal: &mut tui::Terminal<B>,
+    header: Vec<Spans<'_>>,
+    view: &mut view::View,
+    state: &mut State,
+) -> io::Result<()> {
+    terminal.draw(|f| {
+        let constraints = header
+            .iter()
+            .map(|_| Constraint::Length(1))
+            .chain(Some(Constraint::Percentage(95)))
+            .collect::<Vec<_>>();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(0)
+            .constraints(constraints)
+            .split(f.size());
+
+        let lines = header.len();
+        for (text, area) in header.into_iter().zip(chunks.iter()) {
+            let text = Paragraph::new(text).wrap(Wrap { trim: true });
+            f.render_widget(text, *area);
+        }
+        view.render(f, chunks[lines], state);
+    })?;
+    Ok(())
+}
+
+fn view_controls() -> Spans<'static> {
+    Spans::from(vec![
+        Span::raw("views: "),
+        bold("t"),
+        Span::raw(" = tasks, "),
+        bold("r"),
+        Span::raw(" = resources, "),
+        bold("w"),
+        Span::raw(" = warnings, "),
+        bold("s"),
+        Span::raw(" = spawn locations, "),
+        bold("p"),
+        Span::raw(" = resource tree, "),
+        bold("o"),
+        Span::raw(" = overview"),
+    ])
+}
+
 /// Given the task details stream for the given task id, sends the updates
 /// to the `details_tx` channel until the currently-viewed task changes.
 ///
@@ -0,0 +1,648 @@
+use console_api as proto;
+use hdrhistogram::{serialization::Serializer, serialization::V2Serializer, Histogram};
+use proto::recording::event::Event as Kind;
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    time::{Duration, SystemTime},
+};
+
+/// Reconstructs the state of a recorded process from its recorded events.
+///
+/// This does the same bookkeeping as `console-subscriber`'s aggregator does
+/// for a live process, so that the updates it produces can be handled exactly
+/// like those received from a live process.
+#[derive(Clone, Default)]
+pub(crate) struct Aggregator {
+    /// All callsites recorded so far.
+    ///
+    /// This is sent as part of the initial state.
+    all_metadata: Vec<proto::register_metadata::NewMetadata>,
+    new_metadata: Vec<proto::register_metadata::NewMetadata>,
+
+    tasks: HashMap<u64, Task>,
+    resources: HashMap<u64, Resource>,
+    async_ops: HashMap<u64, AsyncOp>,
+
+    /// The IDs of tasks, resources and async ops created since the last
+    /// update, in the order in which they were created.
+    new_tasks: Vec<u64>,
+    new_resources: Vec<u64>,
+    new_async_ops: Vec<u64>,
+
+    /// *All* poll ops recorded so far.
+    ///
+    /// This is sent as part of the initial state.
+    all_poll_ops: Vec<proto::resources::PollOp>,
+    new_poll_ops: Vec<proto::resources::PollOp>,
+}
+
+#[derive(Clone)]
+struct Task {
+    task: proto::tasks::Task,
+    stats: TaskStats,
+    is_dirty: bool,
+}
+
+#[derive(Clone)]
+struct TaskStats {
+    created_at: SystemTime,
+    dropped_at: Option<SystemTime>,
+    poll_stats: PollStats,
+    poll_times_histogram: Histogram<u64>,
+    wakes: u64,
+    waker_clones: u64,
+    waker_drops: u64,
+    self_wakes: u64,
+    last_wake: Option<SystemTime>,
+}
+
+#[derive(Clone)]
+struct Resource {
+    resource: proto::resources::Resource,
+    stats: ResourceStats,
+    is_dirty: bool,
+}
+
+#[derive(Clone)]
+struct AsyncOp {
+    async_op: proto::async_ops::AsyncOp,
+    stats: ResourceStats,
+    poll_stats: PollStats,
+    task_id: Option<u64>,
+    is_dirty: bool,
+}
+
+#[derive(Clone)]
+struct ResourceStats {
+    created_at: SystemTime,
+    dropped_at: Option<SystemTime>,
+    /// Attributes are keyed by the ID of the span they were updated on, as a
+    /// resource may inherit attributes with the same name from its children.
+    attributes: HashMap<(u64, proto::field::Name), proto::Attribute>,
+    inherit_child_attrs: bool,
+    parent_id: Option<u64>,
+}
+
+#[derive(Clone, Default)]
+struct PollStats {
+    current_polls: u64,
+    polls: u64,
+    first_poll: Option<SystemTime>,
+    last_poll_started: Option<SystemTime>,
+    last_poll_ended: Option<SystemTime>,
+    busy_time: Duration,
+}
+
+// === impl Aggregator ===
+
+impl Aggregator {
+    /// How long closed tasks, resources and async ops are kept after they
+    /// were closed, matching `console-subscriber`'s default retention.
+    const RETENTION: Duration = Duration::from_secs(60 * 60);
+
+    /// Applies a recorded event to the reconstructed state.
+    pub(crate) fn apply(&mut self, event: proto::recording::Event) {
+        let at = match event.at.clone().map(SystemTime::try_from) {
+            Some(Ok(at)) => at,
+            _ => {
+                tracing::warn!(?event, "recorded event has no timestamp, skipping");
+                return;
+            }
+        };
+        let event = match event.event {
+            Some(event) => event,
+            None => return,
+        };
+
+        match event {
+            Kind::Metadata(metadata) => {
+                self.all_metadata.push(metadata.clone());
+                self.new_metadata.push(metadata);
+            }
+            Kind::Spawn(task) => {
+                let id = match task.id {
+                    Some(ref id) => id.id,
+                    None => return,
+                };
+                self.tasks.insert(
+                    id,
+                    Task {
+                        task,
+                        stats: TaskStats::new(at),
+                        is_dirty: true,
+                    },
+                );
+                self.new_tasks.push(id);
+            }
+            Kind::Resource(proto::recording::Resource {
+                resource: Some(resource),
+                inherit_child_attrs,
+            }) => {
+                let id = match resource.id {
+                    Some(ref id) => id.id,
+                    None => return,
+                };
+                let parent_id = resource.parent_resource_id.as_ref().map(|id| id.id);
+                self.resources.insert(
+                    id,
+                    Resource {
+                        resource,
+                        stats: ResourceStats::new(at, inherit_child_attrs, parent_id),
+                        is_dirty: true,
+                    },
+                );
+                self.new_resources.push(id);
+            }
+            Kind::AsyncOp(proto::recording::AsyncOp {
+                async_op: Some(async_op),
+                inherit_child_attrs,
+            }) => {
+                let id = match async_op.id {
+                    Some(ref id) => id.id,
+                    None => return,
+                };
+                let parent_id = async_op.parent_async_op_id.as_ref().map(|id| id.id);
+                self.async_ops.insert(
+                    id,
+                    AsyncOp {
+                        async_op,
+                        stats: ResourceStats::new(at, inherit_child_attrs, parent_id),
+                        poll_stats: PollStats::default(),
+                        task_id: None,
+                        is_dirty: true,
+                    },
+                );
+                self.new_async_ops.push(id);
+            }
+            Kind::Resource(_) | Kind::AsyncOp(_) => {}
+            Kind::PollOp(poll_op) => {
+                let async_op_id = poll_op.async_op_id.as_ref().map(|id| id.id);
+                if let Some(async_op) = async_op_id.and_then(|id| self.async_ops.get_mut(&id)) {
+                    async_op.task_id = poll_op.task_id.as_ref().map(|id| id.id);
+                    async_op.is_dirty = true;
+                }
+                self.all_poll_ops.push(poll_op.clone());
+                self.new_poll_ops.push(poll_op);
+            }
+            Kind::StateUpdate(update) => self.update_attribute(update),
+            Kind::Enter(proto::SpanId { id }) => {
+                if let Some(task) = self.tasks.get_mut(&id) {
+                    task.stats.poll_stats.start_poll(at);
+                    task.is_dirty = true;
+                } else if let Some(async_op) = self.async_ops.get_mut(&id) {
+                    async_op.poll_stats.start_poll(at);
+                    async_op.is_dirty = true;
+                }
+            }
+            Kind::Exit(proto::SpanId { id }) => {
+                if let Some(task) = self.tasks.get_mut(&id) {
+                    let histogram = &mut task.stats.poll_times_histogram;
+                    if let Some(elapsed) = task.stats.poll_stats.end_poll(at) {
+                        let elapsed_ns = elapsed.as_nanos().try_into().unwrap_or(u64::MAX);
+                        histogram
+                            .record(elapsed_ns)
+                            .expect("poll time histogram should auto-resize");
+                    }
+                    task.is_dirty = true;
+                } else if let Some(async_op) = self.async_ops.get_mut(&id) {
+                    async_op.poll_stats.end_poll(at);
+                    async_op.is_dirty = true;
+                }
+            }
+            Kind::Close(proto::SpanId { id }) => {
+                if let Some(task) = self.tasks.get_mut(&id) {
+                    task.stats.dropped_at.get_or_insert(at);
+                    task.is_dirty = true;
+                } else if let Some(resource) = self.resources.get_mut(&id) {
+                    resource.stats.dropped_at.get_or_insert(at);
+                    resource.is_dirty = true;
+                } else if let Some(async_op) = self.async_ops.get_mut(&id) {
+                    async_op.stats.dropped_at.get_or_insert(at);
+                    async_op.is_dirty = true;
+                }
+            }
+            Kind::Waker(waker) => {
+                let id = match waker.id {
+                    Some(ref id) => id.id,
+                    None => return,
+                };
+                if let Some(task) = self.tasks.get_mut(&id) {
+                    task.stats.record_wake_op(&waker, at);
+                    task.is_dirty = true;
+                }
+            }
+            Kind::Trailer(_) => {}
+        }
+    }
+
+    /// Returns an update containing the entire current state, for a client
+    /// which has not received any previous updates.
+    pub(crate) fn initial_update(&self, now: SystemTime) -> proto::instrument::Update {
+        proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: self.all_metadata.clone(),
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: self.tasks.values().map(|task| task.task.clone()).collect(),
+                stats_update: self
+                    .tasks
+                    .iter()
+                    .map(|(&id, task)| (id, task.stats.to_proto()))
+                    .collect(),
+                dropped_events: 0,
+            }),
+            resource_update: Some(proto::resources::ResourceUpdate {
+                new_resources: self
+                    .resources
+                    .values()
+                    .map(|resource| resource.resource.clone())
+                    .collect(),
+                stats_update: self
+                    .resources
+                    .iter()
+                    .map(|(&id, resource)| (id, resource.stats.to_proto()))
+                    .collect(),
+                new_poll_ops: self.all_poll_ops.clone(),
+                dropped_events: 0,
+            }),
+            async_op_update: Some(proto::async_ops::AsyncOpUpdate {
+                new_async_ops: self
+                    .async_ops
+                    .values()
+                    .map(|async_op| async_op.async_op.clone())
+                    .collect(),
+                stats_update: self
+                    .async_ops
+                    .iter()
+                    .map(|(&id, async_op)| (id, async_op.to_proto()))
+                    .collect(),
+                dropped_events: 0,
+            }),
+        }
+    }
+
+    /// Returns an update containing the entire state, for the first update
+    /// after the state is replaced, such as when the recording is rewound.
+    pub(crate) fn reset_update(&mut self, now: SystemTime) -> proto::instrument::Update {
+        let update = self.initial_update(now);
+        // Everything that changed since the last update is part of the entire
+        // state.
+        self.update(now);
+        update
+    }
+
+    /// Returns an update containing everything that has changed since the
+    /// last update.
+    pub(crate) fn update(&mut self, now: SystemTime) -> proto::instrument::Update {
+        let new_metadata = if !self.new_metadata.is_empty() {
+            Some(proto::RegisterMetadata {
+                metadata: std::mem::take(&mut self.new_metadata),
+            })
+        } else {
+            None
+        };
+
+        let new_tasks = self
+            .new_tasks
+            .drain(..)
+            .filter_map(|id| Some(self.tasks.get(&id)?.task.clone()))
+            .collect();
+        let task_stats = self
+            .tasks
+            .iter_mut()
+            .filter(|(_, task)| task.is_dirty)
+            .map(|(&id, task)| {
+                task.is_dirty = false;
+                (id, task.stats.to_proto())
+            })
+            .collect();
+
+        let new_resources = self
+            .new_resources
+            .drain(..)
+            .filter_map(|id| Some(self.resources.get(&id)?.resource.clone()))
+            .collect();
+        let resource_stats = self
+            .resources
+            .iter_mut()
+            .filter(|(_, resource)| resource.is_dirty)
+            .map(|(&id, resource)| {
+                resource.is_dirty = false;
+                (id, resource.stats.to_proto())
+            })
+            .collect();
+
+        let new_async_ops = self
+            .new_async_ops
+            .drain(..)
+            .filter_map(|id| Some(self.async_ops.get(&id)?.async_op.clone()))
+            .collect();
+        let async_op_stats = self
+            .async_ops
+            .iter_mut()
+            .filter(|(_, async_op)| async_op.is_dirty)
+            .map(|(&id, async_op)| {
+                async_op.is_dirty = false;
+                (id, async_op.to_proto())
+            })
+            .collect();
+
+        self.drop_closed(now);
+
+        proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata,
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks,
+                stats_update: task_stats,
+                dropped_events: 0,
+            }),
+            resource_update: Some(proto::resources::ResourceUpdate {
+                new_resources,
+                stats_update: resource_stats,
+                new_poll_ops: std::mem::take(&mut self.new_poll_ops),
+                dropped_events: 0,
+            }),
+            async_op_update: Some(proto::async_ops::AsyncOpUpdate {
+                new_async_ops,
+                stats_update: async_op_stats,
+                dropped_events: 0,
+            }),
+        }
+    }
+
+    /// Returns the details of the task with the given span ID, if it exists.
+    pub(crate) fn task_details(
+        &self,
+        id: u64,
+        now: SystemTime,
+    ) -> Option<proto::tasks::TaskDetails> {
+        let task = self.tasks.get(&id)?;
+        let mut poll_times_histogram = Vec::new();
+        V2Serializer::new()
+            .serialize(&task.stats.poll_times_histogram, &mut poll_times_histogram)
+            .ok()?;
+        Some(proto::tasks::TaskDetails {
+            task_id: Some(id.into()),
+            now: Some(now.into()),
+            poll_times_histogram: Some(poll_times_histogram),
+        })
+    }
+
+    fn update_attribute(&mut self, update: proto::recording::StateUpdate) {
+        let id = match update.id {
+            Some(ref id) => id.id,
+            None => return,
+        };
+
+        // Attributes updated on a child are also updated on its parent, if the
+        // parent inherits its children's attributes.
+        if let Some(resource) = self.resources.get_mut(&id) {
+            resource.stats.update_attribute(id, &update);
+            resource.is_dirty = true;
+            let parent = resource.stats.parent_id;
+            if let Some(parent) = parent.and_then(|parent| self.resources.get_mut(&parent)) {
+                if parent.stats.inherit_child_attrs {
+                    parent.stats.update_attribute(id, &update);
+                    parent.is_dirty = true;
+                }
+            }
+        } else if let Some(async_op) = self.async_ops.get_mut(&id) {
+            async_op.stats.update_attribute(id, &update);
+            async_op.is_dirty = true;
+            let parent = async_op.stats.parent_id;
+            if let Some(parent) = parent.and_then(|parent| self.async_ops.get_mut(&parent)) {
+                if parent.stats.inherit_child_attrs {
+                    parent.stats.update_attribute(id, &update);
+                    parent.is_dirty = true;
+                }
+            }
+        }
+    }
+
+    /// Drops closed tasks, resources, and async ops that have been sent in an
+    /// update and have been closed for longer than the retention period.
+    fn drop_closed(&mut self, now: SystemTime) {
+        let should_retain = |dropped_at: Option<SystemTime>| match dropped_at {
+            Some(dropped_at) => now
+                .duration_since(dropped_at)
+                .map(|dropped_for| dropped_for <= Self::RETENTION)
+                .unwrap_or(true),
+            None => true,
+        };
+        self.tasks
+            .retain(|_, task| should_retain(task.stats.dropped_at));
+        self.resources
+            .retain(|_, resource| should_retain(resource.stats.dropped_at));
+        self.async_ops
+            .retain(|_, async_op| should_retain(async_op.stats.dropped_at));
+    }
+}
+
+// === impl TaskStats ===
+
+impl TaskStats {
+    fn new(created_at: SystemTime) -> Self {
+        Self {
+            created_at,
+            dropped_at: None,
+            poll_stats: PollStats::default(),
+            // significant figures should be in the [0-5] range and memory usage
+            // grows exponentially with higher a sigfig
+            poll_times_histogram: Histogram::new(2).unwrap(),
+            wakes: 0,
+            waker_clones: 0,
+            waker_drops: 0,
+            self_wakes: 0,
+            last_wake: None,
+        }
+    }
+
+    fn record_wake_op(&mut self, waker: &proto::recording::Waker, at: SystemTime) {
+        use proto::recording::waker::Op;
+        match Op::from_i32(waker.op) {
+            Some(Op::Clone) => self.waker_clones += 1,
+            Some(Op::Drop) => self.waker_drops += 1,
+            Some(Op::WakeByRef) => self.wake(at, waker.self_wake),
+            Some(Op::Wake) => {
+                // Waking by value consumes the waker without dropping it, so
+                // count it as a drop, as `console-subscriber` does.
+                self.waker_drops += 1;
+                self.wake(at, waker.self_wake);
+            }
+            None => tracing::warn!(op = waker.op, "unknown waker op"),
+        }
+    }
+
+    fn wake(&mut self, at: SystemTime, self_wake: bool) {
+        self.last_wake = std::cmp::max(self.last_wake, Some(at));
+        self.wakes += 1;
+        if self_wake {
+            self.self_wakes += 1;
+        }
+    }
+
+    fn to_proto(&self) -> proto::tasks::Stats {
+        proto::tasks::Stats {
+            poll_stats: Some(self.poll_stats.to_proto()),
+            created_at: Some(self.created_at.into()),
+            dropped_at: self.dropped_at.map(Into::into),
+            wakes: self.wakes,
+            waker_clones: self.waker_clones,
+            waker_drops: self.waker_drops,
+            self_wakes: self.self_wakes,
+            last_wake: self.last_wake.map(Into::into),
+        }
+    }
+}
+
+// === impl AsyncOp ===
+
+impl AsyncOp {
+    fn to_proto(&self) -> proto::async_ops::Stats {
+        proto::async_ops::Stats {
+            poll_stats: Some(self.poll_stats.to_proto()),
+            created_at: Some(self.stats.created_at.into()),
+            dropped_at: self.stats.dropped_at.map(Into::into),
+            task_id: self.task_id.map(Into::into),
+            attributes: self.stats.attributes.values().cloned().collect(),
+        }
+    }
+}
+
+// === impl ResourceStats ===
+
+impl ResourceStats {
+    fn new(created_at: SystemTime, inherit_child_attrs: bool, parent_id: Option<u64>) -> Self {
+        Self {
+            created_at,
+            dropped_at: None,
+            attributes: HashMap::new(),
+            inherit_child_attrs,
+            parent_id,
+        }
+    }
+
+    fn update_attribute(&mut self, id: u64, update: &proto::recording::StateUpdate) {
+        use proto::field::Value::*;
+        use proto::recording::state_update::Op;
+
+        let field = match update.field {
+            Some(ref field) => field,
+            None => return,
+        };
+        let name = match field.name {
+            Some(ref name) => name.clone(),
+            None => {
+                tracing::warn!(?field, "field missing name, skipping...");
+                return;
+            }
+        };
+
+        let attribute = self
+            .attributes
+            .entry((id, name))
+            .or_insert_with(|| proto::Attribute {
+                field: None,
+                unit: update.unit.clone(),
+            });
+        let value = match attribute
+            .field
+            .as_mut()
+            .and_then(|field| field.value.as_mut())
+        {
+            Some(value) => value,
+            None => {
+                attribute.field = Some(field.clone());
+                return;
+            }
+        };
+
+        let op = update.op.and_then(Op::from_i32);
+        match (value, field.value.clone()) {
+            (BoolVal(v), Some(BoolVal(upd))) => *v = upd,
+            (StrVal(v), Some(StrVal(upd))) => *v = upd,
+            (DebugVal(v), Some(DebugVal(upd))) => *v = upd,
+            (U64Val(v), Some(U64Val(upd))) => match op {
+                Some(Op::Add) => *v = v.saturating_add(upd),
+                Some(Op::Sub) => *v = v.saturating_sub(upd),
+                Some(Op::Override) => *v = upd,
+                None => tracing::warn!(
+                    "numeric attribute update {:?} needs to have an op field",
+                    field.name
+                ),
+            },
+            (I64Val(v), Some(I64Val(upd))) => match op {
+                Some(Op::Add) => *v = v.saturating_add(upd),
+                Some(Op::Sub) => *v = v.saturating_sub(upd),
+                Some(Op::Override) => *v = upd,
+                None => tracing::warn!(
+                    "numeric attribute update {:?} needs to have an op field",
+                    field.name
+                ),
+            },
+            (val, update) => {
+                tracing::warn!(
+                    "attribute {:?} cannot be updated by update {:?}",
+                    val,
+                    update
+                );
+            }
+        }
+    }
+
+    fn to_proto(&self) -> proto::resources::Stats {
+        proto::resources::Stats {
+            created_at: Some(self.created_at.into()),
+            dropped_at: self.dropped_at.map(Into::into),
+            attributes: self.attributes.values().cloned().collect(),
+        }
+    }
+}
+
+// === impl PollStats ===
+
+impl PollStats {
+    fn start_poll(&mut self, at: SystemTime) {
+        if self.current_polls == 0 {
+            // We are starting the first poll
+            self.first_poll.get_or_insert(at);
+            self.last_poll_started = Some(at);
+            self.polls += 1;
+        }
+        self.current_polls += 1;
+    }
+
+    /// Ends a poll, returning how long it took if it was the last current
+    /// poll.
+    fn end_poll(&mut self, at: SystemTime) -> Option<Duration> {
+        match self.current_polls {
+            // A span may have been entered before the recording started.
+            0 => return None,
+            1 => {}
+            _ => {
+                self.current_polls -= 1;
+                return None;
+            }
+        }
+
+        // We are ending the last current poll
+        self.current_polls = 0;
+        self.last_poll_ended = Some(at);
+        let elapsed = at.duration_since(self.last_poll_started?).ok()?;
+        self.busy_time += elapsed;
+        Some(elapsed)
+    }
+
+    fn to_proto(&self) -> proto::PollStats {
+        proto::PollStats {
+            polls: self.polls,
+            first_poll: self.first_poll.map(Into::into),
+            last_poll_started: self.last_poll_started.map(Into::into),
+            last_poll_ended: self.last_poll_ended.map(Into::into),
+            busy_time: Some(self.busy_time.into()),
+        }
+    }
+}
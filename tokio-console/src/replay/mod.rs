@@ -0,0 +1,237 @@
+//! Replaying recordings written by `console-subscriber`.
+use self::player::Player;
+use crate::{
+    config::{Lints, ReplayArgs},
+    input, term,
+    view::{self, bold, UpdateKind},
+};
+use color_eyre::{eyre::eyre, Help, SectionExt};
+use futures::stream::StreamExt;
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use tui::{
+    style::Color,
+    text::{Span, Spans},
+};
+
+pub(crate) mod aggregator;
+mod player;
+mod server;
+
+/// A position in a recording to move to.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Seek {
+    /// The time at which an event was recorded.
+    At(SystemTime),
+    /// An offset from the start of the recording.
+    Offset(Duration),
+    /// An offset forwards from the current position.
+    Forward(Duration),
+    /// An offset backwards from the current position.
+    Back(Duration),
+}
+
+/// Runs the console, playing back the recording instead of connecting to a
+/// live process.
+pub(crate) async fn run(
+    args: ReplayArgs,
+    styles: view::Styles,
+    lints: &Lints,
+    retain_for: Option<Duration>,
+    history: Duration,
+) -> color_eyre::Result<()> {
+    let mut player = Player::open(args.path.clone(), args.speed)
+        .map_err(|error| eyre!("failed to open recording: {}", error))
+        .with_section(|| args.path.display().to_string().header("Recording:"))?;
+    if let Some(start) = args.start {
+        player.seek(start);
+    }
+    if args.paused {
+        player.toggle_pause();
+    }
+
+    if let Some(addr) = args.serve {
+        return server::serve(player, addr)
+            .await
+            .map_err(|error| eyre!("failed to serve recording: {}", error));
+    }
+
+    let (mut terminal, _cleanup) = term::init_crossterm()?;
+    terminal.clear()?;
+
+    let mut state = crate::new_state(lints, retain_for).with_history_window(history);
+    let mut input = input::EventStream::new();
+    let mut view = view::View::new(styles);
+    // The span ID of the task whose details are being viewed.
+    let mut selected_task = None;
+    // The text entered into the seek prompt, while it is open.
+    let mut prompt: Option<String> = None;
+    let mut error: Option<String> = None;
+
+    loop {
+        tokio::select! { biased;
+            input = input.next() => {
+                let input = input
+                    .ok_or_else(|| eyre!("keyboard input stream ended early"))
+                    .with_section(|| "this is probably a bug".header("Note:"))??;
+                error = None;
+
+                if let Some(text) = prompt.as_mut() {
+                    if let input::Event::Key(key) = input {
+                        match key.code {
+                            input::KeyCode::Enter => {
+                                match text.parse::<Seek>() {
+                                    Ok(seek) => player.seek(seek),
+                                    Err(e) => error = Some(e.to_string()),
+                                }
+                                prompt = None;
+                            }
+                            input::KeyCode::Esc => prompt = None,
+                            input::KeyCode::Backspace => {
+                                text.pop();
+                            }
+                            input::KeyCode::Char(c) => text.push(c),
+                            _ => {}
+                        }
+                    }
+                } else if view.is_editing_filter() {
+                    // While a filter is being typed, every key is part of it.
+                    if input::is_interrupt(&input) {
+                        return Ok(());
+                    }
+                    view.update_input(input, &state);
+                } else {
+                    if input::should_quit(&input) {
+                        return Ok(());
+                    }
+
+                    let code = match input {
+                        input::Event::Key(input::KeyEvent { code, .. }) => Some(code),
+                        _ => None,
+                    };
+                    match code {
+                        Some(input::KeyCode::Char(' ')) => player.toggle_pause(),
+                        Some(input::KeyCode::Char('+')) | Some(input::KeyCode::Char('=')) => player.faster(),
+                        Some(input::KeyCode::Char('-')) => player.slower(),
+                        Some(input::KeyCode::Char('.')) => player.step_forward(),
+                        Some(input::KeyCode::Char(',')) => player.step_back(),
+                        Some(input::KeyCode::Char(']')) => player.seek_forward(),
+                        Some(input::KeyCode::Char('[')) => player.seek_back(),
+                        Some(input::KeyCode::Char(':')) => prompt = Some(String::new()),
+                        _ => match view.update_input(input, &state) {
+                            UpdateKind::SelectTask(task_id) => {
+                                selected_task = Some(task_id);
+                                match player.task_details(task_id) {
+                                    Some(details) => state.update_task_details(details),
+                                    None => state.unset_task_details(),
+                                }
+                            }
+                            UpdateKind::ExitTaskView => {
+                                selected_task = None;
+                                state.unset_task_details();
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+            },
+            frame = player.next_frame() => {
+                if frame.reset {
+                    state = crate::new_state(lints, retain_for).with_history_window(history);
+                }
+                state.update(&view.styles, view.current_view(), frame.update);
+                if frame.reset && !view.reset(&mut state) {
+                    selected_task = None;
+                }
+
+                if let Some(details) = selected_task.and_then(|id| player.task_details(id)) {
+                    state.update_task_details(details);
+                }
+            },
+        }
+
+        if player.is_paused() {
+            state.pause();
+        } else {
+            state.resume();
+        }
+
+        let controls = match (&prompt, &error) {
+            (Some(text), _) => Spans::from(vec![
+                bold("seek to: "),
+                Span::raw(text.clone()),
+                Span::raw("_"),
+            ]),
+            (None, Some(error)) => Spans::from(vec![Span::styled(
+                error.clone(),
+                view.styles.fg(Color::Red),
+            )]),
+            (None, None) => controls(),
+        };
+        let header = vec![
+            player.render(&view.styles),
+            controls,
+            crate::view_controls(),
+        ];
+        crate::draw(&mut terminal, header, &mut view, &mut state)?;
+    }
+}
+
+fn controls() -> Spans<'static> {
+    Spans::from(vec![
+        Span::raw("replay: "),
+        bold("space"),
+        Span::raw(" = play/pause, "),
+        bold("+"),
+        Span::raw("/"),
+        bold("-"),
+        Span::raw(" = speed, "),
+        bold("."),
+        Span::raw("/"),
+        bold(","),
+        Span::raw(" = step, "),
+        bold("]"),
+        Span::raw("/"),
+        bold("["),
+        Span::raw(" = seek 10s, "),
+        bold(":"),
+        Span::raw(" = seek to"),
+    ])
+}
+
+// === impl Seek ===
+
+impl FromStr for Seek {
+    type Err = &'static str;
+
+    /// Parses a position in a recording.
+    ///
+    /// This accepts a duration (such as `1m30s`) from the start of the
+    /// recording, a duration prefixed with `+` or `-` to move relative to the
+    /// current position, or an RFC 3339 timestamp (such as
+    /// `2021-12-01T12:00:00Z`) at which an event was recorded.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str =
+            "expected a duration (such as `1m30s`, `+10s` or `-10s`) or an RFC 3339 timestamp";
+        let s = s.trim();
+        let duration = |s: &str| {
+            s.trim()
+                .parse::<humantime::Duration>()
+                .map(Into::into)
+                .map_err(|_| ERROR)
+        };
+
+        if let Some(forward) = s.strip_prefix('+') {
+            return duration(forward).map(Seek::Forward);
+        }
+        if let Some(back) = s.strip_prefix('-') {
+            return duration(back).map(Seek::Back);
+        }
+        duration(s)
+            .map(Seek::Offset)
+            .or_else(|_| humantime::parse_rfc3339_weak(s).map(Seek::At))
+            .map_err(|_| ERROR)
+    }
+}
@@ -0,0 +1,520 @@
+use super::{aggregator::Aggregator, Seek};
+use console_api as proto;
+use proto::recording::Reader;
+use std::{
+    convert::TryFrom,
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use tokio::time::{self, Interval, MissedTickBehavior};
+use tui::{
+    style::{Color, Modifier},
+    text::{Span, Spans},
+};
+
+/// Plays back a recording, producing updates as though they were received
+/// from the recorded process.
+pub(crate) struct Player {
+    path: PathBuf,
+    reader: Reader,
+    /// The number of events read from `reader`, including `next`.
+    read: u64,
+    /// The next event in the recording, which has not been applied yet.
+    next: Option<proto::recording::Event>,
+    aggregator: Aggregator,
+    /// Snapshots of the state taken periodically while playing, oldest first,
+    /// so that rewinding doesn't replay the recording from the start.
+    checkpoints: Vec<Checkpoint>,
+    /// How much recording time passes between checkpoints.
+    checkpoint_interval: Duration,
+    /// The time at which the recording starts.
+    start: SystemTime,
+    /// The time at which the recording ends, once the end has been reached.
+    end: Option<SystemTime>,
+    /// The current position in the recording.
+    now: SystemTime,
+    speed: f64,
+    paused: bool,
+    /// Whether the next frame must be sent immediately, rather than on the
+    /// next tick, and whether it must replace the state built from previous
+    /// frames.
+    pending: Option<Pending>,
+    /// How often a new frame is produced while playing, in real time.
+    tick: Duration,
+    ticks: Interval,
+}
+
+/// An update produced by playing back a recording.
+pub(crate) struct Frame {
+    /// If `true`, the recording was rewound, and the update replaces any
+    /// previously received state.
+    pub(crate) reset: bool,
+    pub(crate) update: proto::instrument::Update,
+}
+
+/// The state of the recording just before the first event recorded at `at`
+/// was applied.
+struct Checkpoint {
+    at: SystemTime,
+    /// The number of events read from the recording, including `next`.
+    read: u64,
+    next: Option<proto::recording::Event>,
+    aggregator: Aggregator,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Pending {
+    Update,
+    Reset,
+}
+
+// === impl Player ===
+
+impl Player {
+    /// How often the console is updated while playing, in real time, by
+    /// default.
+    const TICK: Duration = Duration::from_millis(100);
+
+    /// How far a single step moves through the recording.
+    ///
+    /// This is the default interval at which `console-subscriber` publishes
+    /// updates to a live console.
+    const STEP: Duration = Duration::from_secs(1);
+
+    /// How far seeking forwards or backwards moves through the recording.
+    const SEEK: Duration = Duration::from_secs(10);
+
+    /// How much recording time passes between checkpoints, until there are
+    /// too many of them.
+    const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// The most checkpoints that are kept. Once there are more, every other
+    /// checkpoint is discarded, and the interval between them is doubled, so
+    /// that long recordings don't hold too many copies of the state.
+    const MAX_CHECKPOINTS: usize = 64;
+
+    const MIN_SPEED: f64 = 1.0 / 64.0;
+    const MAX_SPEED: f64 = 64.0;
+
+    pub(crate) fn open(path: PathBuf, speed: f64) -> io::Result<Self> {
+        let mut reader = Reader::open(&path)?;
+        tracing::debug!(?path, version = reader.version(), format = ?reader.format(), "opened recording");
+        let next = reader.next().transpose()?;
+        let start = next
+            .as_ref()
+            .and_then(event_time)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "recording is empty"))?;
+        let mut ticks = time::interval(Self::TICK);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut player = Self {
+            path,
+            reader,
+            read: 1,
+            next,
+            aggregator: Aggregator::default(),
+            checkpoints: Vec::new(),
+            checkpoint_interval: Self::CHECKPOINT_INTERVAL,
+            start,
+            end: None,
+            now: start,
+            speed: 1.0,
+            paused: false,
+            pending: Some(Pending::Update),
+            tick: Self::TICK,
+            ticks,
+        };
+        player.set_speed(speed);
+        // Apply every event recorded at the very start of the recording, such
+        // as the callsites and spans in the preamble of a rotated file.
+        player.advance_to(start);
+        Ok(player)
+    }
+
+    /// Sets how often a new frame is produced while playing, in real time.
+    pub(crate) fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self.ticks = time::interval(tick);
+        self.ticks
+            .set_missed_tick_behavior(MissedTickBehavior::Delay);
+        self
+    }
+
+    /// Waits for the next update from the recording.
+    ///
+    /// While the recording is playing, this produces a new update each tick.
+    /// While it is paused, this only produces an update when the position in
+    /// the recording is changed.
+    pub(crate) async fn next_frame(&mut self) -> Frame {
+        if let Some(pending) = self.pending.take() {
+            return self.frame(pending == Pending::Reset);
+        }
+
+        if self.paused {
+            futures::future::pending::<()>().await;
+        }
+
+        self.ticks.tick().await;
+        let to = self.now + self.tick.mul_f64(self.speed);
+        self.advance_to(to);
+        if self.is_at_end() {
+            self.paused = true;
+        }
+        self.frame(false)
+    }
+
+    /// Returns an update containing the entire state at the current position
+    /// in the recording.
+    pub(crate) fn initial_update(&self) -> proto::instrument::Update {
+        self.aggregator.initial_update(self.now)
+    }
+
+    /// Returns the details of the task with the given span ID at the current
+    /// position in the recording.
+    pub(crate) fn task_details(&self, id: u64) -> Option<proto::tasks::TaskDetails> {
+        self.aggregator.task_details(id, self.now)
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub(crate) fn toggle_pause(&mut self) {
+        if !self.paused {
+            self.pause();
+            return;
+        }
+
+        if self.is_at_end() {
+            // Start again from the beginning, rather than resuming at the end.
+            self.seek(Seek::At(self.start));
+        }
+        self.resume();
+    }
+
+    pub(crate) fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes playback, unless the end of the recording has been reached.
+    pub(crate) fn resume(&mut self) {
+        if !self.is_at_end() {
+            self.paused = false;
+            self.ticks.reset();
+        }
+    }
+
+    pub(crate) fn faster(&mut self) {
+        self.set_speed(self.speed * 2.0);
+    }
+
+    pub(crate) fn slower(&mut self) {
+        self.set_speed(self.speed / 2.0);
+    }
+
+    pub(crate) fn step_forward(&mut self) {
+        self.paused = true;
+        self.seek(Seek::Forward(Self::STEP));
+    }
+
+    pub(crate) fn step_back(&mut self) {
+        self.paused = true;
+        self.seek(Seek::Back(Self::STEP));
+    }
+
+    pub(crate) fn seek_forward(&mut self) {
+        self.seek(Seek::Forward(Self::SEEK));
+    }
+
+    pub(crate) fn seek_back(&mut self) {
+        self.seek(Seek::Back(Self::SEEK));
+    }
+
+    /// Moves to a new position in the recording.
+    pub(crate) fn seek(&mut self, seek: Seek) {
+        let to = match seek {
+            Seek::At(at) => at,
+            Seek::Offset(offset) => self.start + offset,
+            Seek::Forward(by) => self.now + by,
+            Seek::Back(by) => self.now.checked_sub(by).unwrap_or(self.start),
+        };
+        let to = to.max(self.start);
+        tracing::debug!(?seek, ?to, "seeking");
+
+        if to < self.now {
+            if let Err(error) = self.rewind(to) {
+                tracing::warn!(%error, path = ?self.path, "failed to rewind recording");
+                return;
+            }
+            self.pending = Some(Pending::Reset);
+        } else if self.pending.is_none() {
+            self.pending = Some(Pending::Update);
+        }
+        self.advance_to(to);
+    }
+
+    pub(crate) fn render(&self, styles: &crate::view::Styles) -> Spans<'static> {
+        let state = if self.is_at_end() {
+            Span::styled("(END)", styles.fg(Color::Blue).add_modifier(Modifier::BOLD))
+        } else if self.paused {
+            Span::styled(
+                "(PAUSED)",
+                styles.fg(Color::Red).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(
+                "(PLAYING)",
+                styles.fg(Color::Green).add_modifier(Modifier::BOLD),
+            )
+        };
+        let end = match self.end {
+            Some(end) => format_offset(end.duration_since(self.start).unwrap_or_default()),
+            None => "?".to_string(),
+        };
+        let now = humantime::format_rfc3339_millis(self.now);
+        Spans::from(vec![
+            Span::raw("replay: "),
+            Span::raw(self.path.display().to_string()),
+            Span::raw(" "),
+            state,
+            Span::raw(format!(
+                " {} / {} ({}) at {}x",
+                format_offset(self.now.duration_since(self.start).unwrap_or_default()),
+                end,
+                now,
+                self.speed,
+            )),
+        ])
+    }
+
+    fn set_speed(&mut self, speed: f64) {
+        // `clamp` passes NaN through, which would panic when computing the
+        // next frame's position.
+        let speed = if speed.is_nan() { 1.0 } else { speed };
+        self.speed = speed.clamp(Self::MIN_SPEED, Self::MAX_SPEED);
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.next.is_none()
+    }
+
+    /// Applies every event recorded up to `to`.
+    fn advance_to(&mut self, to: SystemTime) {
+        while let Some(at) = self.next.as_ref().map(event_time) {
+            if at.map(|at| at > to).unwrap_or(false) {
+                break;
+            }
+            if let Some(at) = at {
+                self.checkpoint(at);
+            }
+
+            let event = self.next.take().expect("next event exists");
+            self.aggregator.apply(event);
+            self.next = match self.reader.next().transpose() {
+                Ok(next) => next,
+                Err(error) => {
+                    // Treat an unreadable event as the end of the recording,
+                    // such as if the recording was not finished.
+                    tracing::warn!(%error, path = ?self.path, "error reading recording");
+                    None
+                }
+            };
+            self.read += 1;
+            if self.next.is_none() {
+                self.end = Some(at.unwrap_or(to).max(self.start));
+            }
+        }
+
+        self.now = match self.end {
+            Some(end) => to.min(end),
+            None => to,
+        };
+    }
+
+    /// Takes a checkpoint before the next event, recorded at `at`, is
+    /// applied, if enough time has passed since the last checkpoint.
+    fn checkpoint(&mut self, at: SystemTime) {
+        let last = self.checkpoints.last().map_or(self.start, |last| last.at);
+        // After rewinding, `at` may be before the last checkpoint, and the
+        // checkpoints ahead of it are still valid.
+        match at.duration_since(last) {
+            Ok(since) if since > self.checkpoint_interval => {}
+            _ => return,
+        }
+
+        self.checkpoints.push(Checkpoint {
+            at,
+            read: self.read,
+            next: self.next.clone(),
+            aggregator: self.aggregator.clone(),
+        });
+        if self.checkpoints.len() > Self::MAX_CHECKPOINTS {
+            let mut keep = false;
+            self.checkpoints.retain(|_| {
+                keep = !keep;
+                keep
+            });
+            self.checkpoint_interval *= 2;
+        }
+    }
+
+    /// Rewinds to the latest checkpoint at or before `to`, or to the start of
+    /// the recording if there is none.
+    fn rewind(&mut self, to: SystemTime) -> io::Result<()> {
+        let mut reader = Reader::open(&self.path)?;
+        match self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.at <= to)
+        {
+            Some(checkpoint) => {
+                tracing::debug!(at = ?checkpoint.at, "rewinding to checkpoint");
+                // Recordings can't be seeked, so the events before the
+                // checkpoint are read again, but they aren't applied.
+                for _ in 0..checkpoint.read {
+                    if reader.next().transpose()?.is_none() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "recording is shorter than when it was played",
+                        ));
+                    }
+                }
+                self.read = checkpoint.read;
+                self.next = checkpoint.next.clone();
+                self.aggregator = checkpoint.aggregator.clone();
+                self.now = checkpoint.at;
+            }
+            None => {
+                self.read = 1;
+                self.next = reader.next().transpose()?;
+                self.aggregator = Aggregator::default();
+                self.now = self.start;
+            }
+        }
+        self.reader = reader;
+        Ok(())
+    }
+
+    fn frame(&mut self, reset: bool) -> Frame {
+        let update = if reset {
+            // A checkpoint's state may already have been partly sent.
+            self.aggregator.reset_update(self.now)
+        } else {
+            self.aggregator.update(self.now)
+        };
+        Frame { reset, update }
+    }
+}
+
+fn event_time(event: &proto::recording::Event) -> Option<SystemTime> {
+    SystemTime::try_from(event.at.clone()?).ok()
+}
+
+/// Formats an offset into the recording, to the nearest tenth of a second.
+fn format_offset(offset: Duration) -> String {
+    let tenths = offset.as_millis() / 100;
+    let secs = tenths / 10;
+    if secs >= 60 {
+        format!("{}m{:02}.{}s", secs / 60, secs % 60, tenths % 10)
+    } else {
+        format!("{}.{}s", secs, tenths % 10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fmt::Write as _, fs};
+
+    /// Writes a JSON recording in which a task is spawned and polled every
+    /// second for `secs` seconds, and each task is closed after ten seconds.
+    fn recording(name: &str, secs: u64) -> PathBuf {
+        let mut json = "{\"v\":2}\n".to_string();
+        let at = |secs: u64, nanos: u32| {
+            format!(
+                "{{\"secs_since_epoch\":{},\"nanos_since_epoch\":{}}}",
+                1_000_000 + secs,
+                nanos
+            )
+        };
+        for id in 1..=secs {
+            writeln!(
+                json,
+                "{{\"Spawn\":{{\"id\":{},\"at\":{},\"metadata_id\":1,\"fields\":[]}}}}",
+                id,
+                at(id, 0)
+            )
+            .unwrap();
+            writeln!(json, "{{\"Enter\":{{\"id\":{},\"at\":{}}}}}", id, at(id, 1)).unwrap();
+            writeln!(json, "{{\"Exit\":{{\"id\":{},\"at\":{}}}}}", id, at(id, 2)).unwrap();
+            if id > 10 {
+                writeln!(
+                    json,
+                    "{{\"Close\":{{\"id\":{},\"at\":{}}}}}",
+                    id - 10,
+                    at(id, 3)
+                )
+                .unwrap();
+            }
+        }
+        let path = std::env::temp_dir().join(format!(
+            "tokio-console-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, json).unwrap();
+        path
+    }
+
+    fn state(player: &Player) -> proto::instrument::Update {
+        let mut update = player.initial_update();
+        if let Some(ref mut tasks) = update.task_update {
+            tasks.new_tasks.sort_by_key(|task| task.id.map(|id| id.id));
+        }
+        update
+    }
+
+    #[tokio::test]
+    async fn rewinds_to_checkpoints() {
+        let path = recording("rewind", 1000);
+        let mut played = Player::open(path.clone(), 1.0).unwrap();
+        played.seek(Seek::Offset(Duration::from_secs(900)));
+        // Long recordings keep fewer checkpoints, further apart.
+        assert!(played.checkpoints.len() <= Player::MAX_CHECKPOINTS);
+        assert!(played.checkpoint_interval > Player::CHECKPOINT_INTERVAL);
+
+        for offset in [555, 305, 5, 0] {
+            let offset = Duration::from_secs(offset);
+            played.seek(Seek::Offset(offset));
+            let mut direct = Player::open(path.clone(), 1.0).unwrap();
+            direct.seek(Seek::Offset(offset));
+            assert_eq!(state(&played), state(&direct), "at {:?}", offset);
+            assert_eq!(played.read, direct.read, "at {:?}", offset);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn resets_after_rewinding() {
+        let path = recording("reset", 100);
+        let mut player = Player::open(path.clone(), 1.0).unwrap();
+        player.seek(Seek::Offset(Duration::from_secs(90)));
+        player.next_frame().await;
+
+        let to = player.start + Duration::from_secs(45);
+        assert!(player
+            .checkpoints
+            .iter()
+            .any(|checkpoint| checkpoint.at <= to));
+        player.seek(Seek::At(to));
+        let frame = player.next_frame().await;
+        assert!(frame.reset);
+        // The frame replaces the console's state, so it contains every task
+        // spawned so far (closed tasks are retained), even though the
+        // checkpoint's tasks were sent before it was rewound to.
+        let tasks = frame.update.task_update.unwrap();
+        assert_eq!(tasks.new_tasks.len(), 46);
+        fs::remove_file(path).unwrap();
+    }
+}
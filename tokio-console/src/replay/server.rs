@@ -0,0 +1,228 @@
+use super::player::Player;
+use console_api as proto;
+use proto::instrument::instrument_server::{Instrument, InstrumentServer};
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Serves a recording over the `Instrument` API, so that any console client
+/// can inspect the recorded process as though it were still running.
+struct Server {
+    commands: mpsc::Sender<Command>,
+}
+
+/// Plays back the recording, publishing updates to the server's clients.
+struct Publisher {
+    player: Player,
+    commands: mpsc::Receiver<Command>,
+    watchers: Vec<Watch<proto::instrument::Update>>,
+    details_watchers: HashMap<u64, Vec<Watch<proto::tasks::TaskDetails>>>,
+}
+
+struct Watch<T>(mpsc::Sender<Result<T, tonic::Status>>);
+
+enum Command {
+    Instrument(Watch<proto::instrument::Update>),
+    WatchTaskDetail(WatchRequest<proto::tasks::TaskDetails>),
+    Pause,
+    Resume,
+}
+
+struct WatchRequest<T> {
+    id: u64,
+    stream_sender: oneshot::Sender<mpsc::Receiver<Result<T, tonic::Status>>>,
+}
+
+/// The interval at which updates are published to clients, in real time.
+///
+/// This matches `console-subscriber`'s default publish interval.
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of updates buffered for each client.
+///
+/// This matches `console-subscriber`'s default client buffer capacity.
+const CLIENT_BUFFER: usize = 1024 * 4;
+
+/// Serves the recording played back by `player` on `addr`, until the server
+/// fails.
+pub(crate) async fn serve(player: Player, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+    let (commands_tx, commands_rx) = mpsc::channel(256);
+    let publisher = Publisher {
+        player: player.with_tick(PUBLISH_INTERVAL),
+        commands: commands_rx,
+        watchers: Vec::new(),
+        details_watchers: HashMap::new(),
+    };
+    tokio::spawn(publisher.run());
+
+    let server = Server {
+        commands: commands_tx,
+    };
+    tracing::info!(%addr, "serving recording");
+    tonic::transport::Server::builder()
+        .add_service(InstrumentServer::new(server))
+        .serve(addr)
+        .await
+}
+
+// === impl Publisher ===
+
+impl Publisher {
+    async fn run(mut self) {
+        loop {
+            tokio::select! {
+                frame = self.player.next_frame() => {
+                    // Recordings are only rewound before they are served, so
+                    // every frame can be sent as an ordinary update.
+                    self.publish(frame.update);
+                }
+                cmd = self.commands.recv() => {
+                    match cmd {
+                        Some(Command::Instrument(subscription)) => {
+                            if subscription.update(&self.player.initial_update()) {
+                                self.watchers.push(subscription);
+                            }
+                        }
+                        Some(Command::WatchTaskDetail(watch_request)) => {
+                            self.add_task_detail_subscription(watch_request);
+                        }
+                        Some(Command::Pause) => self.player.pause(),
+                        Some(Command::Resume) => self.player.resume(),
+                        None => {
+                            tracing::debug!("rpc channel closed, terminating");
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_task_detail_subscription(
+        &mut self,
+        watch_request: WatchRequest<proto::tasks::TaskDetails>,
+    ) {
+        let WatchRequest { id, stream_sender } = watch_request;
+        tracing::debug!(id, "new task details subscription");
+        // If the task doesn't exist, dropping the sender tells the server
+        // that it was not found.
+        if let Some(details) = self.player.task_details(id) {
+            let (tx, rx) = mpsc::channel(CLIENT_BUFFER);
+            let subscription = Watch(tx);
+            if stream_sender.send(rx).is_ok() && subscription.update(&details) {
+                self.details_watchers
+                    .entry(id)
+                    .or_default()
+                    .push(subscription);
+            }
+        }
+    }
+
+    fn publish(&mut self, update: proto::instrument::Update) {
+        self.watchers.retain(|watch| watch.update(&update));
+
+        let player = &self.player;
+        self.details_watchers.retain(|&id, watchers| {
+            if let Some(details) = player.task_details(id) {
+                watchers.retain(|watch| watch.update(&details));
+                !watchers.is_empty()
+            } else {
+                false
+            }
+        });
+    }
+}
+
+// === impl Watch ===
+
+impl<T: Clone> Watch<T> {
+    fn update(&self, update: &T) -> bool {
+        if let Ok(reserve) = self.0.try_reserve() {
+            reserve.send(Ok(update.clone()));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// === impl Server ===
+
+#[tonic::async_trait]
+impl Instrument for Server {
+    type WatchUpdatesStream = ReceiverStream<Result<proto::instrument::Update, tonic::Status>>;
+    type WatchTaskDetailsStream = ReceiverStream<Result<proto::tasks::TaskDetails, tonic::Status>>;
+
+    async fn watch_updates(
+        &self,
+        req: tonic::Request<proto::instrument::InstrumentRequest>,
+    ) -> Result<tonic::Response<Self::WatchUpdatesStream>, tonic::Status> {
+        match req.remote_addr() {
+            Some(addr) => tracing::debug!(client.addr = %addr, "starting a new watch"),
+            None => tracing::debug!(client.addr = %"<unknown>", "starting a new watch"),
+        }
+        let permit = self.commands.reserve().await.map_err(|_| {
+            tonic::Status::internal("cannot start new watch, replay task is not running")
+        })?;
+        let (tx, rx) = mpsc::channel(CLIENT_BUFFER);
+        permit.send(Command::Instrument(Watch(tx)));
+        Ok(tonic::Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn watch_task_details(
+        &self,
+        req: tonic::Request<proto::instrument::TaskDetailsRequest>,
+    ) -> Result<tonic::Response<Self::WatchTaskDetailsStream>, tonic::Status> {
+        let id = req
+            .into_inner()
+            .id
+            .ok_or_else(|| tonic::Status::invalid_argument("missing task_id"))?
+            .id;
+
+        let permit = self.commands.reserve().await.map_err(|_| {
+            tonic::Status::internal("cannot start new watch, replay task is not running")
+        })?;
+
+        let (stream_sender, stream_recv) = oneshot::channel();
+        permit.send(Command::WatchTaskDetail(WatchRequest { id, stream_sender }));
+        // If the publisher drops the sender, the task doesn't exist.
+        let rx = stream_recv.await.map_err(|_| {
+            tracing::warn!(id, "requested task not found");
+            tonic::Status::not_found("task not found")
+        })?;
+
+        tracing::debug!(id, "task details watch started");
+        Ok(tonic::Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn pause(
+        &self,
+        _req: tonic::Request<proto::instrument::PauseRequest>,
+    ) -> Result<tonic::Response<proto::instrument::PauseResponse>, tonic::Status> {
+        self.commands
+            .send(Command::Pause)
+            .await
+            .map_err(|_| tonic::Status::internal("cannot pause, replay task is not running"))?;
+        Ok(tonic::Response::new(proto::instrument::PauseResponse {}))
+    }
+
+    async fn resume(
+        &self,
+        _req: tonic::Request<proto::instrument::ResumeRequest>,
+    ) -> Result<tonic::Response<proto::instrument::ResumeResponse>, tonic::Status> {
+        self.commands
+            .send(Command::Resume)
+            .await
+            .map_err(|_| tonic::Status::internal("cannot resume, replay task is not running"))?;
+        Ok(tonic::Response::new(proto::instrument::ResumeResponse {}))
+    }
+
+    async fn dump_recording(
+        &self,
+        _req: tonic::Request<proto::instrument::DumpRecordingRequest>,
+    ) -> Result<tonic::Response<proto::instrument::DumpRecordingResponse>, tonic::Status> {
+        Err(tonic::Status::failed_precondition(
+            "a replayed recording has no flight recorder",
+        ))
+    }
+}
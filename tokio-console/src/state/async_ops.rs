@@ -2,6 +2,7 @@ use crate::{
     intern::{self, InternedStr},
     state::{pb_duration, Attribute, Field, Ids, Metadata, Visibility},
     view,
+    warnings::Linter,
 };
 use console_api as proto;
 use std::{
@@ -18,18 +19,20 @@ pub(crate) struct AsyncOpsState {
     async_ops: HashMap<u64, Rc<RefCell<AsyncOp>>>,
     ids: Ids,
     new_async_ops: Vec<AsyncOpRef>,
+    pub(crate) linters: Vec<Linter<AsyncOp>>,
 }
 
 #[derive(Debug, Copy, Clone)]
 #[repr(usize)]
 pub(crate) enum SortBy {
-    Aid = 0,
-    Task = 1,
-    Source = 2,
-    Total = 3,
-    Busy = 4,
-    Idle = 5,
-    Polls = 6,
+    Warns = 0,
+    Aid = 1,
+    Task = 2,
+    Source = 3,
+    Total = 4,
+    Busy = 5,
+    Idle = 6,
+    Polls = 7,
 }
 
 #[derive(Debug)]
@@ -40,6 +43,13 @@ pub(crate) struct AsyncOp {
     meta_id: u64,
     source: InternedStr,
     stats: AsyncOpStats,
+    /// The number of other async ops on the same resource.
+    siblings: usize,
+    /// The median lifetime of the other async ops on the same resource, if
+    /// there are any.
+    sibling_median: Option<Duration>,
+    /// Currently active warnings for this async op.
+    warnings: Vec<Linter<AsyncOp>>,
 }
 
 pub(crate) type AsyncOpRef = Weak<RefCell<AsyncOp>>;
@@ -69,6 +79,9 @@ impl Default for SortBy {
 impl SortBy {
     pub fn sort(&self, now: SystemTime, ops: &mut Vec<Weak<RefCell<AsyncOp>>>) {
         match self {
+            Self::Warns => {
+                ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().warnings().len()))
+            }
             Self::Aid => ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().num)),
             Self::Task => ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().task_id())),
             Self::Source => {
@@ -90,6 +103,7 @@ impl TryFrom<usize> for SortBy {
     type Error = ();
     fn try_from(idx: usize) -> Result<Self, Self::Error> {
         match idx {
+            idx if idx == Self::Warns as usize => Ok(Self::Warns),
             idx if idx == Self::Aid as usize => Ok(Self::Aid),
             idx if idx == Self::Task as usize => Ok(Self::Task),
             idx if idx == Self::Source as usize => Ok(Self::Source),
@@ -183,6 +197,9 @@ impl AsyncOpsState {
                 meta_id,
                 source,
                 stats,
+                siblings: 0,
+                sibling_median: None,
+                warnings: Vec::new(),
             };
             let async_op = Rc::new(RefCell::new(async_op));
             new_list.push(Rc::downgrade(&async_op));
@@ -203,6 +220,56 @@ impl AsyncOpsState {
         }
     }
 
+    /// Checks every async op for warnings as of `now`.
+    ///
+    /// Async ops are compared with the other async ops on the same resource,
+    /// so this first finds the median lifetime of each op's siblings.
+    pub(crate) fn lint_async_ops(&mut self, now: SystemTime) {
+        let mut lifetimes = HashMap::<u64, Vec<Duration>>::new();
+        for async_op in self.async_ops.values() {
+            let async_op = async_op.borrow();
+            lifetimes
+                .entry(async_op.resource_id)
+                .or_default()
+                .push(async_op.total(now));
+        }
+        for lifetimes in lifetimes.values_mut() {
+            lifetimes.sort_unstable();
+        }
+
+        for async_op in self.async_ops.values() {
+            let mut async_op = async_op.borrow_mut();
+            let lifetimes = &lifetimes[&async_op.resource_id];
+            let siblings = lifetimes.len() - 1;
+            async_op.siblings = siblings;
+            async_op.sibling_median = if siblings > 0 {
+                // The median of the lifetimes, leaving out this op's own.
+                let own = lifetimes
+                    .binary_search(&async_op.total(now))
+                    .unwrap_or_else(|idx| idx);
+                let mid = siblings / 2;
+                Some(if mid < own {
+                    lifetimes[mid]
+                } else {
+                    lifetimes[mid + 1]
+                })
+            } else {
+                None
+            };
+            async_op.lint(&self.linters, now);
+        }
+    }
+
+    pub(crate) fn warnings(&self) -> impl Iterator<Item = &Linter<AsyncOp>> {
+        self.linters.iter().filter(|linter| linter.count() > 0)
+    }
+
+    /// Returns every enabled linter, whether or not any async op has its
+    /// warning.
+    pub(crate) fn linters(&self) -> &[Linter<AsyncOp>] {
+        &self.linters
+    }
+
     pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
         self.async_ops.retain(|_, async_op| {
             let async_op = async_op.borrow();
@@ -270,10 +337,42 @@ impl AsyncOp {
         self.stats.polls
     }
 
+    /// Returns the most recent time this async op was polled, if it has
+    /// been.
+    pub(crate) fn last_polled(&self) -> Option<SystemTime> {
+        self.stats.last_poll_started.max(self.stats.last_poll_ended)
+    }
+
     pub(crate) fn dropped(&self) -> bool {
         self.stats.total.is_some()
     }
 
+    pub(crate) fn warnings(&self) -> &[Linter<AsyncOp>] {
+        &self.warnings[..]
+    }
+
+    /// Returns the number of other async ops on the same resource.
+    pub(crate) fn siblings(&self) -> usize {
+        self.siblings
+    }
+
+    /// Returns the median lifetime of the other async ops on the same
+    /// resource, or `None` if there are none.
+    pub(crate) fn sibling_median(&self) -> Option<Duration> {
+        self.sibling_median
+    }
+
+    fn lint(&mut self, linters: &[Linter<AsyncOp>], now: SystemTime) {
+        let previous = std::mem::take(&mut self.warnings);
+        for lint in linters {
+            tracing::debug!(?lint, async_op = ?self, "checking...");
+            if let Some(warning) = lint.check(self, now, &previous) {
+                tracing::info!(?warning, async_op = ?self, "found a warning!");
+                self.warnings.push(warning)
+            }
+        }
+    }
+
     pub(crate) fn formatted_attributes(&self) -> &[Vec<Span<'static>>] {
         &self.stats.formatted_attributes
     }
@@ -0,0 +1,135 @@
+use crate::state::tasks::{TaskRef, TaskState};
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+/// A rolling history of aggregate stats about the tasks, with a sample for
+/// each update, so that trends can be displayed rather than only the latest
+/// values.
+#[derive(Debug)]
+pub(crate) struct History {
+    samples: VecDeque<Sample>,
+    /// How long samples are kept for.
+    window: Duration,
+}
+
+/// The aggregate stats about the tasks as of an update.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Sample {
+    pub(crate) at: SystemTime,
+    /// The time since the previous sample, if there was one.
+    pub(crate) interval: Option<Duration>,
+    pub(crate) running: usize,
+    pub(crate) idle: usize,
+    pub(crate) completed: usize,
+    /// What happened since the previous sample.
+    pub(crate) totals: IntervalTotals,
+}
+
+/// Totals accumulated from the updates between two samples.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct IntervalTotals {
+    /// How many tasks were spawned.
+    pub(crate) spawned: u64,
+    /// How many tasks completed.
+    pub(crate) completed: u64,
+    /// How long tasks spent being polled.
+    pub(crate) busy: Duration,
+    /// How many times tasks were polled.
+    pub(crate) polls: u64,
+}
+
+// === impl History ===
+
+impl History {
+    pub(crate) const DEFAULT_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window,
+        }
+    }
+
+    /// Records a sample of the `tasks` as of `now`, and discards the samples
+    /// that are older than the window.
+    pub(crate) fn record(
+        &mut self,
+        now: SystemTime,
+        tasks: impl Iterator<Item = TaskRef>,
+        totals: IntervalTotals,
+    ) {
+        let mut sample = Sample {
+            at: now,
+            interval: self
+                .samples
+                .back()
+                .and_then(|previous| now.duration_since(previous.at).ok()),
+            running: 0,
+            idle: 0,
+            completed: 0,
+            totals,
+        };
+        for task in tasks.filter_map(|task| task.upgrade()) {
+            match task.borrow().state() {
+                TaskState::Running => sample.running += 1,
+                TaskState::Idle => sample.idle += 1,
+                TaskState::Completed => sample.completed += 1,
+            }
+        }
+        self.samples.push_back(sample);
+
+        while let Some(oldest) = self.samples.front() {
+            match now.duration_since(oldest.at) {
+                Ok(age) if age > self.window => {
+                    self.samples.pop_front();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Returns the samples in the window, oldest first.
+    pub(crate) fn samples(&self) -> impl ExactSizeIterator<Item = &Sample> + '_ {
+        self.samples.iter()
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+// === impl Sample ===
+
+impl Sample {
+    /// Returns how many tasks were spawned per second since the previous
+    /// sample.
+    pub(crate) fn spawn_rate(&self) -> f64 {
+        self.per_second(self.totals.spawned as f64)
+    }
+
+    /// Returns how many tasks completed per second since the previous sample.
+    pub(crate) fn completion_rate(&self) -> f64 {
+        self.per_second(self.totals.completed as f64)
+    }
+
+    /// Returns how many times tasks were polled per second since the previous
+    /// sample.
+    pub(crate) fn polls_per_second(&self) -> f64 {
+        self.per_second(self.totals.polls as f64)
+    }
+
+    fn per_second(&self, value: f64) -> f64 {
+        match self.interval {
+            Some(interval) if interval > Duration::ZERO => value / interval.as_secs_f64(),
+            _ => 0.0,
+        }
+    }
+}
@@ -1,10 +1,15 @@
-use self::{async_ops::AsyncOpsState, resources::ResourcesState};
+use self::{
+    async_ops::{AsyncOp, AsyncOpsState},
+    history::History,
+    resources::{Resource, ResourcesState},
+};
 use crate::{
     intern::{self, InternedStr},
     view,
     warnings::Linter,
 };
 use console_api as proto;
+use hdrhistogram::Histogram;
 use std::{
     cell::RefCell,
     cmp::Ordering,
@@ -15,13 +20,14 @@ use std::{
     rc::Rc,
     time::{Duration, SystemTime},
 };
-use tasks::{Details, Task, TasksState};
+use tasks::{ActiveResource, Details, Task, TasksState};
 use tui::{
     style::{Color, Modifier},
     text::Span,
 };
 
 pub mod async_ops;
+pub(crate) mod history;
 pub mod resources;
 pub mod tasks;
 
@@ -37,6 +43,7 @@ pub(crate) struct State {
     async_ops_state: AsyncOpsState,
     current_task_details: DetailsRef,
     retain_for: Option<Duration>,
+    history: History,
     strings: intern::Strings,
 }
 
@@ -92,6 +99,11 @@ impl State {
         self
     }
 
+    pub(crate) fn with_history_window(mut self, window: Duration) -> Self {
+        self.history = History::new(window);
+        self
+    }
+
     pub(crate) fn with_task_linters(
         mut self,
         linters: impl IntoIterator<Item = Linter<Task>>,
@@ -100,17 +112,38 @@ impl State {
         self
     }
 
+    pub(crate) fn with_resource_linters(
+        mut self,
+        linters: impl IntoIterator<Item = Linter<Resource>>,
+    ) -> Self {
+        self.resources_state.linters.extend(linters);
+        self
+    }
+
+    pub(crate) fn with_async_op_linters(
+        mut self,
+        linters: impl IntoIterator<Item = Linter<AsyncOp>>,
+    ) -> Self {
+        self.async_ops_state.linters.extend(linters);
+        self
+    }
+
     pub(crate) fn last_updated_at(&self) -> Option<SystemTime> {
         self.last_updated_at
     }
 
+    pub(crate) fn history(&self) -> &History {
+        &self.history
+    }
+
     pub(crate) fn update(
         &mut self,
         styles: &view::Styles,
         current_view: &view::ViewState,
         update: proto::instrument::Update,
     ) {
-        if let Some(now) = update.now.map(|v| v.try_into().unwrap()) {
+        let now = update.now.map(|v| v.try_into().unwrap());
+        if let Some(now) = now {
             self.last_updated_at = Some(now);
         }
 
@@ -150,6 +183,7 @@ impl State {
                 &mut self.strings,
                 &self.metas,
                 resources_update,
+                now,
                 visibility,
             )
         }
@@ -170,6 +204,73 @@ impl State {
                 visibility,
             )
         }
+
+        if let Some(now) = now {
+            self.update_active_resources();
+            self.tasks_state.update_recent_stats(now);
+            self.tasks_state.lint_tasks(now);
+            self.resources_state.lint_resources(now);
+            self.async_ops_state.lint_async_ops(now);
+            let totals = self.tasks_state.take_interval();
+            self.history.record(now, self.tasks_state.tasks(), totals);
+        }
+    }
+
+    /// Finds, for each task, a resource the task is waiting on that other
+    /// tasks have used since the task was last polled.
+    ///
+    /// A task is waiting on a resource if it has an async op on that
+    /// resource which has not been dropped.
+    fn update_active_resources(&mut self) {
+        let mut last_polled = HashMap::new();
+        for op in self.async_ops_state.async_ops() {
+            let op = match op.upgrade() {
+                Some(op) => op,
+                None => continue,
+            };
+            let op = op.borrow();
+            if let Some(polled) = op.last_polled() {
+                let last = last_polled.entry(op.resource_id()).or_insert(polled);
+                *last = polled.max(*last);
+            }
+        }
+
+        let mut active = HashMap::new();
+        for op in self.async_ops_state.async_ops() {
+            let op = match op.upgrade() {
+                Some(op) => op,
+                None => continue,
+            };
+            let op = op.borrow();
+            let task_id = match op.task_id() {
+                Some(task_id) if !op.dropped() => task_id,
+                _ => continue,
+            };
+            let task = match self.tasks_state.task(task_id).and_then(|t| t.upgrade()) {
+                Some(task) => task,
+                None => continue,
+            };
+            // The task's own async ops are polled while the task is, so only
+            // polls after the task's last poll ended are by other tasks.
+            let used = last_polled.get(&op.resource_id()).copied();
+            if used.is_none() || used <= task.borrow().last_poll_ended() {
+                continue;
+            }
+            let resource = self
+                .resources_state
+                .resource(op.resource_id())
+                .and_then(|r| r.upgrade());
+            if let Some(resource) = resource {
+                active.insert(
+                    task_id,
+                    ActiveResource {
+                        id: op.resource_id(),
+                        concrete_type: resource.borrow().concrete_type().to_string(),
+                    },
+                );
+            }
+        }
+        self.tasks_state.set_active_resources(active);
     }
 
     pub(crate) fn retain_active(&mut self) {
@@ -192,7 +293,7 @@ impl State {
         self.current_task_details.clone()
     }
 
-    pub(crate) fn tasks_state(&mut self) -> &TasksState {
+    pub(crate) fn tasks_state(&self) -> &TasksState {
         &self.tasks_state
     }
 
@@ -200,6 +301,10 @@ impl State {
         &mut self.tasks_state
     }
 
+    pub(crate) fn resources_state(&self) -> &ResourcesState {
+        &self.resources_state
+    }
+
     pub(crate) fn resources_state_mut(&mut self) -> &mut ResourcesState {
         &mut self.resources_state
     }
@@ -214,14 +319,36 @@ impl State {
 
     pub(crate) fn update_task_details(&mut self, update: proto::tasks::TaskDetails) {
         if let Some(id) = update.task_id {
-            let details = Details {
-                span_id: id.id,
-                poll_times_histogram: update.poll_times_histogram.and_then(|data| {
+            let poll_times_histogram: Option<Histogram<u64>> =
+                update.poll_times_histogram.and_then(|data| {
                     hdrhistogram::serialization::Deserializer::new()
                         .deserialize(&mut Cursor::new(&data))
                         .ok()
-                }),
+                });
+            // The histogram covers every poll of the task, so the polls since
+            // the previous update are the difference between the two.
+            let previous = self
+                .current_task_details
+                .borrow_mut()
+                .take()
+                .filter(|details| details.span_id == id.id)
+                .and_then(|details| details.poll_times_histogram);
+            let recent_poll_times_histogram = match (&poll_times_histogram, previous) {
+                (Some(histogram), Some(previous)) => {
+                    let mut recent = histogram.clone();
+                    recent.subtract(&previous).ok().map(|_| recent)
+                }
+                _ => None,
+            };
+            let details = Details {
+                span_id: id.id,
+                poll_times_histogram,
+                recent_poll_times_histogram,
             };
+            if let Some(ref histogram) = details.poll_times_histogram {
+                self.tasks_state
+                    .update_poll_times(id.id, histogram, self.last_updated_at);
+            }
 
             *self.current_task_details.borrow_mut() = Some(details);
         }
@@ -271,6 +398,8 @@ impl Metadata {
 impl Field {
     const SPAWN_LOCATION: &'static str = "spawn.location";
     const NAME: &'static str = "task.name";
+    const KIND: &'static str = "kind";
+    const KIND_BLOCKING: &'static str = "blocking";
 
     /// Converts a wire-format `Field` into an internal `Field` representation,
     /// using the provided `Metadata` for the task span that the field came
@@ -463,6 +592,11 @@ impl PartialOrd for Attribute {
 // === impl Attribute ===
 
 impl Attribute {
+    /// The number of permits a semaphore has available.
+    const PERMITS: &'static str = "permits";
+    /// Whether a mutex is currently locked.
+    const LOCKED: &'static str = "locked";
+
     fn make_formatted(
         styles: &view::Styles,
         attributes: &mut Vec<Attribute>,
@@ -507,6 +641,11 @@ impl Ids {
             }
         }
     }
+
+    /// Returns the pretty ID for `span_id`, if one has been assigned.
+    pub(crate) fn get(&self, span_id: u64) -> Option<u64> {
+        self.map.get(&span_id).copied()
+    }
 }
 
 impl Default for Ids {
@@ -536,7 +675,7 @@ fn truncate_registry_path(s: String) -> String {
     };
 }
 
-fn format_location(loc: Option<proto::Location>) -> String {
+pub(crate) fn format_location(loc: Option<proto::Location>) -> String {
     loc.map(|mut l| {
         if let Some(file) = l.file.take() {
             let truncated = truncate_registry_path(file);
@@ -1,6 +1,7 @@
 use crate::intern::{self, InternedStr};
-use crate::state::{format_location, Attribute, Field, Ids, Metadata, Visibility};
+use crate::state::{format_location, Attribute, Field, FieldValue, Ids, Metadata, Visibility};
 use crate::view;
+use crate::warnings::Linter;
 use console_api as proto;
 use std::{
     cell::RefCell,
@@ -16,6 +17,7 @@ pub(crate) struct ResourcesState {
     resources: HashMap<u64, Rc<RefCell<Resource>>>,
     pub(crate) ids: Ids,
     new_resources: Vec<ResourceRef>,
+    pub(crate) linters: Vec<Linter<Resource>>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -27,11 +29,12 @@ pub(crate) enum TypeVisibility {
 #[derive(Debug, Copy, Clone)]
 #[repr(usize)]
 pub(crate) enum SortBy {
-    Rid = 0,
-    Kind = 1,
-    ConcreteType = 2,
-    Target = 3,
-    Total = 4,
+    Warns = 0,
+    Rid = 1,
+    Kind = 2,
+    ConcreteType = 3,
+    Target = 4,
+    Total = 5,
 }
 
 #[derive(Debug)]
@@ -48,6 +51,8 @@ pub(crate) struct Resource {
     id_str: InternedStr,
     parent: InternedStr,
     parent_id: InternedStr,
+    /// The pretty ID of the resource's parent, if it has one.
+    parent_num: Option<u64>,
     meta_id: u64,
     kind: InternedStr,
     stats: ResourceStats,
@@ -55,6 +60,14 @@ pub(crate) struct Resource {
     concrete_type: InternedStr,
     location: String,
     visibility: TypeVisibility,
+    /// When the resource was last seen with no permits available, if none
+    /// have been released or acquired since.
+    exhausted_since: Option<SystemTime>,
+    /// When the resource was last seen being locked, if it hasn't been
+    /// unlocked since.
+    locked_since: Option<SystemTime>,
+    /// Currently active warnings for this resource.
+    warnings: Vec<Linter<Resource>>,
 }
 
 pub(crate) type ResourceRef = Weak<RefCell<Resource>>;
@@ -65,6 +78,10 @@ struct ResourceStats {
     dropped_at: Option<SystemTime>,
     total: Option<Duration>,
     formatted_attributes: Vec<Vec<Span<'static>>>,
+    /// The value of the `permits` attribute, for resources that have one.
+    permits: Option<u64>,
+    /// The value of the `locked` attribute, for resources that have one.
+    locked: Option<bool>,
 }
 
 impl Default for SortBy {
@@ -76,6 +93,9 @@ impl Default for SortBy {
 impl SortBy {
     pub fn sort(&self, now: SystemTime, resources: &mut Vec<Weak<RefCell<Resource>>>) {
         match self {
+            Self::Warns => resources.sort_unstable_by_key(|resource| {
+                resource.upgrade().map(|r| r.borrow().warnings().len())
+            }),
             Self::Rid => resources
                 .sort_unstable_by_key(|resource| resource.upgrade().map(|r| r.borrow().num)),
             Self::Kind => resources.sort_unstable_by_key(|resource| {
@@ -97,6 +117,7 @@ impl TryFrom<usize> for SortBy {
     type Error = ();
     fn try_from(idx: usize) -> Result<Self, Self::Error> {
         match idx {
+            idx if idx == Self::Warns as usize => Ok(Self::Warns),
             idx if idx == Self::Rid as usize => Ok(Self::Rid),
             idx if idx == Self::Kind as usize => Ok(Self::Kind),
             idx if idx == Self::ConcreteType as usize => Ok(Self::ConcreteType),
@@ -122,12 +143,18 @@ impl ResourcesState {
         self.resources.get(&id).map(Rc::downgrade)
     }
 
+    /// Returns all resources.
+    pub(crate) fn resources(&self) -> impl Iterator<Item = ResourceRef> + '_ {
+        self.resources.values().map(Rc::downgrade)
+    }
+
     pub(crate) fn update_resources(
         &mut self,
         styles: &view::Styles,
         strings: &mut intern::Strings,
         metas: &HashMap<u64, Metadata>,
         update: proto::resources::ResourceUpdate,
+        now: Option<SystemTime>,
         visibility: Visibility,
     ) {
         let parents: HashMap<u64, ResourceRef> = update
@@ -192,6 +219,7 @@ impl ResourcesState {
                 None => "n/a".to_string(),
             });
 
+            let parent_num = parent_id;
             let parent_id = strings.string(
                 parent_id
                     .as_ref()
@@ -206,12 +234,13 @@ impl ResourcesState {
                 TypeVisibility::Public
             };
 
-            let resource = Resource {
+            let mut resource = Resource {
                 num,
                 span_id,
                 id_str: strings.string(num.to_string()),
                 parent,
                 parent_id,
+                parent_num,
                 kind,
                 stats,
                 target: meta.target.clone(),
@@ -219,7 +248,13 @@ impl ResourcesState {
                 meta_id,
                 location,
                 visibility,
+                exhausted_since: None,
+                locked_since: None,
+                warnings: Vec::new(),
             };
+            if let Some(now) = now {
+                resource.observe_attributes(now);
+            }
             let resource = Rc::new(RefCell::new(resource));
             new_list.push(Rc::downgrade(&resource));
             Some((num, resource))
@@ -233,11 +268,31 @@ impl ResourcesState {
                 let mut r = resource.borrow_mut();
                 if let Some(meta) = metas.get(&r.meta_id) {
                     r.stats = ResourceStats::from_proto(stats, meta, styles, strings);
+                    if let Some(now) = now {
+                        r.observe_attributes(now);
+                    }
                 }
             }
         }
     }
 
+    /// Checks every resource for warnings as of `now`.
+    pub(crate) fn lint_resources(&mut self, now: SystemTime) {
+        for resource in self.resources.values() {
+            resource.borrow_mut().lint(&self.linters, now);
+        }
+    }
+
+    pub(crate) fn warnings(&self) -> impl Iterator<Item = &Linter<Resource>> {
+        self.linters.iter().filter(|linter| linter.count() > 0)
+    }
+
+    /// Returns every enabled linter, whether or not any resource has its
+    /// warning.
+    pub(crate) fn linters(&self) -> &[Linter<Resource>] {
+        &self.linters
+    }
+
     pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
         self.resources.retain(|_, resource| {
             let resource = resource.borrow();
@@ -275,6 +330,10 @@ impl Resource {
         &self.parent_id
     }
 
+    pub(crate) fn parent_num(&self) -> Option<u64> {
+        self.parent_num
+    }
+
     pub(crate) fn type_visibility(&self) -> TypeVisibility {
         self.visibility
     }
@@ -308,6 +367,43 @@ impl Resource {
     pub(crate) fn location(&self) -> &str {
         &self.location
     }
+
+    pub(crate) fn warnings(&self) -> &[Linter<Resource>] {
+        &self.warnings[..]
+    }
+
+    /// Returns how long this resource has had no permits available, as of
+    /// `now`, or `None` if it has permits or doesn't have any at all.
+    pub(crate) fn exhausted_for(&self, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.exhausted_since?).ok()
+    }
+
+    /// Returns how long this resource has been locked, as of `now`, or
+    /// `None` if it is unlocked or can't be locked at all.
+    pub(crate) fn locked_for(&self, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.locked_since?).ok()
+    }
+
+    fn lint(&mut self, linters: &[Linter<Resource>], now: SystemTime) {
+        let previous = std::mem::take(&mut self.warnings);
+        for lint in linters {
+            tracing::debug!(?lint, resource = ?self, "checking...");
+            if let Some(warning) = lint.check(self, now, &previous) {
+                tracing::info!(?warning, resource = ?self, "found a warning!");
+                self.warnings.push(warning)
+            }
+        }
+    }
+
+    /// Records when the resource's permits were exhausted or it was locked.
+    ///
+    /// The remote process only sends a resource's attributes when they
+    /// change, so seeing a resource locked in an update means that it was
+    /// locked (perhaps again) since the previous update.
+    fn observe_attributes(&mut self, now: SystemTime) {
+        self.exhausted_since = Some(now).filter(|_| self.stats.permits == Some(0));
+        self.locked_since = Some(now).filter(|_| self.stats.locked == Some(true));
+    }
 }
 
 impl ResourceStats {
@@ -331,6 +427,16 @@ impl ResourceStats {
             })
             .collect::<Vec<_>>();
 
+        let mut permits = None;
+        let mut locked = None;
+        for attribute in &attributes {
+            match (&*attribute.field.name, &attribute.field.value) {
+                (Attribute::PERMITS, &FieldValue::U64(value)) => permits = Some(value),
+                (Attribute::LOCKED, &FieldValue::Bool(value)) => locked = Some(value),
+                _ => {}
+            }
+        }
+
         let formatted_attributes = Attribute::make_formatted(styles, &mut attributes);
         let created_at = pb
             .created_at
@@ -345,6 +451,8 @@ impl ResourceStats {
             dropped_at,
             total,
             formatted_attributes,
+            permits,
+            locked,
         }
     }
 }
@@ -1,6 +1,8 @@
 use crate::{
     intern::{self, InternedStr},
-    state::{format_location, pb_duration, Field, Ids, Metadata, Visibility},
+    state::{
+        format_location, history::IntervalTotals, pb_duration, Field, Ids, Metadata, Visibility,
+    },
     util::Percentage,
     view,
     warnings::Linter,
@@ -9,6 +11,7 @@ use console_api as proto;
 use hdrhistogram::Histogram;
 use std::{
     cell::RefCell,
+    cmp,
     collections::HashMap,
     convert::{TryFrom, TryInto},
     rc::{Rc, Weak},
@@ -22,12 +25,17 @@ pub(crate) struct TasksState {
     pub(crate) ids: Ids,
     new_tasks: Vec<TaskRef>,
     pub(crate) linters: Vec<Linter<Task>>,
+    /// Totals accumulated from the updates since the last history sample.
+    interval: IntervalTotals,
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct Details {
     pub(crate) span_id: u64,
     pub(crate) poll_times_histogram: Option<Histogram<u64>>,
+    /// The poll times recorded since the previous details update, if there
+    /// was one.
+    pub(crate) recent_poll_times_histogram: Option<Histogram<u64>>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -41,8 +49,11 @@ pub(crate) enum SortBy {
     Busy = 5,
     Idle = 6,
     Polls = 7,
-    Target = 8,
-    Location = 9,
+    PollsPerSec = 8,
+    BusyPercent = 9,
+    WakesPerSec = 10,
+    Target = 11,
+    Location = 12,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -54,6 +65,15 @@ pub(crate) enum TaskState {
 
 pub(crate) type TaskRef = Weak<RefCell<Task>>;
 
+/// A resource that a task is waiting on, which other tasks have used since the
+/// task was last polled.
+#[derive(Clone, Debug)]
+pub(crate) struct ActiveResource {
+    /// The resource's pretty (console-generated, sequential) ID.
+    pub(crate) id: u64,
+    pub(crate) concrete_type: String,
+}
+
 #[derive(Debug)]
 pub(crate) struct Task {
     /// The task's pretty (console-generated, sequential) task ID.
@@ -73,6 +93,39 @@ pub(crate) struct Task {
     /// Currently active warnings for this task.
     warnings: Vec<Linter<Task>>,
     location: String,
+    /// Whether this task was spawned with `spawn_blocking`, and is therefore
+    /// expected to block its thread.
+    is_blocking: bool,
+    /// The longest single poll of this task that the console has observed.
+    ///
+    /// The console doesn't see individual polls unless the task's details are
+    /// being watched, so this is usually a lower bound: the mean duration of
+    /// the polls between two updates, or the time spent in a poll that is
+    /// still in progress.
+    longest_poll: Duration,
+    /// A resource this task is waiting on that has been used since the task
+    /// was last polled, if there is one.
+    active_resource: Option<ActiveResource>,
+    /// The task's activity over the interval between the two most recent
+    /// updates.
+    recent: RecentStats,
+}
+
+/// How active a task was over the interval between two updates, unlike its
+/// `TaskStats`, which are cumulative since it was spawned.
+#[derive(Debug)]
+struct RecentStats {
+    /// When the previous values were recorded.
+    at: SystemTime,
+    /// The task's cumulative stats as of `at`.
+    polls: u64,
+    busy: Duration,
+    wakes: u64,
+
+    polls_per_sec: f64,
+    /// The percentage of the interval that the task spent being polled.
+    busy_percent: f64,
+    wakes_per_sec: f64,
 }
 
 #[derive(Debug)]
@@ -107,6 +160,11 @@ impl TasksState {
         self.new_tasks.drain(..)
     }
 
+    /// Returns the totals accumulated since this was last called.
+    pub(crate) fn take_interval(&mut self) -> IntervalTotals {
+        std::mem::take(&mut self.interval)
+    }
+
     pub(crate) fn update_tasks(
         &mut self,
         styles: &view::Styles,
@@ -117,12 +175,11 @@ impl TasksState {
     ) {
         let mut stats_update = update.stats_update;
         let new_list = &mut self.new_tasks;
+        let interval = &mut self.interval;
         if matches!(visibility, Visibility::Show) {
             new_list.clear();
         }
 
-        let linters = &self.linters;
-
         let new_tasks = update.new_tasks.into_iter().filter_map(|mut task| {
             if task.id.is_none() {
                 tracing::warn!(?task, "skipping task with no id");
@@ -143,6 +200,7 @@ impl TasksState {
                 }
             };
             let mut name = None;
+            let mut is_blocking = false;
             let mut fields = task
                 .fields
                 .drain(..)
@@ -153,6 +211,9 @@ impl TasksState {
                         name = Some(strings.string(field.value.to_string()));
                         return None;
                     }
+                    if &*field.name == Field::KIND {
+                        is_blocking = field.value.to_string() == Field::KIND_BLOCKING;
+                    }
                     Some(field)
                 })
                 .collect::<Vec<_>>();
@@ -160,7 +221,14 @@ impl TasksState {
             let formatted_fields = Field::make_formatted(styles, &mut fields);
             let span_id = task.id?.id;
 
-            let stats = stats_update.remove(&span_id)?.into();
+            let stats: TaskStats = stats_update.remove(&span_id)?.into();
+            let (polls, busy) = (stats.polls, stats.busy);
+            interval.spawned += 1;
+            interval.polls += polls;
+            interval.busy += busy;
+            if stats.dropped_at.is_some() {
+                interval.completed += 1;
+            }
             let location = format_location(task.location);
 
             // remap the server's ID to a pretty, sequential task ID
@@ -177,12 +245,17 @@ impl TasksState {
                 span_id,
                 short_desc,
                 formatted_fields,
-                stats,
                 target: meta.target.clone(),
                 warnings: Vec::new(),
                 location,
+                is_blocking,
+                longest_poll: Duration::ZERO,
+                active_resource: None,
+                // The task's first rates are averaged over its whole lifetime.
+                recent: RecentStats::new(stats.created_at),
+                stats,
             };
-            task.lint(linters);
+            task.observe_polls(polls, busy);
             let task = Rc::new(RefCell::new(task));
             new_list.push(Rc::downgrade(&task));
             Some((num, task))
@@ -193,12 +266,76 @@ impl TasksState {
             if let Some(task) = self.tasks.get_mut(&num) {
                 let mut task = task.borrow_mut();
                 tracing::trace!(?task, "processing stats update for");
-                task.stats = stats.into();
-                task.lint(linters);
+                let stats: TaskStats = stats.into();
+                let polls = stats.polls.saturating_sub(task.stats.polls);
+                let busy = stats.busy.saturating_sub(task.stats.busy);
+                self.interval.polls += polls;
+                self.interval.busy += busy;
+                if task.stats.dropped_at.is_none() && stats.dropped_at.is_some() {
+                    self.interval.completed += 1;
+                }
+                task.stats = stats;
+                task.observe_polls(polls, busy);
             }
         }
     }
 
+    /// Updates every task's polls, busy time and wakes over the interval
+    /// since the previous update.
+    ///
+    /// Tasks that didn't get a stats update were inactive during the
+    /// interval, so every task is updated, not only those whose stats changed.
+    pub(crate) fn update_recent_stats(&mut self, now: SystemTime) {
+        for task in self.tasks.values() {
+            task.borrow_mut().update_recent_stats(now);
+        }
+    }
+
+    /// Checks every task for warnings as of `now`.
+    ///
+    /// Some warnings depend on how long a task has been in a particular state,
+    /// so every task is checked again on each update, not only those whose
+    /// stats changed.
+    pub(crate) fn lint_tasks(&mut self, now: SystemTime) {
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            // A task that is blocking its thread doesn't send stats updates
+            // until the poll completes, so check the polls in progress too.
+            task.observe_current_poll(now);
+            task.lint(&self.linters, now);
+        }
+    }
+
+    /// Updates the longest poll of the task with the given span ID from its
+    /// poll time histogram.
+    pub(crate) fn update_poll_times(
+        &mut self,
+        span_id: u64,
+        histogram: &Histogram<u64>,
+        now: Option<SystemTime>,
+    ) {
+        let task = self.ids.get(span_id).and_then(|num| self.tasks.get(&num));
+        if let Some(task) = task {
+            let mut task = task.borrow_mut();
+            let longest_poll = Duration::from_nanos(histogram.max());
+            if longest_poll > task.longest_poll {
+                task.longest_poll = longest_poll;
+                if let Some(now) = now {
+                    task.lint(&self.linters, now);
+                }
+            }
+        }
+    }
+
+    /// Sets the resource that each task is waiting on which has been used
+    /// since the task was last polled, from a map of pretty task IDs to
+    /// resources.
+    pub(crate) fn set_active_resources(&mut self, mut active: HashMap<u64, ActiveResource>) {
+        for (num, task) in &self.tasks {
+            task.borrow_mut().active_resource = active.remove(num);
+        }
+    }
+
     pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
         self.tasks.retain(|_, task| {
             let task = task.borrow();
@@ -217,9 +354,19 @@ impl TasksState {
         self.linters.iter().filter(|linter| linter.count() > 0)
     }
 
+    /// Returns every enabled linter, whether or not any task has its warning.
+    pub(crate) fn linters(&self) -> &[Linter<Task>] {
+        &self.linters
+    }
+
     pub(crate) fn task(&self, id: u64) -> Option<TaskRef> {
         self.tasks.get(&id).map(Rc::downgrade)
     }
+
+    /// Returns all tasks.
+    pub(crate) fn tasks(&self) -> impl Iterator<Item = TaskRef> + '_ {
+        self.tasks.values().map(Rc::downgrade)
+    }
 }
 
 impl Details {
@@ -230,6 +377,10 @@ impl Details {
     pub(crate) fn poll_times_histogram(&self) -> Option<&Histogram<u64>> {
         self.poll_times_histogram.as_ref()
     }
+
+    pub(crate) fn recent_poll_times_histogram(&self) -> Option<&Histogram<u64>> {
+        self.recent_poll_times_histogram.as_ref()
+    }
 }
 
 impl Task {
@@ -306,6 +457,24 @@ impl Task {
         self.stats.polls
     }
 
+    /// Returns how many times per second the task was polled over the most
+    /// recent interval.
+    pub(crate) fn polls_per_sec(&self) -> f64 {
+        self.recent.polls_per_sec
+    }
+
+    /// Returns the percentage of the most recent interval that the task spent
+    /// being polled.
+    pub(crate) fn busy_percent(&self) -> f64 {
+        self.recent.busy_percent
+    }
+
+    /// Returns how many times per second the task was woken over the most
+    /// recent interval.
+    pub(crate) fn wakes_per_sec(&self) -> f64 {
+        self.recent.wakes_per_sec
+    }
+
     /// Returns the elapsed time since the task was last woken, relative to
     /// given `now` timestamp.
     ///
@@ -363,11 +532,11 @@ impl Task {
         &self.warnings[..]
     }
 
-    fn lint(&mut self, linters: &[Linter<Task>]) {
-        self.warnings.clear();
+    fn lint(&mut self, linters: &[Linter<Task>], now: SystemTime) {
+        let previous = std::mem::take(&mut self.warnings);
         for lint in linters {
             tracing::debug!(?lint, task = ?self, "checking...");
-            if let Some(warning) = lint.check(self) {
+            if let Some(warning) = lint.check(self, now, &previous) {
                 tracing::info!(?warning, task = ?self, "found a warning!");
                 self.warnings.push(warning)
             }
@@ -377,6 +546,80 @@ impl Task {
     pub(crate) fn location(&self) -> &str {
         &self.location
     }
+
+    /// Returns `true` if this task was spawned with `spawn_blocking`.
+    pub(crate) fn is_blocking(&self) -> bool {
+        self.is_blocking
+    }
+
+    /// Returns the longest single poll of this task that has been observed.
+    pub(crate) fn longest_poll(&self) -> Duration {
+        self.longest_poll
+    }
+
+    /// Returns when this task's most recent poll ended, if it has been polled.
+    pub(crate) fn last_poll_ended(&self) -> Option<SystemTime> {
+        self.stats.last_poll_ended
+    }
+
+    /// Returns the amount of time since this task's most recent poll ended,
+    /// as of the given `now` timestamp.
+    ///
+    /// Returns `None` if the task has never finished a poll.
+    pub(crate) fn since_poll(&self, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.last_poll_ended()?).ok()
+    }
+
+    /// Returns a resource this task is waiting on that other tasks have used
+    /// since this task was last polled, if there is one.
+    pub(crate) fn active_resource(&self) -> Option<&ActiveResource> {
+        self.active_resource.as_ref()
+    }
+
+    /// Updates the longest poll from the number of polls since the last
+    /// update, and the time they took.
+    fn observe_polls(&mut self, polls: u64, busy: Duration) {
+        if polls == 0 {
+            return;
+        }
+        // The longest of these polls took at least as long as their mean.
+        let mean = busy / u32::try_from(polls).unwrap_or(u32::MAX);
+        self.longest_poll = self.longest_poll.max(mean);
+    }
+
+    /// Computes the task's activity since its previous values were recorded,
+    /// and records its current values.
+    fn update_recent_stats(&mut self, now: SystemTime) {
+        let interval = match now.duration_since(self.recent.at) {
+            Ok(interval) if interval > Duration::ZERO => interval.as_secs_f64(),
+            // Keep the previous rates until some time has passed.
+            _ => return,
+        };
+        let busy = self.busy(now);
+        let recent = &mut self.recent;
+        recent.polls_per_sec = self.stats.polls.saturating_sub(recent.polls) as f64 / interval;
+        recent.busy_percent =
+            (busy.saturating_sub(recent.busy).as_secs_f64() / interval * 100.0).min(100.0);
+        recent.wakes_per_sec = self.stats.wakes.saturating_sub(recent.wakes) as f64 / interval;
+        recent.at = now;
+        recent.polls = self.stats.polls;
+        recent.busy = busy;
+        recent.wakes = self.stats.wakes;
+    }
+
+    /// Updates the longest poll from the poll that is currently in progress,
+    /// if there is one.
+    fn observe_current_poll(&mut self, now: SystemTime) {
+        if !self.is_running() {
+            return;
+        }
+        let current = self
+            .stats
+            .last_poll_started
+            .and_then(|started| now.duration_since(started).ok())
+            .unwrap_or_default();
+        self.longest_poll = self.longest_poll.max(current);
+    }
 }
 
 impl From<proto::tasks::Stats> for TaskStats {
@@ -411,6 +654,21 @@ impl From<proto::tasks::Stats> for TaskStats {
     }
 }
 
+impl RecentStats {
+    /// Returns the stats of a task that was spawned `at` the given time.
+    fn new(at: SystemTime) -> Self {
+        Self {
+            at,
+            polls: 0,
+            busy: Duration::ZERO,
+            wakes: 0,
+            polls_per_sec: 0.0,
+            busy_percent: 0.0,
+            wakes_per_sec: 0.0,
+        }
+    }
+}
+
 impl Default for SortBy {
     fn default() -> Self {
         Self::Total
@@ -441,6 +699,9 @@ impl SortBy {
             Self::Polls => {
                 tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().stats.polls))
             }
+            Self::PollsPerSec => sort_by_rate(tasks, Task::polls_per_sec),
+            Self::BusyPercent => sort_by_rate(tasks, Task::busy_percent),
+            Self::WakesPerSec => sort_by_rate(tasks, Task::wakes_per_sec),
             Self::Target => {
                 tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().target.clone()))
             }
@@ -450,6 +711,16 @@ impl SortBy {
     }
 }
 
+/// Sorts `tasks` by one of their recent rates, which are floats and so can't
+/// be used as a sort key.
+fn sort_by_rate(tasks: &mut [Weak<RefCell<Task>>], rate: fn(&Task) -> f64) {
+    tasks.sort_unstable_by(|a, b| {
+        let a = a.upgrade().map(|t| rate(&t.borrow()));
+        let b = b.upgrade().map(|t| rate(&t.borrow()));
+        a.partial_cmp(&b).unwrap_or(cmp::Ordering::Equal)
+    })
+}
+
 impl view::SortBy for SortBy {
     fn as_column(&self) -> usize {
         *self as usize
@@ -468,6 +739,9 @@ impl TryFrom<usize> for SortBy {
             idx if idx == Self::Busy as usize => Ok(Self::Busy),
             idx if idx == Self::Idle as usize => Ok(Self::Idle),
             idx if idx == Self::Polls as usize => Ok(Self::Polls),
+            idx if idx == Self::PollsPerSec as usize => Ok(Self::PollsPerSec),
+            idx if idx == Self::BusyPercent as usize => Ok(Self::BusyPercent),
+            idx if idx == Self::WakesPerSec as usize => Ok(Self::WakesPerSec),
             idx if idx == Self::Target as usize => Ok(Self::Target),
             idx if idx == Self::Location as usize => Ok(Self::Location),
             _ => Err(()),
@@ -42,3 +42,15 @@ impl Drop for OnShutdown {
         }
     }
 }
+
+/// Copies `text` to the clipboard of the terminal the console is running in.
+///
+/// This uses the OSC 52 escape sequence, which is supported by most terminal
+/// emulators (and passed on by `tmux` when `set-clipboard` is enabled), so it
+/// also works when the console is running on a remote host.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    use std::io::Write;
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    stdout.flush()
+}
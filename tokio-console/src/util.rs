@@ -32,3 +32,19 @@ pub(crate) fn percentage(total: f64, amount: f64) -> f64 {
     );
     (amount / total) * 100.0
 }
+
+/// Serializes a duration as a number of seconds.
+pub(crate) fn serialize_secs<S: serde::Serializer>(
+    duration: &std::time::Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Serializes a timestamp as an RFC 3339 string.
+pub(crate) fn serialize_time<S: serde::Serializer>(
+    time: &std::time::SystemTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&humantime::format_rfc3339_nanos(*time))
+}
@@ -1,15 +1,16 @@
 use crate::{
     state::{
-        async_ops::{AsyncOp, SortBy},
+        async_ops::{AsyncOp, AsyncOpRef, SortBy},
         State,
     },
     view::{
         self, bold,
         table::{self, TableList, TableListState},
-        DUR_LEN, DUR_PRECISION,
+        warnings, DUR_LEN, DUR_PRECISION,
     },
 };
 
+use regex::Regex;
 use tui::{
     layout,
     style::{self, Color, Style},
@@ -31,6 +32,7 @@ impl TableList for AsyncOpsTable {
     type Context = AsyncOpsTableCtx;
 
     const HEADER: &'static [&'static str] = &[
+        "Warn",
         "ID",
         "Parent",
         "Task",
@@ -42,6 +44,11 @@ impl TableList for AsyncOpsTable {
         "Attributes",
     ];
 
+    fn matches(async_op: &AsyncOp, filter: &Regex) -> bool {
+        filter.is_match(async_op.source())
+            || table::fields_match(async_op.formatted_attributes(), filter)
+    }
+
     fn render<B: tui::backend::Backend>(
         table_list_state: &mut TableListState<Self>,
         styles: &view::Styles,
@@ -62,35 +69,30 @@ impl TableList for AsyncOpsTable {
             resource_id,
         } = ctx;
 
-        if initial_render {
-            table_list_state
-                .sorted_items
-                .extend(state.async_ops_state().async_ops().filter(|op| {
-                    op.upgrade()
-                        .map(|op| resource_id == op.borrow().resource_id())
-                        .unwrap_or(false)
-                }))
+        let async_ops = state.async_ops_state_mut();
+        let is_resource_op = |op: &AsyncOpRef| {
+            op.upgrade()
+                .map(|op| resource_id == op.borrow().resource_id())
+                .unwrap_or(false)
+        };
+        if table_list_state.take_filter_changed() || initial_render {
+            async_ops.take_new_async_ops().for_each(drop);
+            table_list_state.sorted_items.clear();
+            table_list_state.extend_filtered(async_ops.async_ops().filter(is_resource_op), now)
         } else {
-            table_list_state.sorted_items.extend(
-                state
-                    .async_ops_state_mut()
-                    .take_new_async_ops()
-                    .filter(|op| {
-                        op.upgrade()
-                            .map(|op| resource_id == op.borrow().resource_id())
-                            .unwrap_or(false)
-                    }),
-            )
+            table_list_state
+                .extend_filtered(async_ops.take_new_async_ops().filter(is_resource_op), now)
         };
         table_list_state
             .sort_by
             .sort(now, &mut table_list_state.sorted_items);
 
-        let mut id_width = view::Width::new(Self::HEADER[0].len() as u16);
-        let mut parent_width = view::Width::new(Self::HEADER[1].len() as u16);
-        let mut task_width = view::Width::new(Self::HEADER[2].len() as u16);
-        let mut source_width = view::Width::new(Self::HEADER[3].len() as u16);
-        let mut polls_width = view::Width::new(Self::HEADER[7].len() as u16);
+        let mut warn_width = view::Width::new(Self::HEADER[0].len() as u16);
+        let mut id_width = view::Width::new(Self::HEADER[1].len() as u16);
+        let mut parent_width = view::Width::new(Self::HEADER[2].len() as u16);
+        let mut task_width = view::Width::new(Self::HEADER[3].len() as u16);
+        let mut source_width = view::Width::new(Self::HEADER[4].len() as u16);
+        let mut polls_width = view::Width::new(Self::HEADER[8].len() as u16);
 
         let dur_cell = |dur: std::time::Duration| -> Cell<'static> {
             Cell::from(styles.time_units(format!(
@@ -102,6 +104,7 @@ impl TableList for AsyncOpsTable {
         };
 
         let rows = {
+            let warn_width = &mut warn_width;
             let id_width = &mut id_width;
             let parent_width = &mut parent_width;
             let task_width = &mut task_width;
@@ -123,6 +126,7 @@ impl TableList for AsyncOpsTable {
                     let task_str = task.unwrap_or_else(|| async_op.task_id_str().to_owned());
 
                     let mut row = Row::new(vec![
+                        warnings::count_cell(styles, async_op.warnings(), warn_width),
                         Cell::from(id_width.update_str(format!(
                             "{:>width$}",
                             async_op.id(),
@@ -180,10 +184,9 @@ impl TableList for AsyncOpsTable {
             Table::new(rows.rev())
         };
 
-        let block = styles.border_block().title(vec![bold(format!(
-            "Async Ops ({}) ",
-            table_list_state.len()
-        ))]);
+        let mut title = vec![bold(format!("Async Ops ({}) ", table_list_state.len()))];
+        title.extend(table_list_state.filter_title());
+        let block = styles.border_block().title(title);
 
         let layout = layout::Layout::default()
             .direction(layout::Direction::Vertical)
@@ -205,6 +208,7 @@ impl TableList for AsyncOpsTable {
 
         let attributes_width = layout::Constraint::Percentage(100);
         let widths = &[
+            warn_width.constraint(),
             id_width.constraint(),
             parent_width.constraint(),
             task_width.constraint(),
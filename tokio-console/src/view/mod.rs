@@ -1,4 +1,12 @@
-use crate::view::{resources::ResourcesTable, table::TableListState, tasks::TasksTable};
+use crate::view::{
+    overview::TaskHistory,
+    resource_tree::ResourceTree,
+    resources::ResourcesTable,
+    table::TableListState,
+    task_groups::TaskGroupsList,
+    tasks::TasksTable,
+    warnings::{Entity, WarningsList},
+};
 use crate::{input, state::State};
 use std::{borrow::Cow, cmp};
 use tui::{
@@ -9,12 +17,16 @@ use tui::{
 
 mod async_ops;
 mod mini_histogram;
+mod overview;
 mod resource;
+mod resource_tree;
 mod resources;
 mod styles;
 mod table;
 mod task;
+mod task_groups;
 mod tasks;
+mod warnings;
 pub(crate) use self::styles::{Palette, Styles};
 pub(crate) use self::table::SortBy;
 
@@ -34,7 +46,15 @@ pub struct View {
     /// --- e.g., if the user previously selected a particular sorting, we want
     /// it to remain sorted that way when we return to it.
     tasks_list: TableListState<TasksTable>,
+    /// The views that led to the current one, most recent last, so that
+    /// `Esc` returns to the previous view when following links between
+    /// tasks and resources.
+    back_stack: Vec<ViewState>,
     resources_list: TableListState<ResourcesTable>,
+    warnings_list: WarningsList,
+    task_groups: TaskGroupsList,
+    resource_tree: ResourceTree,
+    overview: TaskHistory,
     state: ViewState,
     pub(crate) styles: Styles,
 }
@@ -44,6 +64,14 @@ pub(crate) enum ViewState {
     TasksList,
     /// The table list of all resources.
     ResourcesList,
+    /// The table list of every active warning.
+    WarningsList,
+    /// The table list of tasks grouped by spawn location, name or target.
+    TaskGroups,
+    /// The tree of resources arranged by their parents.
+    ResourceTree,
+    /// Sparklines of the task statistics over time.
+    Overview,
     /// Inspecting a single task instance.
     TaskInstance(self::task::TaskView),
     /// Inspecting a single resource instance.
@@ -88,7 +116,12 @@ impl View {
         Self {
             state: ViewState::TasksList,
             tasks_list: TableListState::<TasksTable>::default(),
+            back_stack: Vec::new(),
             resources_list: TableListState::<ResourcesTable>::default(),
+            warnings_list: WarningsList::default(),
+            task_groups: TaskGroupsList::default(),
+            resource_tree: ResourceTree::default(),
+            overview: TaskHistory,
             styles,
         }
     }
@@ -101,18 +134,32 @@ impl View {
                 // The enter key changes views, so handle here since we can
                 // mutate the currently selected view.
                 match event {
+                    _ if self.tasks_list.is_editing_filter() => {
+                        self.tasks_list.update_input(event);
+                    }
                     key!(Enter) => {
                         if let Some(task) = self.tasks_list.selected_item().upgrade() {
-                            update_kind = UpdateKind::SelectTask(task.borrow().span_id());
-                            self.state = TaskInstance(self::task::TaskView::new(
+                            update_kind = self.push_view(TaskInstance(self::task::TaskView::new(
                                 task,
                                 state.task_details_ref(),
-                            ));
+                            )));
                         }
                     }
                     key!(Char('r')) => {
                         self.state = ResourcesList;
                     }
+                    key!(Char('w')) => {
+                        self.state = WarningsList;
+                    }
+                    key!(Char('s')) => {
+                        self.state = TaskGroups;
+                    }
+                    key!(Char('p')) => {
+                        self.state = ResourceTree;
+                    }
+                    key!(Char('o')) => {
+                        self.state = Overview;
+                    }
                     _ => {
                         // otherwise pass on to view
                         self.tasks_list.update_input(event);
@@ -121,28 +168,192 @@ impl View {
             }
             ResourcesList => {
                 match event {
+                    _ if self.resources_list.is_editing_filter() => {
+                        self.resources_list.update_input(event);
+                    }
                     key!(Enter) => {
                         if let Some(res) = self.resources_list.selected_item().upgrade() {
-                            update_kind = UpdateKind::SelectResource(res.borrow().span_id());
-                            self.state = ResourceInstance(self::resource::ResourceView::new(res));
+                            update_kind = self.push_view(ResourceInstance(
+                                self::resource::ResourceView::new(res),
+                            ));
                         }
                     }
                     key!(Char('t')) => {
                         self.state = TasksList;
                     }
+                    key!(Char('w')) => {
+                        self.state = WarningsList;
+                    }
+                    key!(Char('s')) => {
+                        self.state = TaskGroups;
+                    }
+                    key!(Char('p')) => {
+                        self.state = ResourceTree;
+                    }
+                    key!(Char('o')) => {
+                        self.state = Overview;
+                    }
                     _ => {
                         // otherwise pass on to view
                         self.resources_list.update_input(event);
                     }
                 }
             }
+            WarningsList => {
+                match event {
+                    _ if self.warnings_list.is_editing_filter() => {
+                        self.warnings_list.update_input(event);
+                    }
+                    key!(Enter) => {
+                        // Jump straight to the entity that has the warning. For
+                        // async ops, that's the resource they operate on.
+                        match self.warnings_list.selected() {
+                            Some(Entity::Task(id)) => {
+                                let task = state.tasks_state().task(id);
+                                if let Some(task) = task.and_then(|task| task.upgrade()) {
+                                    update_kind = self.push_view(TaskInstance(
+                                        self::task::TaskView::new(task, state.task_details_ref()),
+                                    ));
+                                }
+                            }
+                            Some(Entity::Resource(id))
+                            | Some(Entity::AsyncOp {
+                                resource_id: id, ..
+                            }) => {
+                                let res = state.resources_state().resource(id);
+                                if let Some(res) = res.and_then(|res| res.upgrade()) {
+                                    update_kind = self.push_view(ResourceInstance(
+                                        self::resource::ResourceView::new(res),
+                                    ));
+                                }
+                            }
+                            None => {}
+                        }
+                    }
+                    key!(Char('t')) => {
+                        self.state = TasksList;
+                    }
+                    key!(Char('r')) => {
+                        self.state = ResourcesList;
+                    }
+                    key!(Char('s')) => {
+                        self.state = TaskGroups;
+                    }
+                    key!(Char('p')) => {
+                        self.state = ResourceTree;
+                    }
+                    key!(Char('o')) => {
+                        self.state = Overview;
+                    }
+                    _ => {
+                        // otherwise pass on to view
+                        self.warnings_list.update_input(event);
+                    }
+                }
+            }
+            TaskGroups => {
+                match event {
+                    _ if self.task_groups.is_editing_filter() => {
+                        self.task_groups.update_input(event);
+                    }
+                    key!(Enter) => {
+                        // Drill down into the tasks in the selected group.
+                        if let Some(filter) = self.task_groups.selected_filter() {
+                            self.tasks_list.set_filter_text(&filter);
+                            self.state = TasksList;
+                        }
+                    }
+                    key!(Char('t')) => {
+                        self.state = TasksList;
+                    }
+                    key!(Char('r')) => {
+                        self.state = ResourcesList;
+                    }
+                    key!(Char('w')) => {
+                        self.state = WarningsList;
+                    }
+                    key!(Char('p')) => {
+                        self.state = ResourceTree;
+                    }
+                    key!(Char('o')) => {
+                        self.state = Overview;
+                    }
+                    _ => {
+                        // otherwise pass on to view
+                        self.task_groups.update_input(event);
+                    }
+                }
+            }
+            ResourceTree => {
+                match event {
+                    key!(Enter) => {
+                        if let Some(res) = self.resource_tree.selected_resource(state) {
+                            update_kind = self.push_view(ResourceInstance(
+                                self::resource::ResourceView::new(res),
+                            ));
+                        }
+                    }
+                    key!(Char('t')) => {
+                        self.state = TasksList;
+                    }
+                    key!(Char('r')) => {
+                        self.state = ResourcesList;
+                    }
+                    key!(Char('w')) => {
+                        self.state = WarningsList;
+                    }
+                    key!(Char('s')) => {
+                        self.state = TaskGroups;
+                    }
+                    key!(Char('o')) => {
+                        self.state = Overview;
+                    }
+                    _ => {
+                        // otherwise pass on to view
+                        self.resource_tree.update_input(event);
+                    }
+                }
+            }
+            Overview => match event {
+                key!(Char('t')) => {
+                    self.state = TasksList;
+                }
+                key!(Char('r')) => {
+                    self.state = ResourcesList;
+                }
+                key!(Char('w')) => {
+                    self.state = WarningsList;
+                }
+                key!(Char('s')) => {
+                    self.state = TaskGroups;
+                }
+                key!(Char('p')) => {
+                    self.state = ResourceTree;
+                }
+                _ => {}
+            },
             ResourceInstance(ref mut view) => {
                 // The escape key changes views, so handle here since we can
                 // mutate the currently selected view.
                 match event {
+                    _ if view.is_editing_filter() => {
+                        view.update_input(event);
+                    }
                     key!(Esc) => {
-                        self.state = ResourcesList;
-                        update_kind = UpdateKind::Other;
+                        update_kind = self.pop_view(ResourcesList);
+                    }
+                    key!(Enter) => {
+                        // View the task that the selected async op belongs to.
+                        let task = view
+                            .selected_task_id()
+                            .and_then(|id| state.tasks_state().task(id))
+                            .and_then(|task| task.upgrade());
+                        if let Some(task) = task {
+                            update_kind = self.push_view(TaskInstance(self::task::TaskView::new(
+                                task,
+                                state.task_details_ref(),
+                            )));
+                        }
                     }
                     _ => {
                         // otherwise pass on to view
@@ -155,8 +366,20 @@ impl View {
                 // mutate the currently selected view.
                 match event {
                     key!(Esc) => {
-                        self.state = TasksList;
-                        update_kind = UpdateKind::ExitTaskView;
+                        update_kind = self.pop_view(TasksList);
+                    }
+                    key!(Enter) => {
+                        // View the resource that the selected async op is
+                        // waiting on.
+                        let res = view
+                            .selected_resource_id()
+                            .and_then(|id| state.resources_state().resource(id))
+                            .and_then(|res| res.upgrade());
+                        if let Some(res) = res {
+                            update_kind = self.push_view(ResourceInstance(
+                                self::resource::ResourceView::new(res),
+                            ));
+                        }
                     }
                     _ => {
                         // otherwise pass on to view
@@ -168,6 +391,37 @@ impl View {
         update_kind
     }
 
+    /// Shows `view`, remembering the current view so that `Esc` returns to
+    /// it.
+    fn push_view(&mut self, view: ViewState) -> UpdateKind {
+        let previous = std::mem::replace(&mut self.state, view);
+        let update_kind = self.update_kind_from(&previous);
+        self.back_stack.push(previous);
+        update_kind
+    }
+
+    /// Returns to the view shown before the current one, or to `list` if
+    /// there isn't one.
+    fn pop_view(&mut self, list: ViewState) -> UpdateKind {
+        let view = self.back_stack.pop().unwrap_or(list);
+        let previous = std::mem::replace(&mut self.state, view);
+        self.update_kind_from(&previous)
+    }
+
+    /// Returns the outcome of switching from the `previous` view to the
+    /// current one.
+    ///
+    /// The task details stream is stopped when leaving a task's view, and
+    /// started again when returning to it.
+    fn update_kind_from(&self, previous: &ViewState) -> UpdateKind {
+        match self.state {
+            ViewState::TaskInstance(ref view) => UpdateKind::SelectTask(view.span_id()),
+            _ if matches!(previous, ViewState::TaskInstance(_)) => UpdateKind::ExitTaskView,
+            ViewState::ResourceInstance(ref view) => UpdateKind::SelectResource(view.span_id()),
+            _ => UpdateKind::Other,
+        }
+    }
+
     pub(crate) fn render<B: tui::backend::Backend>(
         &mut self,
         frame: &mut tui::terminal::Frame<B>,
@@ -182,11 +436,20 @@ impl View {
                 self.resources_list
                     .render(&self.styles, frame, area, state, ());
             }
+            ViewState::WarningsList => {
+                self.warnings_list.render(&self.styles, frame, area, state);
+            }
+            ViewState::TaskGroups => {
+                self.task_groups.render(&self.styles, frame, area, state);
+            }
+            ViewState::ResourceTree => {
+                self.resource_tree.render(&self.styles, frame, area, state);
+            }
+            ViewState::Overview => {
+                self.overview.render(&self.styles, frame, area, state);
+            }
             ViewState::TaskInstance(ref mut view) => {
-                let now = state
-                    .last_updated_at()
-                    .expect("task view implies we've received an update");
-                view.render(&self.styles, frame, area, now);
+                view.render(&self.styles, frame, area, state);
             }
             ViewState::ResourceInstance(ref mut view) => {
                 view.render(&self.styles, frame, area, state);
@@ -196,6 +459,56 @@ impl View {
         state.retain_active();
     }
 
+    /// Rebinds the view to a `State` that replaced the one it was displaying,
+    /// such as when a replay is rewound.
+    ///
+    /// Returns `false` if a task was being inspected and it no longer exists.
+    pub(crate) fn reset(&mut self, state: &mut State) -> bool {
+        use ViewState::*;
+        self.tasks_list.sorted_items.clear();
+        self.resources_list.sorted_items.clear();
+        self.warnings_list.clear();
+        self.task_groups.clear();
+        self.resource_tree.clear();
+        // The views that led here may show tasks and resources that no longer
+        // exist, so `Esc` returns to the lists instead.
+        self.back_stack.clear();
+        self.state = match std::mem::replace(&mut self.state, TasksList) {
+            TaskInstance(view) => {
+                let task = state.tasks_state().task(view.task_id());
+                match task.and_then(|task| task.upgrade()) {
+                    Some(task) => {
+                        TaskInstance(self::task::TaskView::new(task, state.task_details_ref()))
+                    }
+                    None => return false,
+                }
+            }
+            ResourceInstance(view) => {
+                let resource = state.resources_state_mut().resource(view.resource_id());
+                match resource.and_then(|resource| resource.upgrade()) {
+                    Some(resource) => ResourceInstance(self::resource::ResourceView::new(resource)),
+                    None => ResourcesList,
+                }
+            }
+            view => view,
+        };
+        true
+    }
+
+    /// Returns `true` if a table's filter prompt is open, in which case every
+    /// key should be passed to the view, rather than handled as a global
+    /// control.
+    pub(crate) fn is_editing_filter(&self) -> bool {
+        match self.state {
+            ViewState::TasksList => self.tasks_list.is_editing_filter(),
+            ViewState::ResourcesList => self.resources_list.is_editing_filter(),
+            ViewState::WarningsList => self.warnings_list.is_editing_filter(),
+            ViewState::TaskGroups => self.task_groups.is_editing_filter(),
+            ViewState::ResourceInstance(ref view) => view.is_editing_filter(),
+            ViewState::ResourceTree | ViewState::Overview | ViewState::TaskInstance(_) => false,
+        }
+    }
+
     pub(crate) fn current_view(&self) -> &ViewState {
         &self.state
     }
@@ -0,0 +1,155 @@
+use crate::{
+    state::{history::Sample, State},
+    view::{self, bold, DUR_PRECISION},
+};
+use std::time::Duration;
+use tui::{
+    layout::{self, Constraint, Direction, Layout},
+    style::Color,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Sparkline},
+};
+
+/// Sparklines of the task statistics over the history window, so that trends
+/// are visible rather than only the latest values.
+#[derive(Debug)]
+pub(crate) struct TaskHistory;
+
+/// A statistic plotted by the overview.
+struct Series {
+    title: &'static str,
+    value: fn(&Sample) -> f64,
+    format: fn(f64) -> String,
+}
+
+/// The height the sparklines are scaled to, so that statistics with
+/// fractional values can be plotted.
+const SPARKLINE_MAX: u64 = 1000;
+
+// === impl TaskHistory ===
+
+impl TaskHistory {
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &mut self,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &mut State,
+    ) {
+        let series = [
+            Series {
+                title: "Running tasks",
+                value: |sample| sample.running as f64,
+                format: format_count,
+            },
+            Series {
+                title: "Idle tasks",
+                value: |sample| sample.idle as f64,
+                format: format_count,
+            },
+            Series {
+                title: "Completed tasks",
+                value: |sample| sample.completed as f64,
+                format: format_count,
+            },
+            Series {
+                title: "Spawns",
+                value: Sample::spawn_rate,
+                format: format_rate,
+            },
+            Series {
+                title: "Completions",
+                value: Sample::completion_rate,
+                format: format_rate,
+            },
+            Series {
+                title: "Busy time per update",
+                value: |sample| sample.totals.busy.as_secs_f64(),
+                format: format_duration,
+            },
+            Series {
+                title: "Polls",
+                value: Sample::polls_per_second,
+                format: format_rate,
+            },
+        ];
+
+        let history = state.history();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Spans::from(vec![
+                bold("Task history"),
+                Span::from(format!(
+                    " (last {})",
+                    humantime::format_duration(history.window())
+                )),
+            ]));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                series
+                    .iter()
+                    .map(|_| Constraint::Ratio(1, series.len() as u32))
+                    .collect::<Vec<_>>(),
+            )
+            .split(inner);
+
+        for (series, area) in series.iter().zip(chunks) {
+            // Only plot as many samples as there is room for, most recent last.
+            let width = area.width.saturating_sub(2) as usize;
+            let samples = history.samples();
+            let skip = samples.len().saturating_sub(width);
+            let values: Vec<f64> = samples.skip(skip).map(series.value).collect();
+
+            let max = values.iter().copied().fold(0.0, f64::max);
+            let data: Vec<u64> = values
+                .iter()
+                .map(|value| {
+                    if max > 0.0 {
+                        (value / max * SPARKLINE_MAX as f64).round() as u64
+                    } else {
+                        0
+                    }
+                })
+                .collect();
+
+            let mut title = vec![bold(series.title)];
+            if let Some(&latest) = values.last() {
+                title.push(Span::from(format!(
+                    ": {} (max {})",
+                    (series.format)(latest),
+                    (series.format)(max),
+                )));
+            }
+            let sparkline = Sparkline::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(Spans::from(title)),
+                )
+                .data(&data)
+                .max(SPARKLINE_MAX)
+                .style(styles.fg(Color::Cyan));
+            frame.render_widget(sparkline, area);
+        }
+    }
+}
+
+fn format_count(value: f64) -> String {
+    format!("{}", value as u64)
+}
+
+fn format_rate(value: f64) -> String {
+    format!("{:.2}/s", value)
+}
+
+fn format_duration(value: f64) -> String {
+    format!(
+        "{:.prec$?}",
+        Duration::from_secs_f64(value),
+        prec = DUR_PRECISION
+    )
+}
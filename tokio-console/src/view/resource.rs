@@ -12,7 +12,7 @@ use std::{cell::RefCell, rc::Rc};
 use tui::{
     layout::{self, Layout},
     text::{Span, Spans, Text},
-    widgets::{Block, Paragraph},
+    widgets::{Block, List, ListItem, Paragraph},
 };
 
 pub(crate) struct ResourceView {
@@ -30,10 +30,30 @@ impl ResourceView {
         }
     }
 
+    pub(crate) fn resource_id(&self) -> u64 {
+        self.resource.borrow().id()
+    }
+
+    pub(crate) fn span_id(&self) -> u64 {
+        self.resource.borrow().span_id()
+    }
+
+    /// Returns the ID of the task that the selected async op belongs to, if
+    /// one is selected.
+    pub(crate) fn selected_task_id(&self) -> Option<u64> {
+        let async_op = self.async_ops_table.selected_item().upgrade()?;
+        let task_id = async_op.borrow().task_id();
+        task_id
+    }
+
     pub(crate) fn update_input(&mut self, event: input::Event) {
         self.async_ops_table.update_input(event)
     }
 
+    pub(crate) fn is_editing_filter(&self) -> bool {
+        self.async_ops_table.is_editing_filter()
+    }
+
     pub(crate) fn render<B: tui::backend::Backend>(
         &mut self,
         styles: &view::Styles,
@@ -42,23 +62,67 @@ impl ResourceView {
         state: &mut State,
     ) {
         let resource = &*self.resource.borrow();
+        let now = match state.last_updated_at() {
+            Some(now) => now,
+            // If we have never gotten an update yet, skip...
+            None => return,
+        };
 
-        let (controls_area, stats_area, async_ops_area) = {
+        // List the warnings for the resource itself, followed by those for
+        // each of its async ops.
+        let mut warnings: Vec<_> = resource
+            .warnings()
+            .iter()
+            .map(|linter| {
+                ListItem::new(Text::from(Spans::from(vec![
+                    styles.severity_wide(linter.severity()),
+                    Span::from(linter.format(resource, now)),
+                ])))
+            })
+            .collect();
+        let mut async_ops = state
+            .async_ops_state()
+            .async_ops()
+            .filter_map(|op| op.upgrade())
+            .filter(|op| {
+                let op = op.borrow();
+                op.resource_id() == resource.id() && !op.warnings().is_empty()
+            })
+            .collect::<Vec<_>>();
+        async_ops.sort_by_key(|op| op.borrow().id());
+        for op in async_ops {
+            let op = op.borrow();
+            warnings.extend(op.warnings().iter().map(|linter| {
+                ListItem::new(Text::from(Spans::from(vec![
+                    styles.severity_wide(linter.severity()),
+                    bold(format!("async op {}: ", op.id())),
+                    Span::from(linter.format(&op, now)),
+                ])))
+            }));
+        }
+
+        let (controls_area, stats_area, warnings_area, async_ops_area) = {
+            let mut constraints = vec![
+                // controls
+                layout::Constraint::Length(1),
+                // resource stats
+                layout::Constraint::Length(8),
+            ];
+            if !warnings.is_empty() {
+                // warnings (add 2 for top and bottom borders)
+                constraints.push(layout::Constraint::Length(warnings.len() as u16 + 2));
+            }
+            // async ops
+            constraints.push(layout::Constraint::Percentage(60));
             let chunks = Layout::default()
                 .direction(layout::Direction::Vertical)
-                .constraints(
-                    [
-                        // controls
-                        layout::Constraint::Length(1),
-                        // resource stats
-                        layout::Constraint::Length(8),
-                        // async ops
-                        layout::Constraint::Percentage(60),
-                    ]
-                    .as_ref(),
-                )
+                .constraints(constraints)
                 .split(area);
-            (chunks[0], chunks[1], chunks[2])
+            if warnings.is_empty() {
+                (chunks[0], chunks[1], None, chunks[2])
+            } else {
+                (chunks[0], chunks[1], Some(chunks[2]), chunks[3])
+            }
         };
 
         let stats_area = Layout::default()
@@ -75,7 +139,9 @@ impl ResourceView {
         let controls = Spans::from(vec![
             Span::raw("controls: "),
             bold(styles.if_utf8("\u{238B} esc", "esc")),
-            Span::raw(" = return to task list, "),
+            Span::raw(" = go back, "),
+            bold(styles.if_utf8("\u{21B5}", "enter")),
+            Span::raw(" = view the async op's task, "),
             bold("q"),
             Span::raw(" = quit"),
         ]);
@@ -110,6 +176,10 @@ impl ResourceView {
         frame.render_widget(Block::default().title(controls), controls_area);
         frame.render_widget(resource_widget, stats_area[0]);
         frame.render_widget(fields_widget, stats_area[1]);
+        if let Some(warnings_area) = warnings_area {
+            let warnings = List::new(warnings).block(styles.border_block().title("Warnings"));
+            frame.render_widget(warnings, warnings_area);
+        }
         let ctx = AsyncOpsTableCtx {
             initial_render: self.initial_render,
             resource_id: resource.id(),
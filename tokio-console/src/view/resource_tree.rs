@@ -0,0 +1,448 @@
+use crate::{
+    input,
+    state::{
+        resources::{Resource, TypeVisibility},
+        State,
+    },
+    view::{self, bold, table::Controls, DUR_LEN, DUR_PRECISION},
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+use tui::{
+    layout,
+    style::{self, Style},
+    text::{Span, Spans, Text},
+    widgets::{Cell, Row, Table, TableState},
+};
+
+/// Resources arranged as a tree by their parent resource, such as the
+/// semaphore inside a `Mutex`.
+///
+/// Each row summarizes the subtree rooted at its resource. Collapsed
+/// subtrees and hidden internal resources are still counted in the summaries
+/// of the resources above them.
+#[derive(Debug, Default)]
+pub(crate) struct ResourceTree {
+    /// The IDs of the resources whose children are hidden.
+    collapsed: HashSet<u64>,
+    /// If `true`, internal resources are hidden.
+    hide_internal: bool,
+    /// The IDs of the resources in the order they were last rendered, for
+    /// moving the selection.
+    rows: Vec<u64>,
+    selected: Option<u64>,
+    table_state: TableState,
+}
+
+/// The aggregated stats of a resource and its descendants.
+#[derive(Clone, Debug, Default)]
+struct Subtree {
+    resources: usize,
+    live: usize,
+    warnings: usize,
+    attributes: Vec<(String, Aggregate)>,
+}
+
+/// An attribute aggregated over a subtree.
+#[derive(Clone, Debug)]
+enum Aggregate {
+    /// The sum of a numeric attribute, and its unit.
+    Sum(f64, String),
+    /// How many of a boolean attribute's values are `true`, out of all of
+    /// them.
+    Count { set: usize, total: usize },
+    /// Any other attribute, while every resource has the same value for it.
+    Same(String),
+    /// An attribute with differing values that can't be aggregated.
+    Mixed,
+}
+
+struct TreeRow {
+    resource: Rc<RefCell<Resource>>,
+    depth: usize,
+    has_children: bool,
+    subtree: Subtree,
+}
+
+const HEADER: &[&str] = &[
+    "Warn",
+    "ID",
+    "Kind",
+    "Type",
+    "VIS",
+    "Total",
+    "Subtree",
+    "Live",
+    "Location",
+    "Attributes",
+];
+
+// === impl ResourceTree ===
+
+impl ResourceTree {
+    pub(crate) fn update_input(&mut self, event: input::Event) {
+        use input::KeyCode::*;
+        let code = match event {
+            input::Event::Key(input::KeyEvent { code, .. }) => code,
+            _ => return,
+        };
+        match code {
+            Down | Char('j') => self.scroll(|i, len| (i + 1) % len),
+            Up | Char('k') => self.scroll(|i, len| (i + len - 1) % len),
+            Right | Char('l') => {
+                if let Some(id) = self.selected {
+                    self.collapsed.remove(&id);
+                }
+            }
+            Left | Char('h') => {
+                if let Some(id) = self.selected {
+                    self.collapsed.insert(id);
+                }
+            }
+            Char('v') => self.hide_internal = !self.hide_internal,
+            _ => {}
+        }
+    }
+
+    fn scroll(&mut self, f: impl Fn(usize, usize) -> usize) {
+        if self.rows.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let i = self
+            .selected
+            .and_then(|id| self.rows.iter().position(|&row| row == id))
+            .map(|i| f(i, self.rows.len()))
+            .unwrap_or(0);
+        self.selected = Some(self.rows[i]);
+    }
+
+    /// Returns the selected resource, if there is one.
+    pub(crate) fn selected_resource(&self, state: &State) -> Option<Rc<RefCell<Resource>>> {
+        state
+            .resources_state()
+            .resource(self.selected?)
+            .and_then(|resource| resource.upgrade())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.rows.clear();
+        self.selected = None;
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &mut self,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &mut State,
+    ) {
+        let now = match state.last_updated_at() {
+            Some(now) => now,
+            // If we have never gotten an update yet, skip...
+            None => return,
+        };
+
+        let resources: Vec<_> = state
+            .resources_state()
+            .resources()
+            .filter_map(|resource| resource.upgrade())
+            .collect();
+        let ids: HashSet<u64> = resources.iter().map(|r| r.borrow().id()).collect();
+
+        // Resources whose parent is no longer around are listed as roots.
+        let mut children: HashMap<Option<u64>, Vec<Rc<RefCell<Resource>>>> = HashMap::new();
+        for resource in resources {
+            let parent = resource.borrow().parent_num().filter(|id| ids.contains(id));
+            children.entry(parent).or_default().push(resource);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_unstable_by_key(|resource| resource.borrow().id());
+        }
+
+        let mut rows = Vec::new();
+        for root in children.get(&None).into_iter().flatten() {
+            if !self.is_hidden(&root.borrow()) {
+                self.visit(root, 0, true, &children, &mut rows);
+            }
+        }
+
+        self.rows = rows.iter().map(|row| row.resource.borrow().id()).collect();
+        let selected = self
+            .selected
+            .and_then(|id| self.rows.iter().position(|&row| row == id));
+        if selected.is_none() {
+            self.selected = None;
+        }
+        self.table_state.select(selected);
+
+        let mut warn_width = view::Width::new(HEADER[0].len() as u16);
+        let mut id_width = view::Width::new(HEADER[1].len() as u16);
+        let mut kind_width = view::Width::new(HEADER[2].len() as u16);
+        let mut type_width = view::Width::new(HEADER[3].len() as u16);
+        let viz_len = HEADER[4].len() as u16;
+        let mut subtree_width = view::Width::new(HEADER[6].len() as u16);
+        let mut live_width = view::Width::new(HEADER[7].len() as u16);
+        let mut location_width = view::Width::new(HEADER[8].len() as u16);
+
+        let table_rows = rows.iter().map(|row| {
+            let resource = row.resource.borrow();
+            let subtree = &row.subtree;
+            let marker = if !row.has_children {
+                "  "
+            } else if self.collapsed.contains(&resource.id()) {
+                styles.if_utf8("\u{25B8} ", "+ ")
+            } else {
+                styles.if_utf8("\u{25BE} ", "- ")
+            };
+            let warns = if subtree.warnings > 0 {
+                warn_width.update_len(subtree.warnings.to_string().len() + 2);
+                Cell::from(Spans::from(vec![
+                    styles.warning_narrow(),
+                    Span::from(subtree.warnings.to_string()),
+                ]))
+            } else {
+                Cell::from("")
+            };
+            let attributes = subtree
+                .attributes
+                .iter()
+                .filter_map(|(name, aggregate)| Some(format!("{}={}", name, aggregate.display()?)))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let mut row = Row::new(vec![
+                warns,
+                Cell::from(id_width.update_str(format!(
+                    "{}{}{}",
+                    "  ".repeat(row.depth),
+                    marker,
+                    resource.id()
+                ))),
+                Cell::from(kind_width.update_str(resource.kind()).to_owned()),
+                Cell::from(type_width.update_str(resource.concrete_type()).to_owned()),
+                Cell::from(resource.type_visibility().render(styles)),
+                Cell::from(styles.time_units(format!(
+                    "{:>width$.prec$?}",
+                    resource.total(now),
+                    width = DUR_LEN,
+                    prec = DUR_PRECISION,
+                ))),
+                Cell::from(subtree_width.update_str(format!(
+                    "{:>width$}",
+                    subtree.resources,
+                    width = subtree_width.chars() as usize
+                ))),
+                Cell::from(live_width.update_str(format!(
+                    "{:>width$}",
+                    subtree.live,
+                    width = live_width.chars() as usize
+                ))),
+                Cell::from(location_width.update_str(String::from(resource.location()))),
+                Cell::from(attributes),
+            ]);
+            if resource.dropped() {
+                row = row.style(styles.terminated());
+            }
+            row
+        });
+        let table_rows: Vec<_> = table_rows.collect();
+
+        let header = Row::new(HEADER.iter().copied())
+            .height(1)
+            .style(Style::default().add_modifier(style::Modifier::BOLD));
+
+        let mut title = vec![bold(format!("Resource tree ({}) ", self.rows.len()))];
+        if self.hide_internal {
+            title.push(Span::raw("(internal resources hidden) "));
+        }
+        let block = styles.border_block().title(title);
+
+        let controls = Controls::from_text(
+            &area,
+            Text::from(Spans::from(vec![
+                Span::raw("controls: "),
+                bold(styles.if_utf8("\u{2191}\u{2193}", "up, down")),
+                Span::raw(" or "),
+                bold("k, j"),
+                Span::raw(" = scroll, "),
+                bold(styles.if_utf8("\u{2190}\u{2192}", "left, right")),
+                Span::raw(" or "),
+                bold("h, l"),
+                Span::raw(" = collapse/expand, "),
+                bold(styles.if_utf8("\u{21B5}", "enter")),
+                Span::raw(" = view details, "),
+                bold("v"),
+                Span::raw(" = show/hide internal resources, "),
+                bold("q"),
+                Span::raw(" = quit"),
+            ])),
+        );
+
+        let chunks = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0)
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height),
+                    layout::Constraint::Max(area.height),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let widths = &[
+            warn_width.constraint(),
+            id_width.constraint(),
+            kind_width.constraint(),
+            type_width.constraint(),
+            layout::Constraint::Length(viz_len),
+            layout::Constraint::Length(DUR_LEN as u16),
+            subtree_width.constraint(),
+            live_width.constraint(),
+            location_width.constraint(),
+            layout::Constraint::Percentage(100),
+        ];
+        let table = Table::new(table_rows)
+            .header(header)
+            .block(block)
+            .widths(widths)
+            .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+
+        frame.render_widget(controls.paragraph, chunks[0]);
+        frame.render_stateful_widget(table, chunks[1], &mut self.table_state);
+    }
+
+    /// Adds the row for `resource`, if it's `visible`, followed by those for
+    /// its descendants, and returns the summary of its subtree.
+    fn visit(
+        &self,
+        resource: &Rc<RefCell<Resource>>,
+        depth: usize,
+        visible: bool,
+        tree: &HashMap<Option<u64>, Vec<Rc<RefCell<Resource>>>>,
+        rows: &mut Vec<TreeRow>,
+    ) -> Subtree {
+        let mut subtree = Subtree::from_resource(&resource.borrow());
+        let id = resource.borrow().id();
+        let children = tree.get(&Some(id)).map(Vec::as_slice).unwrap_or(&[]);
+
+        let index = rows.len();
+        if visible {
+            let has_children = children
+                .iter()
+                .any(|child| !self.is_hidden(&child.borrow()));
+            rows.push(TreeRow {
+                resource: resource.clone(),
+                depth,
+                has_children,
+                subtree: Subtree::default(),
+            });
+        }
+
+        let expanded = visible && !self.collapsed.contains(&id);
+        for child in children {
+            let visible = expanded && !self.is_hidden(&child.borrow());
+            subtree.merge(self.visit(child, depth + 1, visible, tree, rows));
+        }
+
+        if visible {
+            rows[index].subtree = subtree.clone();
+        }
+        subtree
+    }
+
+    fn is_hidden(&self, resource: &Resource) -> bool {
+        self.hide_internal && resource.type_visibility() == TypeVisibility::Internal
+    }
+}
+
+// === impl Subtree ===
+
+impl Subtree {
+    fn from_resource(resource: &Resource) -> Self {
+        let attributes = resource
+            .formatted_attributes()
+            .iter()
+            .filter_map(|attribute| match attribute.as_slice() {
+                [name, _, value, rest @ ..] => {
+                    // The unit, if there is one, precedes the trailing space.
+                    let unit = match rest {
+                        [unit, _] => unit.content.to_string(),
+                        _ => String::new(),
+                    };
+                    let aggregate = Aggregate::new(value.content.trim(), unit);
+                    Some((name.content.to_string(), aggregate))
+                }
+                _ => None,
+            })
+            .collect();
+        Self {
+            resources: 1,
+            live: if resource.dropped() { 0 } else { 1 },
+            warnings: resource.warnings().len(),
+            attributes,
+        }
+    }
+
+    fn merge(&mut self, other: Subtree) {
+        self.resources += other.resources;
+        self.live += other.live;
+        self.warnings += other.warnings;
+        for (name, aggregate) in other.attributes {
+            match self.attributes.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, existing)) => existing.merge(aggregate),
+                None => self.attributes.push((name, aggregate)),
+            }
+        }
+    }
+}
+
+// === impl Aggregate ===
+
+impl Aggregate {
+    fn new(value: &str, unit: String) -> Self {
+        if let Ok(value) = value.parse::<bool>() {
+            return Aggregate::Count {
+                set: value as usize,
+                total: 1,
+            };
+        }
+        match value.parse::<f64>() {
+            Ok(value) => Aggregate::Sum(value, unit),
+            Err(_) => Aggregate::Same(format!("{}{}", value, unit)),
+        }
+    }
+
+    fn merge(&mut self, other: Aggregate) {
+        *self = match (std::mem::replace(self, Aggregate::Mixed), other) {
+            (Aggregate::Sum(a, unit), Aggregate::Sum(b, other_unit)) if unit == other_unit => {
+                Aggregate::Sum(a + b, unit)
+            }
+            (Aggregate::Count { set, total }, Aggregate::Count { set: s, total: t }) => {
+                Aggregate::Count {
+                    set: set + s,
+                    total: total + t,
+                }
+            }
+            (Aggregate::Same(a), Aggregate::Same(b)) if a == b => Aggregate::Same(a),
+            _ => Aggregate::Mixed,
+        }
+    }
+
+    /// Returns the aggregated value, or `None` if it can't be aggregated.
+    fn display(&self) -> Option<String> {
+        match self {
+            Aggregate::Sum(value, unit) => Some(format!("{}{}", value, unit)),
+            // A single value is displayed as it is.
+            Aggregate::Count { set, total: 1 } => Some((*set == 1).to_string()),
+            Aggregate::Count { set, total } => Some(format!("{}/{}", set, total)),
+            Aggregate::Same(value) => Some(value.clone()),
+            Aggregate::Mixed => None,
+        }
+    }
+}
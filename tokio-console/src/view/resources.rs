@@ -6,10 +6,11 @@ use crate::{
     view::{
         self, bold,
         table::{self, TableList, TableListState},
-        DUR_LEN, DUR_PRECISION,
+        warnings, DUR_LEN, DUR_PRECISION,
     },
 };
 
+use regex::Regex;
 use tui::{
     layout,
     style::{self, Color, Style},
@@ -26,6 +27,7 @@ impl TableList for ResourcesTable {
     type Context = ();
 
     const HEADER: &'static [&'static str] = &[
+        "Warn",
         "ID",
         "Parent",
         "Kind",
@@ -37,6 +39,14 @@ impl TableList for ResourcesTable {
         "Attributes",
     ];
 
+    fn matches(resource: &Resource, filter: &Regex) -> bool {
+        filter.is_match(resource.concrete_type())
+            || filter.is_match(resource.kind())
+            || filter.is_match(resource.target())
+            || filter.is_match(resource.location())
+            || table::fields_match(resource.formatted_attributes(), filter)
+    }
+
     fn render<B: tui::backend::Backend>(
         table_list_state: &mut TableListState<Self>,
         styles: &view::Styles,
@@ -52,24 +62,33 @@ impl TableList for ResourcesTable {
             return;
         };
 
-        table_list_state
-            .sorted_items
-            .extend(state.resources_state_mut().take_new_resources());
+        let resources = state.resources_state_mut();
+        if table_list_state.take_filter_changed() {
+            // Collect every resource matching the new filter, which includes
+            // any new resources.
+            resources.take_new_resources().for_each(drop);
+            table_list_state.sorted_items.clear();
+            table_list_state.extend_filtered(resources.resources(), now);
+        } else {
+            table_list_state.extend_filtered(resources.take_new_resources(), now);
+        }
         table_list_state
             .sort_by
             .sort(now, &mut table_list_state.sorted_items);
 
-        let viz_len: u16 = Self::HEADER[6].len() as u16;
+        let viz_len: u16 = Self::HEADER[7].len() as u16;
 
-        let mut id_width = view::Width::new(Self::HEADER[0].len() as u16);
-        let mut parent_width = view::Width::new(Self::HEADER[1].len() as u16);
+        let mut warn_width = view::Width::new(Self::HEADER[0].len() as u16);
+        let mut id_width = view::Width::new(Self::HEADER[1].len() as u16);
+        let mut parent_width = view::Width::new(Self::HEADER[2].len() as u16);
 
-        let mut kind_width = view::Width::new(Self::HEADER[2].len() as u16);
-        let mut target_width = view::Width::new(Self::HEADER[4].len() as u16);
-        let mut type_width = view::Width::new(Self::HEADER[5].len() as u16);
-        let mut location_width = view::Width::new(Self::HEADER[7].len() as u16);
+        let mut kind_width = view::Width::new(Self::HEADER[3].len() as u16);
+        let mut target_width = view::Width::new(Self::HEADER[5].len() as u16);
+        let mut type_width = view::Width::new(Self::HEADER[6].len() as u16);
+        let mut location_width = view::Width::new(Self::HEADER[8].len() as u16);
 
         let rows = {
+            let warn_width = &mut warn_width;
             let id_width = &mut id_width;
             let parent_width = &mut parent_width;
             let kind_width = &mut kind_width;
@@ -85,6 +104,7 @@ impl TableList for ResourcesTable {
                     let resource = resource.borrow();
 
                     let mut row = Row::new(vec![
+                        warnings::count_cell(styles, resource.warnings(), warn_width),
                         Cell::from(id_width.update_str(format!(
                             "{:>width$}",
                             resource.id(),
@@ -147,10 +167,20 @@ impl TableList for ResourcesTable {
             Table::new(rows.rev())
         };
 
-        let block = styles.border_block().title(vec![bold(format!(
-            "Resources ({}) ",
-            table_list_state.len()
-        ))]);
+        let mut title = vec![bold(format!("Resources ({}) ", table_list_state.len()))];
+        title.extend(table_list_state.filter_title());
+        let block = styles.border_block().title(title);
+
+        // Async ops are listed in the view of their resource, so summarize
+        // their warnings here too.
+        let mut summaries = warnings::summaries(styles, state.resources_state().warnings());
+        summaries.extend(warnings::summaries(
+            styles,
+            state.async_ops_state().warnings(),
+        ));
+        let resource_linters = state.resources_state().linters();
+        let async_op_linters = state.async_ops_state().linters();
+        let no_linters = resource_linters.is_empty() && async_op_linters.is_empty();
 
         let controls = table::Controls::for_area(&area, styles);
 
@@ -158,20 +188,35 @@ impl TableList for ResourcesTable {
             .direction(layout::Direction::Vertical)
             .margin(0);
 
-        let chunks = layout
-            .constraints(
-                [
-                    layout::Constraint::Length(controls.height),
-                    layout::Constraint::Max(area.height),
-                ]
-                .as_ref(),
-            )
-            .split(area);
-        let controls_area = chunks[0];
-        let tasks_area = chunks[1];
+        let (controls_area, resources_area, warnings_area) = if no_linters {
+            let chunks = layout
+                .constraints(
+                    [
+                        layout::Constraint::Length(controls.height),
+                        layout::Constraint::Max(area.height),
+                    ]
+                    .as_ref(),
+                )
+                .split(area);
+            (chunks[0], chunks[1], None)
+        } else {
+            let warnings_height = warnings::pane_height(&summaries);
+            let chunks = layout
+                .constraints(
+                    [
+                        layout::Constraint::Length(controls.height),
+                        layout::Constraint::Length(warnings_height),
+                        layout::Constraint::Max(area.height),
+                    ]
+                    .as_ref(),
+                )
+                .split(area);
+            (chunks[0], chunks[2], Some(chunks[1]))
+        };
 
         let attributes_width = layout::Constraint::Percentage(100);
         let widths = &[
+            warn_width.constraint(),
             id_width.constraint(),
             parent_width.constraint(),
             kind_width.constraint(),
@@ -190,9 +235,14 @@ impl TableList for ResourcesTable {
             .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
             .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
 
-        frame.render_stateful_widget(table, tasks_area, &mut table_list_state.table_state);
+        frame.render_stateful_widget(table, resources_area, &mut table_list_state.table_state);
         frame.render_widget(controls.paragraph, controls_area);
 
+        if let Some(area) = warnings_area {
+            let lints = warnings::lints(resource_linters).chain(warnings::lints(async_op_linters));
+            frame.render_widget(warnings::pane(styles, summaries, lints), area);
+        }
+
         table_list_state
             .sorted_items
             .retain(|t| t.upgrade().is_some());
@@ -1,4 +1,4 @@
-use crate::config;
+use crate::{config, warnings::Severity};
 use std::{borrow::Cow, str::FromStr};
 use tui::{
     style::{Color, Modifier, Style},
@@ -127,6 +127,28 @@ impl Styles {
         )
     }
 
+    pub fn error_wide(&self) -> Span<'static> {
+        Span::styled(
+            self.if_utf8("\u{2716} ", "[X] "),
+            self.fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        )
+    }
+
+    pub fn error_narrow(&self) -> Span<'static> {
+        Span::styled(
+            self.if_utf8("\u{2716} ", "X "),
+            self.fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        )
+    }
+
+    /// Returns the wide icon for a lint of the given severity.
+    pub(crate) fn severity_wide(&self, severity: Severity) -> Span<'static> {
+        match severity {
+            Severity::Warning => self.warning_wide(),
+            Severity::Error => self.error_wide(),
+        }
+    }
+
     pub fn color(&self, color: Color) -> Option<Color> {
         use Palette::*;
         match (self.palette, color) {
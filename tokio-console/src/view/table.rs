@@ -1,8 +1,10 @@
 use crate::{
+    filter::{self, Expr},
     input, state,
     view::{self, bold},
 };
-use std::convert::TryFrom;
+use regex::Regex;
+use std::{convert::TryFrom, time::SystemTime};
 use tui::{
     layout,
     text::{self, Span, Spans, Text},
@@ -13,12 +15,16 @@ use std::cell::RefCell;
 use std::rc::Weak;
 
 pub(crate) trait TableList {
-    type Row;
+    type Row: filter::Subject;
     type Sort: SortBy + TryFrom<usize>;
     type Context;
 
     const HEADER: &'static [&'static str];
 
+    /// Returns `true` if `row` should be listed while a text `filter` is
+    /// applied.
+    fn matches(row: &Self::Row, filter: &Regex) -> bool;
+
     fn render<B: tui::backend::Backend>(
         state: &mut TableListState<Self>,
         styles: &view::Styles,
@@ -41,9 +47,39 @@ pub(crate) struct TableListState<T: TableList> {
     pub(crate) sort_descending: bool,
     pub(crate) table_state: TableState,
 
+    /// The filter rows must match to be listed, if any.
+    filter: Option<Filter>,
+    /// The filter prompt, while it is open.
+    filter_prompt: Option<Box<FilterPrompt>>,
+    /// Whether the filter has changed since the table's items were last
+    /// collected.
+    filter_changed: bool,
+
     last_key_event: Option<input::KeyEvent>,
 }
 
+#[derive(Clone, Debug)]
+struct Filter {
+    text: String,
+    kind: FilterKind,
+}
+
+#[derive(Clone, Debug)]
+enum FilterKind {
+    /// A filter expression, such as `state == idle && polls > 1000`.
+    Expr(Expr),
+    /// A regular expression, which rows are searched for.
+    Regex(Regex),
+}
+
+#[derive(Debug)]
+struct FilterPrompt {
+    text: String,
+    /// The filter that was applied when the prompt was opened, which is
+    /// restored if it's cancelled.
+    previous: Option<Filter>,
+}
+
 pub(crate) struct Controls {
     pub(crate) paragraph: Paragraph<'static>,
     pub(crate) height: u16,
@@ -72,6 +108,32 @@ impl<T: TableList> TableListState<T> {
         use input::KeyCode::*;
         let header_len = T::HEADER.len();
         let code = event.code;
+
+        // While the filter prompt is open, every key edits the filter, which
+        // is applied as it's typed.
+        if let Some(prompt) = self.filter_prompt.as_mut() {
+            match code {
+                Enter => self.filter_prompt = None,
+                Esc => {
+                    let previous = prompt.previous.take();
+                    self.filter_prompt = None;
+                    self.set_filter(previous);
+                }
+                Backspace => {
+                    prompt.text.pop();
+                    let filter = Filter::new(&prompt.text);
+                    self.set_filter(filter);
+                }
+                Char(c) => {
+                    prompt.text.push(c);
+                    let filter = Filter::new(&prompt.text);
+                    self.set_filter(filter);
+                }
+                _ => {}
+            }
+            return;
+        }
+
         match code {
             Left | Char('h') => {
                 if self.selected_column == 0 {
@@ -88,6 +150,17 @@ impl<T: TableList> TableListState<T> {
                 }
             }
             Char('i') => self.sort_descending = !self.sort_descending,
+            Char('/') => {
+                self.filter_prompt = Some(Box::new(FilterPrompt {
+                    text: self
+                        .filter
+                        .as_ref()
+                        .map(|filter| filter.text.clone())
+                        .unwrap_or_default(),
+                    previous: self.filter.clone(),
+                }))
+            }
+            Esc if self.filter.is_some() => self.set_filter(None),
             Down | Char('j') => self.scroll_next(),
             Up | Char('k') => self.scroll_prev(),
             Char('G') => self.scroll_to_last(),
@@ -104,6 +177,88 @@ impl<T: TableList> TableListState<T> {
         self.last_key_event = Some(event);
     }
 
+    /// Returns `true` if the filter prompt is open, so that keys should be
+    /// passed to the table rather than handled by the view.
+    pub(in crate::view) fn is_editing_filter(&self) -> bool {
+        self.filter_prompt.is_some()
+    }
+
+    /// Applies a filter, such as when drilling down into a table from
+    /// another view.
+    pub(in crate::view) fn set_filter_text(&mut self, text: &str) {
+        self.filter_prompt = None;
+        self.set_filter(Filter::new(text));
+    }
+
+    fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+        self.filter_changed = true;
+        // The selected row may no longer be listed.
+        self.table_state.select(None);
+    }
+
+    /// Returns `true` if the filter has changed since the last time this was
+    /// called, in which case the table's items should be collected again.
+    ///
+    /// Filter expressions may depend on values that change with every update
+    /// (such as `busy > 500ms`), so this always returns `true` while one is
+    /// applied.
+    pub(in crate::view) fn take_filter_changed(&mut self) -> bool {
+        let is_expr = matches!(
+            self.filter,
+            Some(Filter {
+                kind: FilterKind::Expr(_),
+                ..
+            })
+        );
+        std::mem::replace(&mut self.filter_changed, false) || is_expr
+    }
+
+    /// Adds the `items` that match the current filter as of `now` to the
+    /// table.
+    pub(in crate::view) fn extend_filtered(
+        &mut self,
+        items: impl IntoIterator<Item = Weak<RefCell<T::Row>>>,
+        now: SystemTime,
+    ) {
+        let filter = match self.filter {
+            Some(ref filter) => filter,
+            None => return self.sorted_items.extend(items),
+        };
+        self.sorted_items.extend(items.into_iter().filter(|item| {
+            let item = match item.upgrade() {
+                Some(item) => item,
+                None => return false,
+            };
+            let item = item.borrow();
+            match filter.kind {
+                FilterKind::Expr(ref expr) => expr.matches(&*item, now),
+                FilterKind::Regex(ref regex) => T::matches(&*item, regex),
+            }
+        }))
+    }
+
+    /// Returns spans describing the current filter, or the filter prompt if
+    /// it's open, to add to the table's title.
+    pub(in crate::view) fn filter_title(&self) -> Vec<Span<'static>> {
+        match (&self.filter_prompt, &self.filter) {
+            (Some(prompt), _) => vec![
+                Span::raw(" "),
+                bold("/"),
+                Span::raw(prompt.text.clone()),
+                Span::raw("_ "),
+            ],
+            (None, Some(filter)) => {
+                let text = match filter.kind {
+                    FilterKind::Expr(_) => format!("{} ", filter.text),
+                    FilterKind::Regex(_) => format!("/{}/ ", filter.text),
+                };
+                vec![Span::raw(" filter: "), bold(text)]
+            }
+            (None, None) => Vec::new(),
+        }
+    }
+
     pub(in crate::view) fn scroll_with(
         &mut self,
         f: impl Fn(&Vec<Weak<RefCell<T::Row>>>, usize) -> usize,
@@ -195,6 +350,9 @@ where
             table_state: Default::default(),
             selected_column,
             sort_descending: false,
+            filter: None,
+            filter_prompt: None,
+            filter_changed: false,
             last_key_event: None,
         }
     }
@@ -202,28 +360,36 @@ where
 
 impl Controls {
     pub(in crate::view) fn for_area(area: &layout::Rect, styles: &view::Styles) -> Self {
-        let text = Text::from(Spans::from(vec![
-            Span::raw("controls: "),
-            bold(styles.if_utf8("\u{2190}\u{2192}", "left, right")),
-            Span::raw(" or "),
-            bold("h, l"),
-            text::Span::raw(" = select column (sort), "),
-            bold(styles.if_utf8("\u{2191}\u{2193}", "up, down")),
-            Span::raw(" or "),
-            bold("k, j"),
-            text::Span::raw(" = scroll, "),
-            bold(styles.if_utf8("\u{21B5}", "enter")),
-            text::Span::raw(" = view details, "),
-            bold("i"),
-            text::Span::raw(" = invert sort (highest/lowest), "),
-            bold("q"),
-            text::Span::raw(" = quit "),
-            bold("gg"),
-            text::Span::raw(" = scroll to top, "),
-            bold("G"),
-            text::Span::raw(" = scroll to bottom"),
-        ]));
+        Self::from_text(
+            area,
+            Text::from(Spans::from(vec![
+                Span::raw("controls: "),
+                bold(styles.if_utf8("\u{2190}\u{2192}", "left, right")),
+                Span::raw(" or "),
+                bold("h, l"),
+                text::Span::raw(" = select column (sort), "),
+                bold(styles.if_utf8("\u{2191}\u{2193}", "up, down")),
+                Span::raw(" or "),
+                bold("k, j"),
+                text::Span::raw(" = scroll, "),
+                bold(styles.if_utf8("\u{21B5}", "enter")),
+                text::Span::raw(" = view details, "),
+                bold("i"),
+                text::Span::raw(" = invert sort (highest/lowest), "),
+                bold("/"),
+                text::Span::raw(" = filter, "),
+                bold("q"),
+                text::Span::raw(" = quit "),
+                bold("gg"),
+                text::Span::raw(" = scroll to top, "),
+                bold("G"),
+                text::Span::raw(" = scroll to bottom"),
+            ])),
+        )
+    }
 
+    /// Returns controls displaying `text`, wrapped to fit in `area`.
+    pub(in crate::view) fn from_text(area: &layout::Rect, text: Text<'static>) -> Self {
         // how many lines do we need to display the controls?
         let mut height = 1;
 
@@ -249,3 +415,39 @@ impl Controls {
         }
     }
 }
+
+impl Filter {
+    /// Returns a filter for `text`, which is parsed as a filter expression if
+    /// it is one.
+    ///
+    /// Otherwise, rows are searched for `text` as a regular expression, or, if
+    /// it isn't a valid one (such as while it is still being typed), as a
+    /// literal string.
+    ///
+    /// Returns `None` if `text` is empty.
+    fn new(text: &str) -> Option<Self> {
+        if text.is_empty() {
+            return None;
+        }
+        let kind = match text.parse::<Expr>() {
+            Ok(expr) => FilterKind::Expr(expr),
+            Err(_) => FilterKind::Regex(
+                Regex::new(text)
+                    .or_else(|_| Regex::new(&regex::escape(text)))
+                    .ok()?,
+            ),
+        };
+        Some(Self {
+            text: text.to_owned(),
+            kind,
+        })
+    }
+}
+
+/// Returns `true` if any of the formatted `fields` of a row matches `filter`.
+pub(in crate::view) fn fields_match(fields: &[Vec<Span<'_>>], filter: &Regex) -> bool {
+    fields.iter().any(|field| {
+        let field: String = field.iter().map(|span| span.content.as_ref()).collect();
+        filter.is_match(&field)
+    })
+}
@@ -1,38 +1,153 @@
 use crate::{
     input,
     state::{
+        async_ops::AsyncOp,
         tasks::{Details, Task},
-        DetailsRef,
+        DetailsRef, State,
     },
+    term,
     util::Percentage,
     view::{
         self, bold,
         mini_histogram::{HistogramMetadata, MiniHistogram},
+        table::Controls,
+        DUR_LEN, DUR_PRECISION,
     },
 };
+use hdrhistogram::Histogram;
 use std::{
     cell::RefCell,
+    cmp,
     rc::Rc,
     time::{Duration, SystemTime},
 };
 use tui::{
     layout::{self, Layout},
+    style::{self, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, List, ListItem, Paragraph},
+    widgets::{Cell, List, ListItem, Paragraph, Row, Table, TableState, Wrap},
 };
 
 pub(crate) struct TaskView {
     task: Rc<RefCell<Task>>,
     details: DetailsRef,
+    /// The IDs of the pending async ops listed in the "Waiting On" table, and
+    /// of the resources they operate on, in the order they were last rendered.
+    async_ops: Vec<(u64, u64)>,
+    /// The ID of the selected async op, if there is one.
+    selected_op: Option<u64>,
+    async_ops_state: TableState,
+    /// How many lines of the fields list are scrolled past.
+    fields_scroll: u16,
+    /// How many lines of the fields list were visible when it was last
+    /// rendered, to scroll by a page at a time.
+    fields_height: u16,
+    /// If `true`, long field values are wrapped, rather than cut off.
+    expand_fields: bool,
+    histogram: HistogramKind,
+    /// If `true`, the histogram's buckets grow exponentially, rather than
+    /// all being the same width.
+    log_scale: bool,
+    /// The outcome of the last action, such as copying the task's location.
+    status: Option<String>,
+}
+
+/// The histograms shown in the task view.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum HistogramKind {
+    /// Every poll of the task.
+    PollTimes,
+    /// The polls since the previous details update.
+    RecentPollTimes,
 }
 
 impl TaskView {
     pub(super) fn new(task: Rc<RefCell<Task>>, details: DetailsRef) -> Self {
-        TaskView { task, details }
+        TaskView {
+            task,
+            details,
+            async_ops: Vec::new(),
+            selected_op: None,
+            async_ops_state: TableState::default(),
+            fields_scroll: 0,
+            fields_height: 0,
+            expand_fields: false,
+            histogram: HistogramKind::PollTimes,
+            log_scale: false,
+            status: None,
+        }
+    }
+
+    pub(crate) fn task_id(&self) -> u64 {
+        self.task.borrow().id()
+    }
+
+    pub(crate) fn span_id(&self) -> u64 {
+        self.task.borrow().span_id()
+    }
+
+    /// Returns the ID of the resource that the selected async op operates on,
+    /// if one is selected.
+    pub(crate) fn selected_resource_id(&self) -> Option<u64> {
+        let selected = self.selected_op?;
+        self.async_ops
+            .iter()
+            .find(|&&(op, _)| op == selected)
+            .map(|&(_, resource)| resource)
+    }
+
+    pub(crate) fn update_input(&mut self, event: input::Event) {
+        use input::KeyCode::*;
+        let code = match event {
+            input::Event::Key(input::KeyEvent { code, .. }) => code,
+            _ => return,
+        };
+        self.status = None;
+        match code {
+            Down | Char('j') => self.select_op(|i, len| (i + 1) % len),
+            Up | Char('k') => self.select_op(|i, len| (i + len - 1) % len),
+            PageDown | Char('d') => {
+                // The scroll position is clamped to the fields' length when
+                // they're rendered.
+                self.fields_scroll = self
+                    .fields_scroll
+                    .saturating_add(cmp::max(self.fields_height, 1));
+            }
+            PageUp | Char('u') => {
+                self.fields_scroll = self
+                    .fields_scroll
+                    .saturating_sub(cmp::max(self.fields_height, 1));
+            }
+            Char('e') => self.expand_fields = !self.expand_fields,
+            Char('l') => self.log_scale = !self.log_scale,
+            Char('h') => {
+                self.histogram = match self.histogram {
+                    HistogramKind::PollTimes => HistogramKind::RecentPollTimes,
+                    HistogramKind::RecentPollTimes => HistogramKind::PollTimes,
+                }
+            }
+            Char('c') => {
+                let location = self.task.borrow().location().trim().to_owned();
+                self.status = Some(match term::copy_to_clipboard(&location) {
+                    Ok(()) => format!("copied {} to the clipboard", location),
+                    Err(error) => format!("failed to copy the location: {}", error),
+                });
+            }
+            _ => {}
+        }
     }
 
-    pub(crate) fn update_input(&mut self, _event: input::Event) {
-        // TODO :D
+    fn select_op(&mut self, f: impl Fn(usize, usize) -> usize) {
+        if self.async_ops.is_empty() {
+            self.selected_op = None;
+            return;
+        }
+        let i = self
+            .selected_op
+            .and_then(|selected| self.async_ops.iter().position(|&(op, _)| op == selected))
+            .map(|i| f(i, self.async_ops.len()))
+            .unwrap_or(0);
+        self.selected_op = Some(self.async_ops[i].0);
     }
 
     pub(crate) fn render<B: tui::backend::Backend>(
@@ -40,8 +155,11 @@ impl TaskView {
         styles: &view::Styles,
         frame: &mut tui::terminal::Frame<B>,
         area: layout::Rect,
-        now: SystemTime,
+        state: &State,
     ) {
+        let now = state
+            .last_updated_at()
+            .expect("task view implies we've received an update");
         // Rows with the following info:
         // - Task main attributes
         // - task metadata
@@ -61,43 +179,107 @@ impl TaskView {
                 ListItem::new(Text::from(Spans::from(vec![
                     styles.warning_wide(),
                     // TODO(eliza): it would be nice to handle singular vs plural...
-                    Span::from(linter.format(task)),
+                    Span::from(linter.format(task, now)),
                 ])))
             })
             .collect();
 
-        let (controls_area, stats_area, poll_dur_area, fields_area, warnings_area) =
+        // The async ops that the task is currently waiting on.
+        let mut async_ops: Vec<_> = state
+            .async_ops_state()
+            .async_ops()
+            .filter_map(|op| op.upgrade())
+            .filter(|op| {
+                let op = op.borrow();
+                op.task_id() == Some(task.id()) && !op.dropped()
+            })
+            .collect();
+        async_ops.sort_by_key(|op| op.borrow().id());
+        self.async_ops = async_ops
+            .iter()
+            .map(|op| {
+                let op = op.borrow();
+                (op.id(), op.resource_id())
+            })
+            .collect();
+        let selected = self
+            .selected_op
+            .and_then(|selected| self.async_ops.iter().position(|&(op, _)| op == selected));
+        if selected.is_none() {
+            self.selected_op = None;
+        }
+        self.async_ops_state.select(selected);
+        // Show up to 5 async ops at a time, and add 3 for the header and the
+        // top and bottom borders.
+        let async_ops_height = async_ops.len().clamp(1, 5) as u16 + 3;
+
+        let mut controls = vec![
+            Span::raw("controls: "),
+            bold(styles.if_utf8("\u{238B} esc", "esc")),
+            Span::raw(" = go back, "),
+            bold(styles.if_utf8("\u{2191}\u{2193}", "up, down")),
+            Span::raw(" or "),
+            bold("k, j"),
+            Span::raw(" = select async op, "),
+            bold(styles.if_utf8("\u{21B5}", "enter")),
+            Span::raw(" = view resource, "),
+            bold("PgUp, PgDn"),
+            Span::raw(" or "),
+            bold("u, d"),
+            Span::raw(" = scroll fields, "),
+            bold("e"),
+            Span::raw(" = expand fields, "),
+            bold("h"),
+            Span::raw(" = all/recent polls, "),
+            bold("l"),
+            Span::raw(" = linear/log scale, "),
+            bold("c"),
+            Span::raw(" = copy location, "),
+            bold("q"),
+            Span::raw(" = quit"),
+        ];
+        if let Some(ref status) = self.status {
+            controls.push(Span::raw(" -- "));
+            controls.push(bold(status.clone()));
+        }
+        let controls = Controls::from_text(&area, Text::from(Spans::from(controls)));
+
+        let (controls_area, stats_area, poll_dur_area, async_ops_area, fields_area, warnings_area) =
             if warnings.is_empty() {
                 let chunks = Layout::default()
                     .direction(layout::Direction::Vertical)
                     .constraints(
                         [
                             // controls
-                            layout::Constraint::Length(1),
+                            layout::Constraint::Length(controls.height),
                             // task stats
                             layout::Constraint::Length(8),
                             // poll duration
                             layout::Constraint::Length(9),
+                            // async ops
+                            layout::Constraint::Length(async_ops_height),
                             // fields
                             layout::Constraint::Percentage(60),
                         ]
                         .as_ref(),
                     )
                     .split(area);
-                (chunks[0], chunks[1], chunks[2], chunks[3], None)
+                (chunks[0], chunks[1], chunks[2], chunks[3], chunks[4], None)
             } else {
                 let chunks = Layout::default()
                     .direction(layout::Direction::Vertical)
                     .constraints(
                         [
                             // controls
-                            layout::Constraint::Length(1),
+                            layout::Constraint::Length(controls.height),
                             // warnings (add 2 for top and bottom borders)
                             layout::Constraint::Length(warnings.len() as u16 + 2),
                             // task stats
                             layout::Constraint::Length(8),
                             // poll duration
                             layout::Constraint::Length(9),
+                            // async ops
+                            layout::Constraint::Length(async_ops_height),
                             // fields
                             layout::Constraint::Percentage(60),
                         ]
@@ -105,7 +287,14 @@ impl TaskView {
                     )
                     .split(area);
 
-                (chunks[0], chunks[2], chunks[3], chunks[4], Some(chunks[1]))
+                (
+                    chunks[0],
+                    chunks[2],
+                    chunks[3],
+                    chunks[4],
+                    chunks[5],
+                    Some(chunks[1]),
+                )
             };
 
         let stats_area = Layout::default()
@@ -139,14 +328,6 @@ impl TaskView {
 
         let percentiles_area = poll_dur_area[0];
 
-        let controls = Spans::from(vec![
-            Span::raw("controls: "),
-            bold(styles.if_utf8("\u{238B} esc", "esc")),
-            Span::raw(" = return to task list, "),
-            bold("q"),
-            Span::raw(" = quit"),
-        ]);
-
         // Just preallocate capacity for ID, name, target, total, busy, and idle.
         let mut overview = Vec::with_capacity(7);
         overview.push(Spans::from(vec![
@@ -222,6 +403,44 @@ impl TaskView {
         let mut fields = Text::default();
         fields.extend(task.formatted_fields().iter().cloned().map(Spans::from));
 
+        // Keep the fields scrolled no further than their last line, taking
+        // the block's borders into account.
+        let fields_width = cmp::max(fields_area.width.saturating_sub(2), 1) as usize;
+        self.fields_height = fields_area.height.saturating_sub(2);
+        let fields_lines: usize = fields
+            .lines
+            .iter()
+            .map(|line| {
+                if self.expand_fields {
+                    cmp::max((line.width() + fields_width - 1) / fields_width, 1)
+                } else {
+                    1
+                }
+            })
+            .sum();
+        let max_scroll = (fields_lines as u16).saturating_sub(self.fields_height);
+        self.fields_scroll = cmp::min(self.fields_scroll, max_scroll);
+        let fields_title = if max_scroll > 0 {
+            format!(
+                "Fields ({}-{} of {} lines)",
+                self.fields_scroll + 1,
+                cmp::min(self.fields_scroll + self.fields_height, fields_lines as u16),
+                fields_lines
+            )
+        } else {
+            "Fields".to_string()
+        };
+
+        let (histogram_title, percentiles_title) = match self.histogram {
+            HistogramKind::PollTimes => ("Poll Times Histogram", "Poll Times Percentiles"),
+            HistogramKind::RecentPollTimes => ("Recent Poll Times Histogram", "Recent Percentiles"),
+        };
+        let histogram_title = if self.log_scale {
+            format!("{} (log scale)", histogram_title)
+        } else {
+            histogram_title.to_string()
+        };
+
         // If UTF-8 is disabled we can't draw the histogram sparklne.
         if styles.utf8 {
             let sparkline_area = poll_dur_area[1];
@@ -234,11 +453,13 @@ impl TaskView {
             // If we undershoot, the rightmost 1-2 columns/buckets will be hidden.
             // We could get the max bucket value from the previous render though...
             let (chart_data, metadata) = details
-                .map(|d| d.make_chart_data(sparkline_area.width - 3))
+                .map(|d| {
+                    d.make_chart_data(self.histogram, sparkline_area.width - 3, self.log_scale)
+                })
                 .unwrap_or_default();
 
             let histogram_sparkline = MiniHistogram::default()
-                .block(styles.border_block().title("Poll Times Histogram"))
+                .block(styles.border_block().title(histogram_title))
                 .data(&chart_data)
                 .metadata(metadata)
                 .duration_precision(2);
@@ -253,32 +474,73 @@ impl TaskView {
 
         let task_widget = Paragraph::new(overview).block(styles.border_block().title("Task"));
         let wakers_widget = Paragraph::new(waker_stats).block(styles.border_block().title("Waker"));
-        let fields_widget = Paragraph::new(fields).block(styles.border_block().title("Fields"));
+        let mut fields_widget = Paragraph::new(fields)
+            .block(styles.border_block().title(fields_title))
+            .scroll((self.fields_scroll, 0));
+        if self.expand_fields {
+            fields_widget = fields_widget.wrap(Wrap { trim: false });
+        }
         let percentiles_widget = Paragraph::new(
             details
-                .map(|details| details.make_percentiles_widget(styles))
+                .map(|details| details.make_percentiles_widget(self.histogram, styles))
                 .unwrap_or_default(),
         )
-        .block(styles.border_block().title("Poll Times Percentiles"));
+        .block(styles.border_block().title(percentiles_title));
 
-        frame.render_widget(Block::default().title(controls), controls_area);
+        frame.render_widget(controls.paragraph, controls_area);
         frame.render_widget(task_widget, stats_area[0]);
         frame.render_widget(wakers_widget, stats_area[1]);
         frame.render_widget(fields_widget, fields_area);
+        render_async_ops(
+            styles,
+            frame,
+            async_ops_area,
+            &mut self.async_ops_state,
+            state,
+            &async_ops,
+            now,
+        );
         frame.render_widget(percentiles_widget, percentiles_area);
     }
 }
 
 impl Details {
+    /// Returns the histogram of the given `kind`, if it has any values.
+    fn histogram(&self, kind: HistogramKind) -> Option<&Histogram<u64>> {
+        let histogram = match kind {
+            HistogramKind::PollTimes => self.poll_times_histogram(),
+            HistogramKind::RecentPollTimes => self.recent_poll_times_histogram(),
+        };
+        histogram.filter(|histogram| !histogram.is_empty())
+    }
+
     /// From the histogram, build a visual representation by trying to make as
     // many buckets as the width of the render area.
-    fn make_chart_data(&self, width: u16) -> (Vec<u64>, HistogramMetadata) {
-        self.poll_times_histogram()
+    fn make_chart_data(
+        &self,
+        kind: HistogramKind,
+        width: u16,
+        log_scale: bool,
+    ) -> (Vec<u64>, HistogramMetadata) {
+        self.histogram(kind)
             .map(|histogram| {
                 let step_size =
                     ((histogram.max() - histogram.min()) as f64 / width as f64).ceil() as u64 + 1;
+                // Each bucket is `base` times as wide as the previous one, so
+                // that the buckets from the shortest poll to the longest one
+                // fill the width of the render area.
+                let min = cmp::max(histogram.min(), 1);
+                let base = (histogram.max() as f64 / min as f64)
+                    .powf(1.0 / (cmp::max(width, 2) - 1) as f64);
+                let data = if log_scale && base > 1.0 {
+                    histogram
+                        .iter_log(min, base)
+                        .map(|value| value.count_since_last_iteration())
+                        // Remove the 0s from the leading side of the buckets.
+                        .skip_while(|&count| count == 0)
+                        .collect()
                 // `iter_linear` panics if step_size is 0
-                let data = if step_size > 0 {
+                } else if step_size > 0 {
                     let mut found_first_nonzero = false;
                     let data: Vec<u64> = histogram
                         .iter_linear(step_size)
@@ -315,9 +577,9 @@ impl Details {
     }
 
     /// Get the important percentile values from the histogram
-    fn make_percentiles_widget(&self, styles: &view::Styles) -> Text<'static> {
+    fn make_percentiles_widget(&self, kind: HistogramKind, styles: &view::Styles) -> Text<'static> {
         let mut text = Text::default();
-        let histogram = self.poll_times_histogram();
+        let histogram = self.histogram(kind);
         let percentiles = histogram.iter().flat_map(|histogram| {
             let pairs = [10f64, 25f64, 50f64, 75f64, 90f64, 95f64, 99f64]
                 .iter()
@@ -334,6 +596,103 @@ impl Details {
     }
 }
 
+/// Renders a table of the async ops that a task is waiting on, and the
+/// resources they operate on.
+fn render_async_ops<B: tui::backend::Backend>(
+    styles: &view::Styles,
+    frame: &mut tui::terminal::Frame<B>,
+    area: layout::Rect,
+    table_state: &mut TableState,
+    state: &State,
+    async_ops: &[Rc<RefCell<AsyncOp>>],
+    now: SystemTime,
+) {
+    const HEADER: &[&str] = &[
+        "ID",
+        "Resource",
+        "Source",
+        "Total",
+        "Busy",
+        "Idle",
+        "Polls",
+        "Attributes",
+    ];
+
+    let mut id_width = view::Width::new(HEADER[0].len() as u16);
+    let mut resource_width = view::Width::new(HEADER[1].len() as u16);
+    let mut source_width = view::Width::new(HEADER[2].len() as u16);
+    let mut polls_width = view::Width::new(HEADER[6].len() as u16);
+
+    let dur_cell = |dur: Duration| -> Cell<'static> {
+        Cell::from(styles.time_units(format!(
+            "{:>width$.prec$?}",
+            dur,
+            width = DUR_LEN,
+            prec = DUR_PRECISION,
+        )))
+    };
+
+    let rows: Vec<_> = async_ops
+        .iter()
+        .map(|op| {
+            let op = op.borrow();
+            let resource = state
+                .resources_state()
+                .resource(op.resource_id())
+                .and_then(|resource| resource.upgrade())
+                .map(|resource| {
+                    let resource = resource.borrow();
+                    format!("{} ({})", resource.id(), resource.concrete_type())
+                })
+                .unwrap_or_else(|| op.resource_id().to_string());
+            Row::new(vec![
+                Cell::from(id_width.update_str(format!(
+                    "{:>width$}",
+                    op.id(),
+                    width = id_width.chars() as usize
+                ))),
+                Cell::from(resource_width.update_str(resource)),
+                Cell::from(source_width.update_str(op.source()).to_owned()),
+                dur_cell(op.total(now)),
+                dur_cell(op.busy(now)),
+                dur_cell(op.idle(now)),
+                Cell::from(polls_width.update_str(op.total_polls().to_string())),
+                Cell::from(Spans::from(
+                    op.formatted_attributes()
+                        .iter()
+                        .flatten()
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                )),
+            ])
+        })
+        .collect();
+
+    let header = Row::new(HEADER.iter().copied())
+        .height(1)
+        .style(Style::default().add_modifier(style::Modifier::BOLD));
+    let block = styles
+        .border_block()
+        .title(format!("Waiting On ({})", async_ops.len()));
+    let widths = &[
+        id_width.constraint(),
+        resource_width.constraint(),
+        source_width.constraint(),
+        layout::Constraint::Length(DUR_LEN as u16),
+        layout::Constraint::Length(DUR_LEN as u16),
+        layout::Constraint::Length(DUR_LEN as u16),
+        polls_width.constraint(),
+        layout::Constraint::Percentage(100),
+    ];
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(widths)
+        .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+        .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+    frame.render_stateful_widget(table, area, table_state);
+}
+
 fn dur(styles: &view::Styles, dur: std::time::Duration) -> Span<'static> {
     const DUR_PRECISION: usize = 4;
     // TODO(eliza): can we not have to use `format!` to make a string here? is
@@ -0,0 +1,485 @@
+use crate::{
+    filter::{self, Subject, Value},
+    input,
+    state::{tasks::TaskState, State},
+    view::{
+        self, bold,
+        table::{self, TableList, TableListState},
+        DUR_LEN, DUR_PRECISION,
+    },
+};
+use regex::Regex;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    rc::{Rc, Weak},
+    time::{Duration, SystemTime},
+};
+use tui::{
+    layout,
+    style::{self, Color, Style},
+    text::Span,
+    widgets::{Cell, Row, Table},
+};
+
+/// Tasks grouped by where they were spawned (or by name or target), so that
+/// it's clear which call sites are responsible for a large number of tasks.
+///
+/// Like the warnings list, the groups are rebuilt from the tasks whenever the
+/// view is rendered.
+#[derive(Default)]
+pub(crate) struct TaskGroupsList {
+    groups: Vec<Rc<RefCell<TaskGroup>>>,
+    group_by: GroupBy,
+    table: TableListState<TaskGroupsTable>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TaskGroupsTable {}
+
+/// The aggregated stats of a group of tasks.
+#[derive(Debug, Default)]
+pub(crate) struct TaskGroup {
+    /// The location, name or target shared by the group's tasks, or `None`
+    /// for the tasks that don't have a name.
+    key: Option<String>,
+    tasks: u64,
+    running: u64,
+    idle: u64,
+    completed: u64,
+    busy: Duration,
+    idle_time: Duration,
+    polls: u64,
+    warnings: u64,
+}
+
+/// What tasks are grouped by.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum GroupBy {
+    Location,
+    Name,
+    Target,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(usize)]
+pub(crate) enum SortBy {
+    Group = 0,
+    Tasks = 1,
+    Running = 2,
+    Idle = 3,
+    Completed = 4,
+    Busy = 5,
+    MeanBusy = 6,
+    IdleTime = 7,
+    MeanIdle = 8,
+    Polls = 9,
+    Warns = 10,
+}
+
+// === impl TaskGroupsList ===
+
+impl TaskGroupsList {
+    /// Returns a filter expression matching the tasks in the selected group,
+    /// if a group is selected.
+    pub(crate) fn selected_filter(&self) -> Option<String> {
+        let group = self.table.selected_item().upgrade()?;
+        let group = group.borrow();
+        let field = self.group_by.field();
+        let filter = match group.key {
+            Some(ref key) => format!("{} == {}", field, filter::quote(key)),
+            // A field that a task doesn't have never matches a regex, so
+            // `!~` matches the tasks without one.
+            None => format!("{} !~ \"\"", field),
+        };
+        Some(filter)
+    }
+
+    pub(crate) fn update_input(&mut self, event: input::Event) {
+        if let input::Event::Key(input::KeyEvent {
+            code: input::KeyCode::Char('b'),
+            ..
+        }) = event
+        {
+            if !self.table.is_editing_filter() {
+                self.group_by = self.group_by.next();
+                self.table.table_state.select(None);
+                return;
+            }
+        }
+        self.table.update_input(event)
+    }
+
+    pub(crate) fn is_editing_filter(&self) -> bool {
+        self.table.is_editing_filter()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.groups.clear();
+        self.table.sorted_items.clear();
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &mut self,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &mut State,
+    ) {
+        let now = match state.last_updated_at() {
+            Some(now) => now,
+            // If we have never gotten an update yet, skip...
+            None => return,
+        };
+
+        let mut groups: HashMap<Option<String>, TaskGroup> = HashMap::new();
+        for task in state
+            .tasks_state()
+            .tasks()
+            .filter_map(|task| task.upgrade())
+        {
+            let task = task.borrow();
+            let key = match self.group_by {
+                GroupBy::Location => Some(task.location()),
+                GroupBy::Name => task.name(),
+                GroupBy::Target => Some(task.target()),
+            };
+            let group = groups
+                .entry(key.map(ToOwned::to_owned))
+                .or_insert_with(|| TaskGroup {
+                    key: key.map(ToOwned::to_owned),
+                    ..Default::default()
+                });
+            group.tasks += 1;
+            match task.state() {
+                TaskState::Running => group.running += 1,
+                TaskState::Idle => group.idle += 1,
+                TaskState::Completed => group.completed += 1,
+            }
+            group.busy += task.busy(now);
+            group.idle_time += task.idle(now);
+            group.polls += task.total_polls();
+            group.warnings += task.warnings().len() as u64;
+        }
+        self.groups = groups
+            .into_values()
+            .map(|group| Rc::new(RefCell::new(group)))
+            .collect();
+
+        // The groups are rebuilt anyway, so there's no need to check whether
+        // the filter has changed.
+        self.table.take_filter_changed();
+        self.table.sorted_items.clear();
+        self.table
+            .extend_filtered(self.groups.iter().map(Rc::downgrade), now);
+        self.table.render(styles, frame, area, state, self.group_by);
+    }
+}
+
+// === impl TaskGroup ===
+
+impl TaskGroup {
+    fn key(&self) -> &str {
+        self.key.as_deref().unwrap_or("<unnamed>")
+    }
+
+    fn mean_busy(&self) -> Duration {
+        self.busy / self.tasks.max(1) as u32
+    }
+
+    fn mean_idle(&self) -> Duration {
+        self.idle_time / self.tasks.max(1) as u32
+    }
+}
+
+impl Subject for TaskGroup {
+    fn field(&self, name: &str, _: SystemTime) -> Option<Value<'_>> {
+        let value = match name {
+            "group" => Value::str(self.key.as_deref()?),
+            "tasks" => Value::Number(self.tasks as f64),
+            "running" => Value::Number(self.running as f64),
+            "idle" => Value::Number(self.idle as f64),
+            "completed" => Value::Number(self.completed as f64),
+            "busy" => Value::Duration(self.busy),
+            "mean_busy" => Value::Duration(self.mean_busy()),
+            "idle_time" => Value::Duration(self.idle_time),
+            "mean_idle" => Value::Duration(self.mean_idle()),
+            "polls" => Value::Number(self.polls as f64),
+            "warnings" => Value::Number(self.warnings as f64),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+// === impl GroupBy ===
+
+impl GroupBy {
+    fn next(self) -> Self {
+        match self {
+            GroupBy::Location => GroupBy::Name,
+            GroupBy::Name => GroupBy::Target,
+            GroupBy::Target => GroupBy::Location,
+        }
+    }
+
+    /// Returns the task field that tasks are grouped by, for filtering.
+    fn field(self) -> &'static str {
+        match self {
+            GroupBy::Location => "location",
+            GroupBy::Name => "name",
+            GroupBy::Target => "target",
+        }
+    }
+}
+
+impl Default for GroupBy {
+    fn default() -> Self {
+        GroupBy::Location
+    }
+}
+
+// === impl TaskGroupsTable ===
+
+impl TableList for TaskGroupsTable {
+    type Row = TaskGroup;
+    type Sort = SortBy;
+    type Context = GroupBy;
+
+    const HEADER: &'static [&'static str] = &[
+        "Group",
+        "Tasks",
+        "Running",
+        "Idle",
+        "Done",
+        "Busy",
+        "Mean Busy",
+        "Idle Time",
+        "Mean Idle",
+        "Polls",
+        "Warns",
+    ];
+
+    fn matches(group: &TaskGroup, filter: &Regex) -> bool {
+        filter.is_match(group.key())
+    }
+
+    fn render<B: tui::backend::Backend>(
+        table_list_state: &mut TableListState<Self>,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        _: &mut State,
+        group_by: Self::Context,
+    ) {
+        table_list_state
+            .sort_by
+            .sort(&mut table_list_state.sorted_items);
+
+        let mut key_width = view::Width::new(Self::HEADER[0].len() as u16);
+        let mut count_widths = [
+            view::Width::new(Self::HEADER[1].len() as u16),
+            view::Width::new(Self::HEADER[2].len() as u16),
+            view::Width::new(Self::HEADER[3].len() as u16),
+            view::Width::new(Self::HEADER[4].len() as u16),
+        ];
+        let mut polls_width = view::Width::new(Self::HEADER[9].len() as u16);
+        let mut warns_width = view::Width::new(Self::HEADER[10].len() as u16);
+
+        let dur_cell = |dur: Duration| -> Cell<'static> {
+            Cell::from(styles.time_units(format!(
+                "{:>width$.prec$?}",
+                dur,
+                width = DUR_LEN,
+                prec = DUR_PRECISION,
+            )))
+        };
+
+        let rows = {
+            let key_width = &mut key_width;
+            let count_widths = &mut count_widths;
+            let polls_width = &mut polls_width;
+            let warns_width = &mut warns_width;
+            table_list_state
+                .sorted_items
+                .iter()
+                .filter_map(move |group| {
+                    let group = group.upgrade()?;
+                    let group = group.borrow();
+                    let counts = [group.tasks, group.running, group.idle, group.completed];
+                    let mut cells =
+                        vec![Cell::from(key_width.update_str(String::from(group.key())))];
+                    for (count, width) in counts.iter().zip(count_widths.iter_mut()) {
+                        cells.push(Cell::from(width.update_str(format!(
+                            "{:>width$}",
+                            count,
+                            width = width.chars() as usize
+                        ))));
+                    }
+                    cells.extend([
+                        dur_cell(group.busy),
+                        dur_cell(group.mean_busy()),
+                        dur_cell(group.idle_time),
+                        dur_cell(group.mean_idle()),
+                        Cell::from(polls_width.update_str(group.polls.to_string())),
+                    ]);
+                    let warns = if group.warnings > 0 {
+                        warns_width.update_len(group.warnings.to_string().len() + 2);
+                        Cell::from(tui::text::Spans::from(vec![
+                            styles.warning_narrow(),
+                            Span::from(group.warnings.to_string()),
+                        ]))
+                    } else {
+                        Cell::from("")
+                    };
+                    cells.push(warns);
+                    Some(Row::new(cells))
+                })
+        };
+
+        let (selected_style, header_style) = if let Some(cyan) = styles.color(Color::Cyan) {
+            (Style::default().fg(cyan), Style::default())
+        } else {
+            (
+                Style::default().remove_modifier(style::Modifier::REVERSED),
+                Style::default().add_modifier(style::Modifier::REVERSED),
+            )
+        };
+        let header_style = header_style.add_modifier(style::Modifier::BOLD);
+
+        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
+            let cell = Cell::from(value);
+            if idx == table_list_state.selected_column {
+                cell.style(selected_style)
+            } else {
+                cell
+            }
+        }))
+        .height(1)
+        .style(header_style);
+
+        let table = if table_list_state.sort_descending {
+            Table::new(rows)
+        } else {
+            Table::new(rows.rev())
+        };
+
+        let mut title = vec![
+            bold(format!(
+                "Tasks by {} ({}) ",
+                group_by.field(),
+                table_list_state.len()
+            )),
+            Span::raw("("),
+            bold("b"),
+            Span::raw(" = group by location, name or target)"),
+        ];
+        title.extend(table_list_state.filter_title());
+        let block = styles.border_block().title(title);
+
+        let layout = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0);
+
+        let controls = table::Controls::for_area(&area, styles);
+        let chunks = layout
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height),
+                    layout::Constraint::Max(area.height),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let controls_area = chunks[0];
+        let groups_area = chunks[1];
+
+        let widths = &[
+            key_width.constraint(),
+            count_widths[0].constraint(),
+            count_widths[1].constraint(),
+            count_widths[2].constraint(),
+            count_widths[3].constraint(),
+            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(DUR_LEN as u16),
+            polls_width.constraint(),
+            warns_width.constraint(),
+        ];
+
+        let table = table
+            .header(header)
+            .block(block)
+            .widths(widths)
+            .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+
+        frame.render_stateful_widget(table, groups_area, &mut table_list_state.table_state);
+        frame.render_widget(controls.paragraph, controls_area);
+    }
+}
+
+// === impl SortBy ===
+
+impl Default for SortBy {
+    fn default() -> Self {
+        Self::Tasks
+    }
+}
+
+impl SortBy {
+    pub fn sort(&self, groups: &mut [Weak<RefCell<TaskGroup>>]) {
+        // Groups are rebuilt on every render, so always break ties by key, so
+        // that rows don't jump around between renders.
+        fn by<K: Ord>(groups: &mut [Weak<RefCell<TaskGroup>>], f: impl Fn(&TaskGroup) -> K) {
+            groups.sort_unstable_by_key(|group| {
+                group.upgrade().map(|group| {
+                    let group = group.borrow();
+                    (f(&group), group.key.clone())
+                })
+            })
+        }
+        match self {
+            Self::Group => by(groups, |group| group.key.clone()),
+            Self::Tasks => by(groups, |group| group.tasks),
+            Self::Running => by(groups, |group| group.running),
+            Self::Idle => by(groups, |group| group.idle),
+            Self::Completed => by(groups, |group| group.completed),
+            Self::Busy => by(groups, |group| group.busy),
+            Self::MeanBusy => by(groups, TaskGroup::mean_busy),
+            Self::IdleTime => by(groups, |group| group.idle_time),
+            Self::MeanIdle => by(groups, TaskGroup::mean_idle),
+            Self::Polls => by(groups, |group| group.polls),
+            Self::Warns => by(groups, |group| group.warnings),
+        }
+    }
+}
+
+impl view::SortBy for SortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TryFrom<usize> for SortBy {
+    type Error = ();
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        match idx {
+            idx if idx == Self::Group as usize => Ok(Self::Group),
+            idx if idx == Self::Tasks as usize => Ok(Self::Tasks),
+            idx if idx == Self::Running as usize => Ok(Self::Running),
+            idx if idx == Self::Idle as usize => Ok(Self::Idle),
+            idx if idx == Self::Completed as usize => Ok(Self::Completed),
+            idx if idx == Self::Busy as usize => Ok(Self::Busy),
+            idx if idx == Self::MeanBusy as usize => Ok(Self::MeanBusy),
+            idx if idx == Self::IdleTime as usize => Ok(Self::IdleTime),
+            idx if idx == Self::MeanIdle as usize => Ok(Self::MeanIdle),
+            idx if idx == Self::Polls as usize => Ok(Self::Polls),
+            idx if idx == Self::Warns as usize => Ok(Self::Warns),
+            _ => Err(()),
+        }
+    }
+}
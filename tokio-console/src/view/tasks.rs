@@ -6,14 +6,15 @@ use crate::{
     view::{
         self, bold,
         table::{self, TableList, TableListState},
-        DUR_LEN, DUR_PRECISION,
+        warnings, DUR_LEN, DUR_PRECISION,
     },
 };
+use regex::Regex;
 use tui::{
     layout,
     style::{self, Color, Style},
-    text::{Span, Spans, Text},
-    widgets::{self, Cell, ListItem, Row, Table},
+    text::{Span, Spans},
+    widgets::{Cell, Row, Table},
 };
 
 #[derive(Debug, Default)]
@@ -25,10 +26,19 @@ impl TableList for TasksTable {
     type Context = ();
 
     const HEADER: &'static [&'static str] = &[
-        "Warn", "ID", "State", "Name", "Total", "Busy", "Idle", "Polls", "Target", "Location",
-        "Fields",
+        "Warn", "ID", "State", "Name", "Total", "Busy", "Idle", "Polls", "Polls/s", "Busy %",
+        "Wakes/s", "Target", "Location", "Fields",
     ];
 
+    fn matches(task: &Task, filter: &Regex) -> bool {
+        task.name()
+            .map(|name| filter.is_match(name))
+            .unwrap_or(false)
+            || filter.is_match(task.target())
+            || filter.is_match(task.location())
+            || table::fields_match(task.formatted_fields(), filter)
+    }
+
     fn render<B: tui::backend::Backend>(
         table_list_state: &mut TableListState<Self>,
         styles: &view::Styles,
@@ -45,9 +55,16 @@ impl TableList for TasksTable {
             return;
         };
 
-        table_list_state
-            .sorted_items
-            .extend(state.tasks_state_mut().take_new_tasks());
+        let tasks = state.tasks_state_mut();
+        if table_list_state.take_filter_changed() {
+            // Collect every task matching the new filter, which includes any
+            // new tasks.
+            tasks.take_new_tasks().for_each(drop);
+            table_list_state.sorted_items.clear();
+            table_list_state.extend_filtered(tasks.tasks(), now);
+        } else {
+            table_list_state.extend_filtered(tasks.take_new_tasks(), now);
+        }
 
         table_list_state
             .sort_by
@@ -67,8 +84,11 @@ impl TableList for TasksTable {
         let mut id_width = view::Width::new(Self::HEADER[1].len() as u16);
         let mut name_width = view::Width::new(Self::HEADER[3].len() as u16);
         let mut polls_width = view::Width::new(Self::HEADER[7].len() as u16);
-        let mut target_width = view::Width::new(Self::HEADER[8].len() as u16);
-        let mut location_width = view::Width::new(Self::HEADER[9].len() as u16);
+        let mut polls_per_sec_width = view::Width::new(Self::HEADER[8].len() as u16);
+        let busy_percent_len = Self::HEADER[9].len() as u16;
+        let mut wakes_per_sec_width = view::Width::new(Self::HEADER[10].len() as u16);
+        let mut target_width = view::Width::new(Self::HEADER[11].len() as u16);
+        let mut location_width = view::Width::new(Self::HEADER[12].len() as u16);
 
         let mut num_idle = 0;
         let mut num_running = 0;
@@ -78,6 +98,8 @@ impl TableList for TasksTable {
             let location_width = &mut location_width;
             let name_width = &mut name_width;
             let polls_width = &mut polls_width;
+            let polls_per_sec_width = &mut polls_per_sec_width;
+            let wakes_per_sec_width = &mut wakes_per_sec_width;
             let warn_width = &mut warn_width;
             let num_running = &mut num_running;
             let num_idle = &mut num_idle;
@@ -96,17 +118,7 @@ impl TableList for TasksTable {
                         TaskState::Idle => *num_idle += 1,
                         _ => {}
                     };
-                    let n_warnings = task.warnings().len();
-                    let warnings = if n_warnings > 0 {
-                        let n_warnings = n_warnings.to_string();
-                        warn_width.update_len(n_warnings.len() + 2); // add 2 for the warning icon + whitespace
-                        Cell::from(Spans::from(vec![
-                            styles.warning_narrow(),
-                            Span::from(n_warnings),
-                        ]))
-                    } else {
-                        Cell::from("")
-                    };
+                    let warnings = warnings::count_cell(styles, task.warnings(), warn_width);
 
                     let mut row = Row::new(vec![
                         warnings,
@@ -121,6 +133,21 @@ impl TableList for TasksTable {
                         dur_cell(task.busy(now)),
                         dur_cell(task.idle(now)),
                         Cell::from(polls_width.update_str(task.total_polls().to_string())),
+                        Cell::from(polls_per_sec_width.update_str(format!(
+                            "{:>width$.1}",
+                            task.polls_per_sec(),
+                            width = polls_per_sec_width.chars() as usize
+                        ))),
+                        Cell::from(format!(
+                            "{:>width$.1}",
+                            task.busy_percent(),
+                            width = busy_percent_len as usize
+                        )),
+                        Cell::from(wakes_per_sec_width.update_str(format!(
+                            "{:>width$.1}",
+                            task.wakes_per_sec(),
+                            width = wakes_per_sec_width.chars() as usize
+                        ))),
                         Cell::from(target_width.update_str(task.target()).to_owned()),
                         Cell::from(location_width.update_str(task.location().to_owned())),
                         Cell::from(Spans::from(
@@ -165,13 +192,15 @@ impl TableList for TasksTable {
             Table::new(rows.rev())
         };
 
-        let block = styles.border_block().title(vec![
+        let mut title = vec![
             bold(format!("Tasks ({}) ", table_list_state.len())),
             TaskState::Running.render(styles),
             Span::from(format!(" Running ({}) ", num_running)),
             TaskState::Idle.render(styles),
             Span::from(format!(" Idle ({})", num_idle)),
-        ]);
+        ];
+        title.extend(table_list_state.filter_title());
+        let block = styles.border_block().title(title);
 
         /* TODO: use this to adjust the max size of name and target columns...
         // How many characters wide are the fixed-length non-field columns?
@@ -184,17 +213,8 @@ impl TableList for TasksTable {
             + POLLS_LEN as u16
             + target_width.chars();
         */
-        let warnings = state
-            .tasks_state()
-            .warnings()
-            .map(|warning| {
-                ListItem::new(Text::from(Spans::from(vec![
-                    styles.warning_wide(),
-                    // TODO(eliza): it would be nice to handle singular vs plural...
-                    Span::from(format!("{} {}", warning.count(), warning.summary())),
-                ])))
-            })
-            .collect::<Vec<_>>();
+        let summaries = warnings::summaries(styles, state.tasks_state().warnings());
+        let linters = state.tasks_state().linters();
 
         let layout = layout::Layout::default()
             .direction(layout::Direction::Vertical)
@@ -202,7 +222,7 @@ impl TableList for TasksTable {
 
         let controls = table::Controls::for_area(&area, styles);
 
-        let (controls_area, tasks_area, warnings_area) = if warnings.is_empty() {
+        let (controls_area, tasks_area, warnings_area) = if linters.is_empty() {
             let chunks = layout
                 .constraints(
                     [
@@ -214,7 +234,7 @@ impl TableList for TasksTable {
                 .split(area);
             (chunks[0], chunks[1], None)
         } else {
-            let warnings_height = warnings.len() as u16 + 2;
+            let warnings_height = warnings::pane_height(&summaries);
             let chunks = layout
                 .constraints(
                     [
@@ -243,6 +263,9 @@ impl TableList for TasksTable {
             layout::Constraint::Length(DUR_LEN as u16),
             layout::Constraint::Length(DUR_LEN as u16),
             polls_width.constraint(),
+            polls_per_sec_width.constraint(),
+            layout::Constraint::Length(busy_percent_len),
+            wakes_per_sec_width.constraint(),
             target_width.constraint(),
             location_width.constraint(),
             fields_width,
@@ -259,10 +282,8 @@ impl TableList for TasksTable {
         frame.render_widget(controls.paragraph, controls_area);
 
         if let Some(area) = warnings_area {
-            let block = styles
-                .border_block()
-                .title(Spans::from(vec![bold("Warnings")]));
-            frame.render_widget(widgets::List::new(warnings).block(block), area);
+            let pane = warnings::pane(styles, summaries, warnings::lints(linters));
+            frame.render_widget(pane, area);
         }
 
         table_list_state
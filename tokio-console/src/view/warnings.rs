@@ -0,0 +1,512 @@
+//! Widgets for displaying the warnings detected by a set of linters, shared
+//! by the views of each kind of monitored entity, and the warnings view, which
+//! lists every active warning for every entity.
+use crate::{
+    filter::{Subject, Value},
+    input,
+    state::State,
+    view::{
+        self, bold,
+        table::{self, TableList, TableListState},
+        DUR_LEN, DUR_PRECISION,
+    },
+    warnings::{Lint, Linter, Severity},
+};
+use regex::Regex;
+use std::{
+    cell::RefCell,
+    convert::TryFrom,
+    rc::{Rc, Weak},
+    time::{Duration, SystemTime},
+};
+use tui::{
+    layout,
+    style::{self, Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Cell, List, ListItem, Row, Table},
+};
+
+/// The list of every active warning, for every task, resource and async op.
+///
+/// Unlike the tasks and resources, warnings aren't tracked individually by the
+/// console's state, so the rows of the table are rebuilt from the warnings of
+/// each entity whenever the view is rendered.
+#[derive(Default)]
+pub(crate) struct WarningsList {
+    warnings: Vec<Rc<RefCell<Warning>>>,
+    table: TableListState<WarningsTable>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct WarningsTable {}
+
+/// A warning for a single entity.
+#[derive(Debug)]
+pub(crate) struct Warning {
+    lint: Lint,
+    severity: Severity,
+    detected_at: SystemTime,
+    entity: Entity,
+    what: String,
+    location: String,
+    message: String,
+}
+
+/// The entity that a [`Warning`] is about.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Entity {
+    Task(u64),
+    Resource(u64),
+    AsyncOp { id: u64, resource_id: u64 },
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(usize)]
+pub(crate) enum SortBy {
+    Lint = 0,
+    Age = 1,
+    Entity = 2,
+    Location = 3,
+}
+
+/// Returns a table cell showing how many warnings an entity has, if any.
+///
+/// The icon is an error icon if any of the warnings is an error.
+pub(crate) fn count_cell<T>(
+    styles: &view::Styles,
+    warnings: &[Linter<T>],
+    width: &mut view::Width,
+) -> Cell<'static> {
+    if warnings.is_empty() {
+        return Cell::from("");
+    }
+    let icon = if warnings
+        .iter()
+        .any(|warning| warning.severity() == Severity::Error)
+    {
+        styles.error_narrow()
+    } else {
+        styles.warning_narrow()
+    };
+    let count = warnings.len().to_string();
+    width.update_len(count.len() + 2); // add 2 for the warning icon + whitespace
+    Cell::from(Spans::from(vec![icon, Span::from(count)]))
+}
+
+/// Returns a list item summarizing each warning that is currently active.
+pub(crate) fn summaries<'a, T: 'a>(
+    styles: &view::Styles,
+    warnings: impl Iterator<Item = &'a Linter<T>>,
+) -> Vec<ListItem<'static>> {
+    warnings
+        .map(|warning| {
+            ListItem::new(Text::from(Spans::from(vec![
+                styles.severity_wide(warning.severity()),
+                // TODO(eliza): it would be nice to handle singular vs plural...
+                Span::from(format!("{} {}", warning.count(), warning.summary())),
+            ])))
+        })
+        .collect()
+}
+
+/// Returns the height of a warnings pane listing `summaries`, including its
+/// borders.
+pub(crate) fn pane_height(summaries: &[ListItem<'_>]) -> u16 {
+    summaries.len().max(1) as u16 + 2
+}
+
+/// Returns a pane listing warning `summaries`, or a placeholder if there are
+/// none.
+///
+/// The pane's title lists the active `lints`, so that it's clear what is (and
+/// isn't) being checked for.
+pub(crate) fn pane<'a>(
+    styles: &'a view::Styles,
+    mut summaries: Vec<ListItem<'a>>,
+    lints: impl IntoIterator<Item = (Lint, Severity)>,
+) -> List<'a> {
+    if summaries.is_empty() {
+        summaries.push(ListItem::new(Span::styled(
+            "no warnings",
+            Style::default().add_modifier(style::Modifier::DIM),
+        )));
+    }
+
+    let mut title = vec![bold("Warnings"), Span::raw(" (lints: ")];
+    for (i, (lint, severity)) in lints.into_iter().enumerate() {
+        if i > 0 {
+            title.push(Span::raw(", "));
+        }
+        let style = match severity {
+            Severity::Warning => Style::default(),
+            Severity::Error => styles.fg(Color::LightRed),
+        };
+        title.push(Span::styled(lint.as_str(), style));
+    }
+    title.push(Span::raw(")"));
+
+    List::new(summaries).block(styles.border_block().title(Spans::from(title)))
+}
+
+/// Returns the lint and severity of each linter, for listing in the title
+/// of a [`pane`].
+pub(crate) fn lints<T>(linters: &[Linter<T>]) -> impl Iterator<Item = (Lint, Severity)> + '_ {
+    linters
+        .iter()
+        .map(|linter| (linter.lint(), linter.severity()))
+}
+
+// === impl WarningsList ===
+
+impl WarningsList {
+    /// Returns the entity of the selected warning, if any.
+    pub(crate) fn selected(&self) -> Option<Entity> {
+        self.table
+            .selected_item()
+            .upgrade()
+            .map(|warning| warning.borrow().entity)
+    }
+
+    pub(crate) fn update_input(&mut self, event: input::Event) {
+        self.table.update_input(event)
+    }
+
+    pub(crate) fn is_editing_filter(&self) -> bool {
+        self.table.is_editing_filter()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.warnings.clear();
+        self.table.sorted_items.clear();
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &mut self,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &mut State,
+    ) {
+        let now = match state.last_updated_at() {
+            Some(now) => now,
+            // If we have never gotten an update yet, skip...
+            None => return,
+        };
+
+        self.warnings.clear();
+        for task in state
+            .tasks_state()
+            .tasks()
+            .filter_map(|task| task.upgrade())
+        {
+            let task = task.borrow();
+            let what = match task.name() {
+                Some(name) => format!("task {} ({})", task.id(), name),
+                None => format!("task {}", task.id()),
+            };
+            self.warnings.extend(task.warnings().iter().map(|warning| {
+                Warning::new(
+                    warning,
+                    Entity::Task(task.id()),
+                    what.clone(),
+                    task.location(),
+                    warning.format(&task, now),
+                    now,
+                )
+            }));
+        }
+
+        let resources = state.resources_state();
+        for resource in resources.resources().filter_map(|res| res.upgrade()) {
+            let resource = resource.borrow();
+            let what = format!("resource {} ({})", resource.id(), resource.concrete_type());
+            self.warnings
+                .extend(resource.warnings().iter().map(|warning| {
+                    Warning::new(
+                        warning,
+                        Entity::Resource(resource.id()),
+                        what.clone(),
+                        resource.location(),
+                        warning.format(&resource, now),
+                        now,
+                    )
+                }));
+        }
+
+        for op in state
+            .async_ops_state()
+            .async_ops()
+            .filter_map(|op| op.upgrade())
+        {
+            let op = op.borrow();
+            // Async ops don't have locations of their own, so use the location
+            // of the resource they're operating on.
+            let location = resources
+                .resource(op.resource_id())
+                .and_then(|res| res.upgrade())
+                .map(|res| res.borrow().location().to_owned())
+                .unwrap_or_default();
+            let what = format!("async op {} ({})", op.id(), op.source());
+            let entity = Entity::AsyncOp {
+                id: op.id(),
+                resource_id: op.resource_id(),
+            };
+            self.warnings.extend(op.warnings().iter().map(|warning| {
+                Warning::new(
+                    warning,
+                    entity,
+                    what.clone(),
+                    &location,
+                    warning.format(&op, now),
+                    now,
+                )
+            }));
+        }
+
+        // The rows are rebuilt anyway, so there's no need to check whether the
+        // filter has changed.
+        self.table.take_filter_changed();
+        self.table.sorted_items.clear();
+        self.table
+            .extend_filtered(self.warnings.iter().map(Rc::downgrade), now);
+        self.table.render(styles, frame, area, state, ());
+    }
+}
+
+// === impl Warning ===
+
+impl Warning {
+    fn new<T>(
+        linter: &Linter<T>,
+        entity: Entity,
+        what: String,
+        location: &str,
+        message: String,
+        now: SystemTime,
+    ) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            lint: linter.lint(),
+            severity: linter.severity(),
+            detected_at: linter.detected_at().unwrap_or(now),
+            entity,
+            what,
+            location: location.to_owned(),
+            message,
+        }))
+    }
+
+    /// Returns how long the warning has been active.
+    fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.detected_at).unwrap_or_default()
+    }
+}
+
+impl Subject for Warning {
+    fn field(&self, name: &str, now: SystemTime) -> Option<Value<'_>> {
+        let value = match name {
+            "lint" => Value::str(self.lint.as_str()),
+            "severity" => Value::str(self.severity.as_str()),
+            "age" => Value::Duration(self.age(now)),
+            "entity" => Value::str(self.what.as_str()),
+            "location" => Value::str(self.location.as_str()),
+            "message" => Value::str(self.message.as_str()),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+// === impl WarningsTable ===
+
+impl TableList for WarningsTable {
+    type Row = Warning;
+    type Sort = SortBy;
+    type Context = ();
+
+    const HEADER: &'static [&'static str] = &["Lint", "Age", "Entity", "Location", "Warning"];
+
+    fn matches(warning: &Warning, filter: &Regex) -> bool {
+        filter.is_match(warning.lint.as_str())
+            || filter.is_match(&warning.what)
+            || filter.is_match(&warning.location)
+            || filter.is_match(&warning.message)
+    }
+
+    fn render<B: tui::backend::Backend>(
+        table_list_state: &mut TableListState<Self>,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &mut State,
+        _: Self::Context,
+    ) {
+        let now = if let Some(now) = state.last_updated_at() {
+            now
+        } else {
+            // If we have never gotten an update yet, skip...
+            return;
+        };
+
+        table_list_state
+            .sort_by
+            .sort(now, &mut table_list_state.sorted_items);
+
+        let mut lint_width = view::Width::new(Self::HEADER[0].len() as u16);
+        let mut what_width = view::Width::new(Self::HEADER[2].len() as u16);
+        let mut location_width = view::Width::new(Self::HEADER[3].len() as u16);
+
+        let rows = {
+            let lint_width = &mut lint_width;
+            let what_width = &mut what_width;
+            let location_width = &mut location_width;
+            table_list_state
+                .sorted_items
+                .iter()
+                .filter_map(move |warning| {
+                    let warning = warning.upgrade()?;
+                    let warning = warning.borrow();
+                    let icon = styles.severity_wide(warning.severity);
+                    lint_width.update_len(icon.width() + warning.lint.as_str().len());
+                    Some(Row::new(vec![
+                        Cell::from(Spans::from(vec![icon, Span::from(warning.lint.as_str())])),
+                        Cell::from(styles.time_units(format!(
+                            "{:>width$.prec$?}",
+                            warning.age(now),
+                            width = DUR_LEN,
+                            prec = DUR_PRECISION,
+                        ))),
+                        Cell::from(what_width.update_str(warning.what.clone())),
+                        Cell::from(location_width.update_str(warning.location.clone())),
+                        Cell::from(warning.message.clone()),
+                    ]))
+                })
+        };
+
+        let (selected_style, header_style) = if let Some(cyan) = styles.color(Color::Cyan) {
+            (Style::default().fg(cyan), Style::default())
+        } else {
+            (
+                Style::default().remove_modifier(style::Modifier::REVERSED),
+                Style::default().add_modifier(style::Modifier::REVERSED),
+            )
+        };
+        let header_style = header_style.add_modifier(style::Modifier::BOLD);
+
+        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
+            let cell = Cell::from(value);
+            if idx == table_list_state.selected_column {
+                cell.style(selected_style)
+            } else {
+                cell
+            }
+        }))
+        .height(1)
+        .style(header_style);
+
+        let table = if table_list_state.sort_descending {
+            Table::new(rows)
+        } else {
+            Table::new(rows.rev())
+        };
+
+        let mut title = vec![bold(format!("Warnings ({}) ", table_list_state.len()))];
+        title.extend(table_list_state.filter_title());
+        let block = styles.border_block().title(title);
+
+        let layout = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0);
+
+        let controls = table::Controls::for_area(&area, styles);
+        let chunks = layout
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height),
+                    layout::Constraint::Max(area.height),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let controls_area = chunks[0];
+        let warnings_area = chunks[1];
+
+        let widths = &[
+            lint_width.constraint(),
+            layout::Constraint::Length(DUR_LEN as u16),
+            what_width.constraint(),
+            location_width.constraint(),
+            layout::Constraint::Percentage(100),
+        ];
+
+        let table = table
+            .header(header)
+            .block(block)
+            .widths(widths)
+            .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+
+        frame.render_stateful_widget(table, warnings_area, &mut table_list_state.table_state);
+        frame.render_widget(controls.paragraph, controls_area);
+    }
+}
+
+// === impl SortBy ===
+
+impl Default for SortBy {
+    fn default() -> Self {
+        Self::Lint
+    }
+}
+
+impl SortBy {
+    pub fn sort(&self, now: SystemTime, warnings: &mut [Weak<RefCell<Warning>>]) {
+        // Warnings are rebuilt on every render, so always break ties by
+        // entity, so that rows don't jump around between renders.
+        match self {
+            Self::Lint => warnings.sort_unstable_by_key(|warning| {
+                warning.upgrade().map(|w| {
+                    let w = w.borrow();
+                    (w.severity, w.lint, w.entity)
+                })
+            }),
+            Self::Age => warnings.sort_unstable_by_key(|warning| {
+                warning.upgrade().map(|w| {
+                    let w = w.borrow();
+                    (w.age(now), w.lint, w.entity)
+                })
+            }),
+            Self::Entity => warnings.sort_unstable_by_key(|warning| {
+                warning.upgrade().map(|w| {
+                    let w = w.borrow();
+                    (w.entity, w.lint)
+                })
+            }),
+            Self::Location => warnings.sort_unstable_by_key(|warning| {
+                warning.upgrade().map(|w| {
+                    let w = w.borrow();
+                    (w.location.clone(), w.entity, w.lint)
+                })
+            }),
+        }
+    }
+}
+
+impl view::SortBy for SortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TryFrom<usize> for SortBy {
+    type Error = ();
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        match idx {
+            idx if idx == Self::Lint as usize => Ok(Self::Lint),
+            idx if idx == Self::Age as usize => Ok(Self::Age),
+            idx if idx == Self::Entity as usize => Ok(Self::Entity),
+            idx if idx == Self::Location as usize => Ok(Self::Location),
+            _ => Err(()),
+        }
+    }
+}
@@ -1,5 +1,17 @@
-use crate::state::tasks::Task;
-use std::{fmt::Debug, rc::Rc};
+use crate::{
+    filter::{Expr, Subject},
+    state::{
+        async_ops::AsyncOp,
+        resources::{Resource, TypeVisibility},
+        tasks::Task,
+    },
+};
+use std::{
+    fmt::{self, Debug},
+    rc::Rc,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
 /// A warning for a particular type of monitored entity (e.g. task or resource).
 ///
@@ -7,8 +19,11 @@ use std::{fmt::Debug, rc::Rc};
 /// generating a warning message describing it. The [`Linter`] type wraps an
 /// instance of this trait to track active instances of the warning.
 pub trait Warn<T>: Debug {
-    /// Returns `true` if the warning applies to `val`.
-    fn check(&self, val: &T) -> bool;
+    /// Returns `true` if the warning applies to `val`, as of `now`.
+    ///
+    /// `now` is the time of the most recent update from the remote process,
+    /// for warnings that depend on how long something has been the case.
+    fn check(&self, val: &T, now: SystemTime) -> bool;
 
     /// Formats a description of the warning detected for a *specific* `val`.
     ///
@@ -20,7 +35,7 @@ pub trait Warn<T>: Debug {
     /// for the [`SelfWakePercent`] warning, this returns a string like:
     ///
     /// > "This task has woken itself for more than 50% of its total wakeups (86%)"
-    fn format(&self, val: &T) -> String;
+    fn format(&self, val: &T, now: SystemTime) -> String;
 
     /// Returns a string summarizing the warning *in general*, suitable for
     /// displaying in a list of all detected warnings.
@@ -47,14 +62,52 @@ pub trait Warn<T>: Debug {
 }
 
 #[derive(Debug)]
-pub(crate) struct Linter<T>(Rc<dyn Warn<T>>);
+pub(crate) struct Linter<T> {
+    warning: Rc<dyn Warn<T>>,
+    lint: Lint,
+    severity: Severity,
+    /// When the warning was first detected for the entity holding this
+    /// instance of the linter, or `None` for the linter itself.
+    detected_at: Option<SystemTime>,
+}
+
+/// Identifies a lint, such as when configuring it on the command line or in
+/// the config file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) enum Lint {
+    SelfWakePercent,
+    LostWaker,
+    LongPoll,
+    NeverPolled,
+    Stuck,
+    ExhaustedPermits,
+    LongLock,
+    SlowAsyncOp,
+    /// A lint defined by a filter expression in the config file.
+    ///
+    /// Custom lints are only defined once, when the config file is read, so
+    /// their names are leaked to keep `Lint` `Copy`.
+    Custom(&'static str),
+}
+
+/// How severe it is for a lint to fire.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Severity {
+    Warning,
+    Error,
+}
 
 impl<T> Linter<T> {
-    pub(crate) fn new<W>(warning: W) -> Self
+    pub(crate) fn new<W>(lint: Lint, severity: Severity, warning: W) -> Self
     where
         W: Warn<T> + 'static,
     {
-        Self(Rc::new(warning))
+        Self {
+            warning: Rc::new(warning),
+            lint,
+            severity,
+            detected_at: None,
+        }
     }
 
     /// Checks if the warning applies to a particular entity, returning a clone
@@ -63,30 +116,114 @@ impl<T> Linter<T> {
     /// The cloned instance of `Self` should be held by the entity that
     /// generated the warning, so that it can be formatted. Holding the clone of
     /// `Self` will increment the warning count for that entity.
-    pub(crate) fn check(&self, val: &T) -> Option<Self> {
-        if self.0.check(val) {
-            Some(Self(self.0.clone()))
-        } else {
-            None
+    ///
+    /// If the entity already had the warning, it should be passed in
+    /// `previous`, so that the time at which it was first detected is kept.
+    pub(crate) fn check(&self, val: &T, now: SystemTime, previous: &[Self]) -> Option<Self> {
+        if !self.warning.check(val, now) {
+            return None;
         }
+        let detected_at = previous
+            .iter()
+            .find(|warning| warning.lint == self.lint)
+            .and_then(|warning| warning.detected_at)
+            .unwrap_or(now);
+        Some(Self {
+            warning: self.warning.clone(),
+            lint: self.lint,
+            severity: self.severity,
+            detected_at: Some(detected_at),
+        })
     }
 
     /// Returns the number of monitored entities that currently have this warning.
     pub(crate) fn count(&self) -> usize {
-        Rc::strong_count(&self.0) - 1
+        Rc::strong_count(&self.warning) - 1
     }
 
-    pub(crate) fn format(&self, val: &T) -> String {
+    pub(crate) fn format(&self, val: &T, now: SystemTime) -> String {
         debug_assert!(
-            self.0.check(val),
+            self.warning.check(val, now),
             "tried to format a warning for a {} that did not have that warning!",
             std::any::type_name::<T>()
         );
-        self.0.format(val)
+        self.warning.format(val, now)
     }
 
     pub(crate) fn summary(&self) -> &str {
-        self.0.summary()
+        self.warning.summary()
+    }
+
+    pub(crate) fn lint(&self) -> Lint {
+        self.lint
+    }
+
+    pub(crate) fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Returns when this warning was first detected for the entity that has
+    /// it.
+    pub(crate) fn detected_at(&self) -> Option<SystemTime> {
+        self.detected_at
+    }
+}
+
+// === impl Lint ===
+
+impl Lint {
+    pub(crate) const ALL: &'static [Lint] = &[
+        Lint::SelfWakePercent,
+        Lint::LostWaker,
+        Lint::LongPoll,
+        Lint::NeverPolled,
+        Lint::Stuck,
+        Lint::ExhaustedPermits,
+        Lint::LongLock,
+        Lint::SlowAsyncOp,
+    ];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Lint::SelfWakePercent => "self-wake-percent",
+            Lint::LostWaker => "lost-waker",
+            Lint::LongPoll => "long-poll",
+            Lint::NeverPolled => "never-polled",
+            Lint::Stuck => "stuck",
+            Lint::ExhaustedPermits => "exhausted-permits",
+            Lint::LongLock => "long-lock",
+            Lint::SlowAsyncOp => "slow-async-op",
+            Lint::Custom(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.as_str() == s)
+            .ok_or_else(|| format!("unknown lint `{}`", s))
+    }
+}
+
+// === impl Severity ===
+
+impl Severity {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
     }
 }
 
@@ -120,12 +257,12 @@ impl Warn<Task> for SelfWakePercent {
         self.description.as_str()
     }
 
-    fn check(&self, task: &Task) -> bool {
+    fn check(&self, task: &Task, _: SystemTime) -> bool {
         let self_wakes = task.self_wake_percent();
         self_wakes > self.min_percent
     }
 
-    fn format(&self, task: &Task) -> String {
+    fn format(&self, task: &Task, _: SystemTime) -> String {
         let self_wakes = task.self_wake_percent();
         format!(
             "This task has woken itself for more than {}% of its total wakeups ({}%)",
@@ -142,11 +279,360 @@ impl Warn<Task> for LostWaker {
         "tasks have lost their waker"
     }
 
-    fn check(&self, task: &Task) -> bool {
+    fn check(&self, task: &Task, _: SystemTime) -> bool {
         !task.is_completed() && task.waker_count() == 0 && !task.is_running() && !task.is_awakened()
     }
 
-    fn format(&self, _: &Task) -> String {
+    fn format(&self, _: &Task, _: SystemTime) -> String {
         "This task has lost its waker, and will never be woken again.".into()
     }
 }
+
+/// Warns when a task blocks its worker thread by taking a long time to
+/// complete a single poll, such as by making a blocking call.
+#[derive(Clone, Debug)]
+pub(crate) struct LongPoll {
+    min_duration: Duration,
+    description: String,
+}
+
+impl LongPoll {
+    pub(crate) const DEFAULT_DURATION: Duration = Duration::from_millis(10);
+    pub(crate) fn new(min_duration: Duration) -> Self {
+        Self {
+            min_duration,
+            description: format!(
+                "tasks have blocked a worker thread for over {:?} in a single poll",
+                min_duration
+            ),
+        }
+    }
+}
+
+impl Default for LongPoll {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+impl Warn<Task> for LongPoll {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, _: SystemTime) -> bool {
+        // Blocking tasks run on their own threads, so they are expected to
+        // block for as long as they like.
+        !task.is_blocking() && task.longest_poll() > self.min_duration
+    }
+
+    fn format(&self, task: &Task, _: SystemTime) -> String {
+        format!(
+            "This task has blocked its worker thread for {:.2?} in a single poll, \
+            starving other tasks on that thread (more than {:?})",
+            task.longest_poll(),
+            self.min_duration
+        )
+    }
+}
+
+/// Warns when a task has been spawned, but the runtime has not polled it for a
+/// long time, such as when every worker thread is busy.
+#[derive(Clone, Debug)]
+pub(crate) struct NeverPolled {
+    min_duration: Duration,
+    description: String,
+}
+
+impl NeverPolled {
+    pub(crate) const DEFAULT_DURATION: Duration = Duration::from_secs(1);
+    pub(crate) fn new(min_duration: Duration) -> Self {
+        Self {
+            min_duration,
+            description: format!(
+                "tasks have not been polled for over {:?} after being spawned",
+                min_duration
+            ),
+        }
+    }
+}
+
+impl Default for NeverPolled {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+impl Warn<Task> for NeverPolled {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        !task.is_completed() && task.total_polls() == 0 && task.total(now) > self.min_duration
+    }
+
+    fn format(&self, task: &Task, now: SystemTime) -> String {
+        format!(
+            "This task was spawned {:.0?} ago, but has never been polled (more than {:?})",
+            task.total(now),
+            self.min_duration
+        )
+    }
+}
+
+/// Warns when a task has been idle for a long time, even though it still has
+/// wakers and other tasks have since used a resource it is waiting on.
+///
+/// This may mean that the resource forgot to wake the task, or that other
+/// tasks are starving it of the resource.
+#[derive(Clone, Debug)]
+pub(crate) struct Stuck {
+    min_duration: Duration,
+    description: String,
+}
+
+impl Stuck {
+    pub(crate) const DEFAULT_DURATION: Duration = Duration::from_secs(60);
+    pub(crate) fn new(min_duration: Duration) -> Self {
+        Self {
+            min_duration,
+            description: format!(
+                "tasks have waited over {:?} on resources that other tasks are using",
+                min_duration
+            ),
+        }
+    }
+}
+
+impl Default for Stuck {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+impl Warn<Task> for Stuck {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        !task.is_completed()
+            && !task.is_running()
+            && !task.is_awakened()
+            && task.waker_count() > 0
+            && task.active_resource().is_some()
+            && task
+                .since_poll(now)
+                .map(|idle| idle > self.min_duration)
+                .unwrap_or(false)
+    }
+
+    fn format(&self, task: &Task, now: SystemTime) -> String {
+        let idle = task.since_poll(now).unwrap_or_default();
+        match task.active_resource() {
+            Some(resource) => format!(
+                "This task has not been woken for {:.0?}, although other tasks have used \
+                resource {} ({}) that it is waiting on (more than {:?})",
+                idle, resource.id, resource.concrete_type, self.min_duration
+            ),
+            None => format!(
+                "This task has not been woken for {:.0?} (more than {:?})",
+                idle, self.min_duration
+            ),
+        }
+    }
+}
+
+/// Warns when a semaphore has had no permits available for a long time,
+/// during which none have been released.
+#[derive(Clone, Debug)]
+pub(crate) struct ExhaustedPermits {
+    min_duration: Duration,
+    description: String,
+}
+
+impl ExhaustedPermits {
+    pub(crate) const DEFAULT_DURATION: Duration = Duration::from_secs(10);
+    pub(crate) fn new(min_duration: Duration) -> Self {
+        Self {
+            min_duration,
+            description: format!(
+                "resources have had no permits available for over {:?}",
+                min_duration
+            ),
+        }
+    }
+}
+
+impl Default for ExhaustedPermits {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+impl Warn<Resource> for ExhaustedPermits {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, resource: &Resource, now: SystemTime) -> bool {
+        // Internal semaphores implement other resources, such as a `Mutex`,
+        // which are checked by their own lints.
+        !resource.dropped()
+            && resource.type_visibility() == TypeVisibility::Public
+            && resource
+                .exhausted_for(now)
+                .map(|exhausted| exhausted > self.min_duration)
+                .unwrap_or(false)
+    }
+
+    fn format(&self, resource: &Resource, now: SystemTime) -> String {
+        format!(
+            "This resource has had no permits available for {:.0?}, and none have been \
+            released (more than {:?})",
+            resource.exhausted_for(now).unwrap_or_default(),
+            self.min_duration
+        )
+    }
+}
+
+/// Warns when a lock has been held for a long time without being released.
+#[derive(Clone, Debug)]
+pub(crate) struct LongLock {
+    min_duration: Duration,
+    description: String,
+}
+
+impl LongLock {
+    pub(crate) const DEFAULT_DURATION: Duration = Duration::from_secs(10);
+    pub(crate) fn new(min_duration: Duration) -> Self {
+        Self {
+            min_duration,
+            description: format!("resources have been locked for over {:?}", min_duration),
+        }
+    }
+}
+
+impl Default for LongLock {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+impl Warn<Resource> for LongLock {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, resource: &Resource, now: SystemTime) -> bool {
+        !resource.dropped()
+            && resource
+                .locked_for(now)
+                .map(|locked| locked > self.min_duration)
+                .unwrap_or(false)
+    }
+
+    fn format(&self, resource: &Resource, now: SystemTime) -> String {
+        format!(
+            "This resource has been locked for {:.0?} without being unlocked (more than {:?})",
+            resource.locked_for(now).unwrap_or_default(),
+            self.min_duration
+        )
+    }
+}
+
+/// Warns when an async op has been pending for much longer than the other
+/// async ops on the same resource.
+#[derive(Clone, Debug)]
+pub(crate) struct SlowAsyncOp {
+    min_ratio: u32,
+    description: String,
+}
+
+impl SlowAsyncOp {
+    pub(crate) const DEFAULT_RATIO: u32 = 10;
+    /// Async ops that have been pending for less than this are never
+    /// reported, however quick their siblings are.
+    const MIN_DURATION: Duration = Duration::from_secs(1);
+    /// The number of siblings an async op must have to be compared with
+    /// them.
+    const MIN_SIBLINGS: usize = 2;
+
+    pub(crate) fn new(min_ratio: u32) -> Self {
+        Self {
+            min_ratio,
+            description: format!(
+                "async ops have been pending over {}x longer than others on their resource",
+                min_ratio
+            ),
+        }
+    }
+}
+
+impl Default for SlowAsyncOp {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_RATIO)
+    }
+}
+
+impl Warn<AsyncOp> for SlowAsyncOp {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, async_op: &AsyncOp, now: SystemTime) -> bool {
+        let total = async_op.total(now);
+        !async_op.dropped()
+            && total > Self::MIN_DURATION
+            && async_op.siblings() >= Self::MIN_SIBLINGS
+            && async_op
+                .sibling_median()
+                .map(|median| total > median * self.min_ratio)
+                .unwrap_or(false)
+    }
+
+    fn format(&self, async_op: &AsyncOp, now: SystemTime) -> String {
+        format!(
+            "This async op has been pending for {:.0?}, over {}x the median of the other {} \
+            async ops on its resource ({:.2?})",
+            async_op.total(now),
+            self.min_ratio,
+            async_op.siblings(),
+            async_op.sibling_median().unwrap_or_default()
+        )
+    }
+}
+
+/// A lint defined by a filter expression in the config file, which fires for
+/// every entity matching the expression.
+#[derive(Clone, Debug)]
+pub(crate) struct Custom {
+    filter: Expr,
+    message: String,
+    description: String,
+}
+
+impl Custom {
+    pub(crate) fn new(filter: Expr, message: String, description: String) -> Self {
+        Self {
+            filter,
+            message,
+            description,
+        }
+    }
+}
+
+impl<T: Subject> Warn<T> for Custom {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, val: &T, now: SystemTime) -> bool {
+        self.filter.matches(val, now)
+    }
+
+    fn format(&self, _: &T, _: SystemTime) -> String {
+        self.message.clone()
+    }
+}